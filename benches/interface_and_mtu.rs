@@ -0,0 +1,52 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![allow(missing_docs, clippy::unwrap_used)]
+
+use std::{
+    hint::black_box,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use mtu::{interface_and_mtu, MtuResolver};
+
+fn interface_and_mtu_loopback(c: &mut Criterion) {
+    for remote in [
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(Ipv6Addr::LOCALHOST),
+    ] {
+        c.bench_function(&format!("interface_and_mtu({remote})"), |b| {
+            b.iter(|| interface_and_mtu(black_box(remote)).unwrap());
+        });
+    }
+}
+
+/// The number of queries per iteration of [`resolver_loopback`], so that per-query allocations
+/// dominate over the setup of the iteration.
+const RESOLVER_QUERIES: usize = 10_000;
+
+fn resolver_loopback(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("MtuResolver::resolve x{RESOLVER_QUERIES}"));
+    group.sample_size(10);
+    for remote in [
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(Ipv6Addr::LOCALHOST),
+    ] {
+        let mut resolver = MtuResolver::new().unwrap();
+        group.bench_function(remote.to_string(), |b| {
+            b.iter(|| {
+                for _ in 0..RESOLVER_QUERIES {
+                    resolver.resolve(black_box(remote)).unwrap();
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, interface_and_mtu_loopback, resolver_loopback);
+criterion_main!(benches);
@@ -0,0 +1,72 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Generated by bindgen from the allowlist in `build.rs`. Do not edit, but regenerate on Linux
+// with `MTU_UPDATE_VENDORED_BINDINGS=1 cargo build`.
+
+pub type __u8 = ::std::os::raw::c_uchar;
+pub type __u16 = ::std::os::raw::c_ushort;
+pub type __u32 = ::std::os::raw::c_uint;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct nlmsghdr {
+    pub nlmsg_len: __u32,
+    pub nlmsg_type: __u16,
+    pub nlmsg_flags: __u16,
+    pub nlmsg_seq: __u32,
+    pub nlmsg_pid: __u32,
+}
+pub const RTMGRP_LINK: u32 = 1;
+pub const RTMGRP_IPV4_ROUTE: u32 = 64;
+pub const RTMGRP_IPV6_ROUTE: u32 = 1024;
+pub const RTM_F_FIB_MATCH: u32 = 8192;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rtattr {
+    pub rta_len: ::std::os::raw::c_ushort,
+    pub rta_type: ::std::os::raw::c_ushort,
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rtmsg {
+    pub rtm_family: ::std::os::raw::c_uchar,
+    pub rtm_dst_len: ::std::os::raw::c_uchar,
+    pub rtm_src_len: ::std::os::raw::c_uchar,
+    pub rtm_tos: ::std::os::raw::c_uchar,
+    pub rtm_table: ::std::os::raw::c_uchar,
+    pub rtm_protocol: ::std::os::raw::c_uchar,
+    pub rtm_scope: ::std::os::raw::c_uchar,
+    pub rtm_type: ::std::os::raw::c_uchar,
+    pub rtm_flags: ::std::os::raw::c_uint,
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rtnexthop {
+    pub rtnh_len: ::std::os::raw::c_ushort,
+    pub rtnh_flags: ::std::os::raw::c_uchar,
+    pub rtnh_hops: ::std::os::raw::c_uchar,
+    pub rtnh_ifindex: ::std::os::raw::c_int,
+}
+pub const RTAX_MTU: ::std::os::raw::c_uint = 2;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ifinfomsg {
+    pub ifi_family: ::std::os::raw::c_uchar,
+    pub __ifi_pad: ::std::os::raw::c_uchar,
+    pub ifi_type: ::std::os::raw::c_ushort,
+    pub ifi_index: ::std::os::raw::c_int,
+    pub ifi_flags: ::std::os::raw::c_uint,
+    pub ifi_change: ::std::os::raw::c_uint,
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ifaddrmsg {
+    pub ifa_family: __u8,
+    pub ifa_prefixlen: __u8,
+    pub ifa_flags: __u8,
+    pub ifa_scope: __u8,
+    pub ifa_index: __u32,
+}
@@ -0,0 +1,135 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Generated by bindgen from the allowlist in `build.rs`. Do not edit, but regenerate on macOS
+// with `MTU_UPDATE_VENDORED_BINDINGS=1 cargo build`.
+
+pub type __int32_t = ::std::os::raw::c_int;
+pub type __darwin_pid_t = __int32_t;
+pub type u_int32_t = ::std::os::raw::c_uint;
+pub type pid_t = __darwin_pid_t;
+pub type u_char = ::std::os::raw::c_uchar;
+pub type u_short = ::std::os::raw::c_ushort;
+pub const RTF_GATEWAY: u32 = 2;
+pub const RTF_REJECT: u32 = 8;
+pub const RTF_BLACKHOLE: u32 = 4096;
+pub const RTM_VERSION: u32 = 5;
+pub const RTM_ADD: u32 = 1;
+pub const RTM_DELETE: u32 = 2;
+pub const RTM_CHANGE: u32 = 3;
+pub const RTM_GET: u32 = 4;
+pub const RTM_NEWADDR: u32 = 12;
+pub const RTM_DELADDR: u32 = 13;
+pub const RTM_IFINFO: u32 = 14;
+pub const RTA_DST: u32 = 1;
+pub const RTA_GATEWAY: u32 = 2;
+pub const RTA_NETMASK: u32 = 4;
+pub const RTA_IFP: u32 = 16;
+pub const RTA_IFA: u32 = 32;
+pub const RTAX_DST: u32 = 0;
+pub const RTAX_GATEWAY: u32 = 1;
+pub const RTAX_NETMASK: u32 = 2;
+pub const RTAX_MAX: u32 = 8;
+pub const IFT_ETHER: u32 = 6;
+pub const IFT_PPP: u32 = 23;
+pub const IFT_LOOP: u32 = 24;
+pub const IFT_GIF: u32 = 55;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct timeval32 {
+    pub tv_sec: __int32_t,
+    pub tv_usec: __int32_t,
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct if_data {
+    pub ifi_type: u_char,
+    pub ifi_typelen: u_char,
+    pub ifi_physical: u_char,
+    pub ifi_addrlen: u_char,
+    pub ifi_hdrlen: u_char,
+    pub ifi_recvquota: u_char,
+    pub ifi_xmitquota: u_char,
+    pub ifi_unused1: u_char,
+    pub ifi_mtu: u_int32_t,
+    pub ifi_metric: u_int32_t,
+    pub ifi_baudrate: u_int32_t,
+    pub ifi_ipackets: u_int32_t,
+    pub ifi_ierrors: u_int32_t,
+    pub ifi_opackets: u_int32_t,
+    pub ifi_oerrors: u_int32_t,
+    pub ifi_collisions: u_int32_t,
+    pub ifi_ibytes: u_int32_t,
+    pub ifi_obytes: u_int32_t,
+    pub ifi_imcasts: u_int32_t,
+    pub ifi_omcasts: u_int32_t,
+    pub ifi_iqdrops: u_int32_t,
+    pub ifi_noproto: u_int32_t,
+    pub ifi_recvtiming: u_int32_t,
+    pub ifi_xmittiming: u_int32_t,
+    pub ifi_lastchange: timeval32,
+    pub ifi_unused2: u_int32_t,
+    pub ifi_hwassist: u_int32_t,
+    pub ifi_reserved1: u_int32_t,
+    pub ifi_reserved2: u_int32_t,
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rt_metrics {
+    pub rmx_locks: u_int32_t,
+    pub rmx_mtu: u_int32_t,
+    pub rmx_hopcount: u_int32_t,
+    pub rmx_expire: i32,
+    pub rmx_recvpipe: u_int32_t,
+    pub rmx_sendpipe: u_int32_t,
+    pub rmx_ssthresh: u_int32_t,
+    pub rmx_rtt: u_int32_t,
+    pub rmx_rttvar: u_int32_t,
+    pub rmx_pksent: u_int32_t,
+    pub rmx_state: u_int32_t,
+    pub rmx_filler: [u_int32_t; 3usize],
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rt_msghdr {
+    pub rtm_msglen: u_short,
+    pub rtm_version: u_char,
+    pub rtm_type: u_char,
+    pub rtm_index: u_short,
+    pub __bindgen_padding_0: [u8; 2usize],
+    pub rtm_flags: ::std::os::raw::c_int,
+    pub rtm_addrs: ::std::os::raw::c_int,
+    pub rtm_pid: pid_t,
+    pub rtm_seq: ::std::os::raw::c_int,
+    pub rtm_errno: ::std::os::raw::c_int,
+    pub rtm_use: ::std::os::raw::c_int,
+    pub rtm_inits: u_int32_t,
+    pub rtm_rmx: rt_metrics,
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct if_msghdr {
+    pub ifm_msglen: ::std::os::raw::c_ushort,
+    pub ifm_version: ::std::os::raw::c_uchar,
+    pub ifm_type: ::std::os::raw::c_uchar,
+    pub ifm_addrs: ::std::os::raw::c_int,
+    pub ifm_flags: ::std::os::raw::c_int,
+    pub ifm_index: ::std::os::raw::c_ushort,
+    pub __bindgen_padding_0: [u8; 2usize],
+    pub ifm_data: if_data,
+}
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ifa_msghdr {
+    pub ifam_msglen: ::std::os::raw::c_ushort,
+    pub ifam_version: ::std::os::raw::c_uchar,
+    pub ifam_type: ::std::os::raw::c_uchar,
+    pub ifam_addrs: ::std::os::raw::c_int,
+    pub ifam_flags: ::std::os::raw::c_int,
+    pub ifam_index: ::std::os::raw::c_ushort,
+    pub __bindgen_padding_0: [u8; 2usize],
+    pub ifam_metric: ::std::os::raw::c_int,
+}
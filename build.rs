@@ -8,9 +8,10 @@
 
 use std::env;
 
+#[cfg(feature = "bindgen")]
 const BINDINGS: &str = "bindings.rs";
 
-#[cfg(feature = "gecko")]
+#[cfg(all(feature = "bindgen", feature = "gecko"))]
 fn clang_args() -> Vec<String> {
     use mozbuild::TOPOBJDIR;
 
@@ -36,11 +37,103 @@ fn clang_args() -> Vec<String> {
     flags
 }
 
-#[cfg(not(feature = "gecko"))]
+#[cfg(all(feature = "bindgen", not(feature = "gecko")))]
 const fn clang_args() -> Vec<String> {
     Vec::new()
 }
 
+/// The environment variables that may point to the Android NDK, in order of preference.
+#[cfg(feature = "bindgen")]
+const ANDROID_NDK_VARS: [&str; 3] = ["ANDROID_NDK_HOME", "ANDROID_NDK_ROOT", "ANDROID_NDK"];
+
+/// Return the clang arguments to find the headers in the sysroot of the Android NDK. The `asm/`
+/// headers that `linux/rtnetlink.h` includes are in a subdirectory named after the target.
+///
+/// Without an NDK, this returns no arguments, and the sysroot needs to be passed via
+/// `BINDGEN_EXTRA_CLANG_ARGS` instead.
+#[cfg(feature = "bindgen")]
+fn android_clang_args() -> Vec<String> {
+    for var in ANDROID_NDK_VARS {
+        println!("cargo:rerun-if-env-changed={var}");
+    }
+    let Some(ndk) = ANDROID_NDK_VARS.iter().find_map(env::var_os) else {
+        return Vec::new();
+    };
+    // The NDK only ships x86_64 host toolchains, which also run on Apple silicon.
+    let host = match env::consts::OS {
+        "macos" => "darwin-x86_64",
+        "windows" => "windows-x86_64",
+        _ => "linux-x86_64",
+    };
+    let sysroot = std::path::Path::new(&ndk)
+        .join("toolchains/llvm/prebuilt")
+        .join(host)
+        .join("sysroot");
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").expect("CARGO_CFG_TARGET_ARCH was not set");
+    let triple = match target_arch.as_str() {
+        "arm" => "arm-linux-androideabi".to_string(),
+        "x86" => "i686-linux-android".to_string(),
+        arch => format!("{arch}-linux-android"),
+    };
+    vec![
+        format!("--sysroot={}", sysroot.display()),
+        format!(
+            "-isystem{}",
+            sysroot.join("usr/include").join(triple).display()
+        ),
+    ]
+}
+
+/// The environment variable that makes the build script also write the bindings it generates to
+/// `bindings/`, to update the checked-in bindings that the `vendored-bindings` feature uses. This
+/// only works with that feature disabled, so that bindgen runs.
+///
+/// The netlink structs only have fixed-width fields, and macOS only supports 64-bit architectures,
+/// which share the routing socket layouts, so the bindings generated for any Linux or macOS target
+/// are valid for all of their architectures. Android uses the Linux bindings.
+#[cfg(feature = "bindgen")]
+const UPDATE_VENDORED_BINDINGS: &str = "MTU_UPDATE_VENDORED_BINDINGS";
+
+/// Return the path of the checked-in bindings for `target_os`, if there are any.
+fn vendored_bindings(target_os: &str) -> Option<std::path::PathBuf> {
+    let name = match target_os {
+        "linux" | "android" => "linux.rs",
+        "macos" => "macos.rs",
+        _ => return None,
+    };
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR was not set");
+    Some(
+        std::path::Path::new(&manifest_dir)
+            .join("bindings")
+            .join(name),
+    )
+}
+
+/// Write `bindings` to the checked-in bindings for `target_os`, if it is Linux or macOS. See
+/// [`UPDATE_VENDORED_BINDINGS`].
+#[cfg(feature = "bindgen")]
+fn update_vendored_bindings(target_os: &str, bindings: &bindgen::Bindings) {
+    let host = match target_os {
+        "linux" => "Linux",
+        "macos" => "macOS",
+        _ => return,
+    };
+    let path = vendored_bindings(target_os).unwrap();
+    let contents = format!(
+        "// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Generated by bindgen from the allowlist in `build.rs`. Do not edit, but regenerate on {host}
+// with `{UPDATE_VENDORED_BINDINGS}=1 cargo build`.
+
+{bindings}"
+    );
+    std::fs::write(path, contents).expect("Couldn't write vendored bindings!");
+}
+
 fn bindgen() {
     let target_os = env::var("CARGO_CFG_TARGET_OS").expect("CARGO_CFG_TARGET_OS was not set");
 
@@ -55,20 +148,48 @@ fn bindgen() {
         return;
     }
 
-    let bindings = if matches!(target_os.as_str(), "linux" | "android") {
-        bindgen::Builder::default()
+    // Without the `bindgen` feature, the checked-in bindings are the only option.
+    if cfg!(any(feature = "vendored-bindings", not(feature = "bindgen"))) {
+        if let Some(path) = vendored_bindings(&target_os) {
+            println!("cargo:rustc-env=BINDINGS={}", path.display());
+            return;
+        }
+    }
+
+    #[cfg(feature = "bindgen")]
+    generate_bindings(&target_os);
+    // There are no checked-in bindings for the BSDs and Solaris.
+    #[cfg(not(feature = "bindgen"))]
+    panic!("There are no vendored bindings for {target_os}, enable the `bindgen` feature");
+}
+
+/// Generate the bindings for `target_os` with bindgen.
+#[cfg(feature = "bindgen")]
+fn generate_bindings(target_os: &str) {
+    let bindings = if matches!(target_os, "linux" | "android") {
+        let bindings = bindgen::Builder::default()
             .header_contents("rtnetlink.h", "#include <linux/rtnetlink.h>")
-            // Only generate bindings for the following types
-            .allowlist_type("rtattr|rtmsg|ifinfomsg|nlmsghdr")
+            // Only generate bindings for the following types and items
+            .allowlist_type("rtattr|rtmsg|rtnexthop|ifinfomsg|ifaddrmsg|nlmsghdr")
+            .allowlist_item(
+                "RTM_F_FIB_MATCH|RTAX_MTU|RTMGRP_LINK|RTMGRP_IPV4_ROUTE|RTMGRP_IPV6_ROUTE",
+            );
+        if target_os == "android" {
+            bindings.clang_args(android_clang_args())
+        } else {
+            bindings
+        }
     } else {
         bindgen::Builder::default()
         .header_contents(
             "route.h",
-            "#include <sys/types.h>\n#include <sys/socket.h>\n#include <net/route.h>\n#include <net/if.h>",
+            "#include <sys/types.h>\n#include <sys/socket.h>\n#include <net/route.h>\n#include <net/if.h>\n#include <net/if_types.h>",
         )
         // Only generate bindings for the following types and items
-        .allowlist_type("rt_msghdr|rt_metrics|if_data")
-        .allowlist_item("RTAX_MAX|RTM_GET|RTM_VERSION|RTA_DST|RTA_IFP")
+        .allowlist_type("rt_msghdr|rt_metrics|if_data|if_msghdr|ifa_msghdr")
+        .allowlist_item(
+            "RTAX_MAX|RTAX_DST|RTAX_GATEWAY|RTAX_NETMASK|RTM_GET|RTM_VERSION|RTM_ADD|RTM_DELETE|RTM_CHANGE|RTM_IFINFO|RTM_NEWADDR|RTM_DELADDR|RTA_DST|RTA_GATEWAY|RTA_NETMASK|RTA_IFP|RTA_IFA|RTF_GATEWAY|RTF_REJECT|RTF_BLACKHOLE|IFT_ETHER|IFT_PPP|IFT_GIF|IFT_LOOP",
+        )
     };
 
     let bindings = bindings
@@ -87,6 +208,11 @@ fn bindgen() {
         // Unwrap the Result and panic on failure.
         .expect("Unable to generate bindings");
 
+    println!("cargo:rerun-if-env-changed={UPDATE_VENDORED_BINDINGS}");
+    if env::var_os(UPDATE_VENDORED_BINDINGS).is_some() {
+        update_vendored_bindings(target_os, &bindings);
+    }
+
     // Write the bindings to the $OUT_DIR/$BINDINGS file.
     let out_path = std::path::PathBuf::from(env::var("OUT_DIR").unwrap()).join(BINDINGS);
     bindings
@@ -98,12 +224,15 @@ fn bindgen() {
 fn main() {
     // Setup cfg aliases
     cfg_aliases::cfg_aliases! {
+        // illumos shares the routing socket ABI of Solaris.
+        solarish: { any(target_os = "solaris", target_os = "illumos") },
         bsd: {
             any(
                 target_os = "freebsd",
+                target_os = "dragonfly",
                 target_os = "openbsd",
                 target_os = "netbsd",
-                target_os = "solaris"
+                solarish
             )
         }
     }
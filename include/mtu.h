@@ -0,0 +1,36 @@
+/* Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms. */
+
+/* C interface to the `mtu` crate, available with its `c_abi` feature. */
+
+#ifndef MTU_H
+#define MTU_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Return the name and MTU of the outgoing network interface towards a remote destination.
+ *
+ * `af` is `AF_INET` or `AF_INET6`, and `addr` points to the 4- or 16-byte address in network byte
+ * order. On success, the NUL-terminated interface name is written to `name_out` (which has room
+ * for `name_len` bytes, including the terminating NUL), the MTU is written to `mtu_out`, and 0 is
+ * returned. Otherwise, an errno-like code is returned: `EINVAL` for invalid arguments, `ERANGE` if
+ * `name_out` is too small, and `ENOENT` or an OS error code if the MTU cannot be determined.
+ *
+ * `name_out` and `mtu_out` may be NULL if the caller is not interested in them.
+ */
+int mtu_interface_and_mtu(int af, const unsigned char *addr, char *name_out, size_t name_len,
+                          size_t *mtu_out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MTU_H */
@@ -4,14 +4,18 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+#[cfg(target_os = "freebsd")]
+use std::os::fd::AsRawFd as _;
 use std::{
+    collections::HashMap,
     ffi::CStr,
     io::{Error, ErrorKind, Read as _, Result, Write as _},
     marker::PhantomData,
-    net::IpAddr,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     num::TryFromIntError,
     ops::Deref,
     ptr, slice,
+    time::{Duration, Instant},
 };
 
 use libc::{
@@ -25,20 +29,27 @@ use static_assertions::{const_assert, const_assert_eq};
     clippy::struct_field_names,
     clippy::too_many_lines,
     clippy::cognitive_complexity,
-    dead_code // RTA_IFP is only used on NetBSD and Solaris
+    dead_code // RTA_IFP is only used on NetBSD, Solaris and illumos
 )]
 mod bindings {
     include!(env!("BINDINGS"));
 }
 
-#[cfg(any(target_os = "netbsd", target_os = "solaris"))]
+#[cfg(any(target_os = "netbsd", solarish))]
 use crate::bsd::bindings::RTA_IFP;
+#[cfg(not(solarish))]
+use crate::bsd::bindings::{RTAX_DST, RTAX_GATEWAY, RTAX_NETMASK};
+#[cfg(feature = "tokio")]
+use crate::routesocket::AsyncRouteSocket;
 use crate::{
-    aligned_by,
-    bsd::bindings::{if_data, rt_msghdr, RTAX_MAX, RTA_DST},
-    default_err,
-    routesocket::RouteSocket,
-    unlikely_err,
+    bsd::bindings::{if_data, if_msghdr, ifa_msghdr, rt_msghdr, RTAX_MAX},
+    connected_source, default_err, inconsistent_reply_err, max_prefix_len, network_address,
+    not_reachable_via,
+    parse::aligned_by,
+    route_if_name_err,
+    routesocket::{self, RouteSocket, QUERY_TIMEOUT},
+    unlikely_err, ChangeEvent, Interface, InterfaceFlags, InterfaceIndex, InterfaceKind,
+    InterfaceStats, InterfaceType, RouteDetails, RouteEntry, RouteQuery, STRICT,
 };
 
 #[cfg(target_os = "macos")]
@@ -50,30 +61,70 @@ const ALIGN: usize = std::mem::size_of::<libc::c_int>();
 // See https://github.com/Arquivotheca/Solaris-8/blob/2ad1d32f9eeed787c5adb07eb32544276e2e2444/osnet_volume/usr/src/cmd/cmd-inet/usr.sbin/route.c#L238-L239
 const ALIGN: usize = std::mem::size_of::<libc::c_long>();
 
-#[cfg(any(target_os = "macos", target_os = "freebsd", target_os = "openbsd"))]
-asserted_const_with_type!(RTM_ADDRS, i32, RTA_DST, u32);
+#[cfg(any(
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd"
+))]
+asserted_const_with_type!(RTM_ADDRS, i32, bindings::RTA_DST, u32);
 
-#[cfg(any(target_os = "netbsd", target_os = "solaris"))]
-asserted_const_with_type!(RTM_ADDRS, i32, RTA_DST | RTA_IFP, u32);
+#[cfg(any(target_os = "netbsd", solarish))]
+asserted_const_with_type!(RTM_ADDRS, i32, bindings::RTA_DST | RTA_IFP, u32);
 
-#[cfg(not(target_os = "solaris"))]
+#[cfg(not(solarish))]
 type AddressFamily = u8;
 
-#[cfg(target_os = "solaris")]
+#[cfg(solarish)]
 type AddressFamily = u16;
 
 asserted_const_with_type!(AF_INET, AddressFamily, libc::AF_INET, i32);
 asserted_const_with_type!(AF_INET6, AddressFamily, libc::AF_INET6, i32);
 asserted_const_with_type!(AF_LINK, AddressFamily, libc::AF_LINK, i32);
+#[allow(clippy::cast_sign_loss)] // The interface flag bits are positive.
+const IFF_POINTOPOINT: u64 = libc::IFF_POINTOPOINT as u64;
 asserted_const_with_type!(RTM_VERSION, u8, bindings::RTM_VERSION, u32);
 asserted_const_with_type!(RTM_GET, u8, bindings::RTM_GET, u32);
+asserted_const_with_type!(RTM_ADD, u8, bindings::RTM_ADD, u32);
+asserted_const_with_type!(RTM_DELETE, u8, bindings::RTM_DELETE, u32);
+asserted_const_with_type!(RTM_CHANGE, u8, bindings::RTM_CHANGE, u32);
+asserted_const_with_type!(RTM_IFINFO, u8, bindings::RTM_IFINFO, u32);
+asserted_const_with_type!(RTM_NEWADDR, u8, bindings::RTM_NEWADDR, u32);
+asserted_const_with_type!(RTM_DELADDR, u8, bindings::RTM_DELADDR, u32);
+asserted_const_with_type!(RTA_DST, i32, bindings::RTA_DST, u32);
+asserted_const_with_type!(RTA_GATEWAY, i32, bindings::RTA_GATEWAY, u32);
+asserted_const_with_type!(RTA_NETMASK, i32, bindings::RTA_NETMASK, u32);
+asserted_const_with_type!(RTA_IFA, i32, bindings::RTA_IFA, u32);
+asserted_const_with_type!(RTF_GATEWAY, i32, bindings::RTF_GATEWAY, u32);
+asserted_const_with_type!(RTF_REJECT, i32, bindings::RTF_REJECT, u32);
+asserted_const_with_type!(IFT_ETHER, u8, bindings::IFT_ETHER, u32);
+asserted_const_with_type!(IFT_PPP, u8, bindings::IFT_PPP, u32);
+asserted_const_with_type!(IFT_GIF, u8, bindings::IFT_GIF, u32);
+asserted_const_with_type!(IFT_LOOP, u8, bindings::IFT_LOOP, u32);
+asserted_const_with_type!(RTF_BLACKHOLE, i32, bindings::RTF_BLACKHOLE, u32);
 
 const_assert!(std::mem::size_of::<sockaddr_in>() + ALIGN <= u8::MAX as usize);
 const_assert!(std::mem::size_of::<sockaddr_in6>() + ALIGN <= u8::MAX as usize);
 const_assert!(std::mem::size_of::<rt_msghdr>() <= u8::MAX as usize);
+// Interface indices are `u_short` in route messages and `sockaddr_dl`, which fits the `u32` of the
+// public API.
+const_assert!(std::mem::size_of::<libc::c_ushort>() <= std::mem::size_of::<u32>());
 
+/// The space taken up by the largest socket address in a route message.
+const SA_SPACE: usize = aligned_by(std::mem::size_of::<sockaddr_in6>(), ALIGN);
+
+const_assert!(std::mem::size_of::<RouteMessage>() <= u16::MAX as usize);
+
+/// How long a [`Resolver`] reuses its `getifaddrs` snapshot across lookups.
+const IFADDRS_TTL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
 struct IfAddrs(*mut ifaddrs);
 
+// SAFETY: The list returned by `getifaddrs` is owned exclusively by `IfAddrs` and is not tied to
+// the thread that allocated it.
+unsafe impl Send for IfAddrs {}
+
 impl Default for IfAddrs {
     fn default() -> Self {
         Self(ptr::null_mut())
@@ -110,6 +161,17 @@ impl Drop for IfAddrs {
     }
 }
 
+#[derive(Debug)]
+struct IfAddrsSnapshot {
+    taken: Instant,
+    ifaddrs: IfAddrs,
+}
+
+/// Call `f` with a new `getifaddrs` snapshot.
+fn with_ifaddrs<T>(f: impl FnOnce(&IfAddrs) -> T) -> Result<T> {
+    IfAddrs::new().map(|ifaddrs| f(&ifaddrs))
+}
+
 struct IfAddrPtr<'a> {
     ptr: *mut ifaddrs,
     _ref: PhantomData<&'a ifaddrs>,
@@ -124,6 +186,58 @@ impl IfAddrPtr<'_> {
         unsafe { CStr::from_ptr(self.ifa_name).to_string_lossy().to_string() }
     }
 
+    /// Return the index of the interface, if it still exists.
+    fn index(&self) -> Option<u32> {
+        // SAFETY: `ifa_name` is a NUL-terminated string owned by the `getifaddrs` list.
+        match unsafe { libc::if_nametoindex(self.ifa_name) } {
+            0 => None,
+            index => Some(index),
+        }
+    }
+
+    /// Return the address of an `AF_INET` or `AF_INET6` entry.
+    #[allow(clippy::cast_ptr_alignment)] // The socket addresses are only read unaligned.
+    fn ip(&self) -> Option<IpAddr> {
+        if self.ifa_addr.is_null() {
+            return None;
+        }
+        match self.addr().sa_family {
+            AF_INET => {
+                let sin = unsafe { self.ifa_addr.cast::<sockaddr_in>().read_unaligned() };
+                Some(IpAddr::V4(Ipv4Addr::from(
+                    sin.sin_addr.s_addr.to_ne_bytes(),
+                )))
+            }
+            AF_INET6 => {
+                let sin6 = unsafe { self.ifa_addr.cast::<sockaddr_in6>().read_unaligned() };
+                Some(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the Ethernet address of an `AF_LINK` entry, if it has a non-zero one. Loopback and
+    /// tunnel interfaces have none.
+    fn mac(&self) -> Option<[u8; 6]> {
+        #[allow(clippy::cast_ptr_alignment)] // The fields are only read unaligned.
+        let sdl = self.ifa_addr.cast::<sockaddr_dl>();
+        // The link-layer address follows the interface name in `sdl_data`, which may extend past
+        // the end of the declared array, so only access the fields and bytes that are present.
+        let (nlen, alen, data) = unsafe {
+            (
+                usize::from(ptr::addr_of!((*sdl).sdl_nlen).read_unaligned()),
+                usize::from(ptr::addr_of!((*sdl).sdl_alen).read_unaligned()),
+                ptr::addr_of!((*sdl).sdl_data).cast::<u8>(),
+            )
+        };
+        if alen != 6 {
+            return None;
+        }
+        let data = unsafe { slice::from_raw_parts(data, nlen + alen) };
+        let mac: [u8; 6] = data[nlen..].try_into().ok()?;
+        (mac != [0; 6]).then_some(mac)
+    }
+
     fn data(&self) -> Option<if_data> {
         if self.ifa_data.is_null() {
             None
@@ -131,6 +245,19 @@ impl IfAddrPtr<'_> {
             Some(unsafe { self.ifa_data.cast::<if_data>().read() })
         }
     }
+
+    /// Fill in the Ethernet address, flags, kind and speed of `interface` from this `AF_LINK`
+    /// entry.
+    fn set_link_info(&self, interface: &mut Interface) {
+        interface.mac = self.mac();
+        interface.flags = InterfaceFlags::from_iff(u64::from(self.ifa_flags));
+        let data = self.data();
+        interface.kind = data.map(|data| InterfaceKind::from_if_type(data.ifi_type.into()));
+        // Drivers that do not know the speed report zero.
+        #[allow(clippy::useless_conversion)] // `ifi_baudrate` is a `u64` on some platforms.
+        let speed_bps = data.map(|data| u64::from(data.ifi_baudrate));
+        interface.speed_bps = speed_bps.filter(|&speed| speed != 0);
+    }
 }
 
 impl Deref for IfAddrPtr<'_> {
@@ -155,24 +282,38 @@ impl Iterator for IfAddrPtr<'_> {
     }
 }
 
-fn if_name_mtu(idx: u32) -> Result<(String, Option<usize>)> {
+fn if_name_mtu(idx: u32, ifaddrs: &IfAddrs) -> Result<(String, Option<usize>)> {
     let mut name = [0; libc::IF_NAMESIZE];
     // if_indextoname writes into the provided buffer.
     if unsafe { if_indextoname(idx, name.as_mut_ptr()).is_null() } {
         return Err(Error::last_os_error());
     }
-    // Convert to Rust string.
-    let name = unsafe {
-        CStr::from_ptr(name.as_ptr())
-            .to_str()
-            .map_err(|err| Error::new(ErrorKind::Other, err))?
-    };
-    let mtu = IfAddrs::new()?
+    // Convert to Rust string. Names that are not valid UTF-8 are converted lossily, as in
+    // `IfAddrPtr::name`, so that they still match.
+    let name = unsafe { CStr::from_ptr(name.as_ptr()).to_string_lossy().to_string() };
+    let mtu = if_mtu(&name, ifaddrs);
+    Ok((name, mtu))
+}
+
+/// Fill in the Ethernet address, flags and kind of `interface` from the `AF_LINK` entry with its
+/// name in `ifaddrs`. They remain unknown if there is none.
+fn add_link_info(interface: &mut Interface, ifaddrs: &IfAddrs) {
+    if let Some(ifa) = ifaddrs
+        .iter()
+        .find(|ifa| ifa.addr().sa_family == AF_LINK && ifa.name() == interface.name)
+    {
+        ifa.set_link_info(interface);
+    }
+}
+
+/// Return the MTU of the interface named `name`, from its `AF_LINK` entry in `ifaddrs`, if there
+/// is one.
+fn if_mtu(name: &str, ifaddrs: &IfAddrs) -> Option<usize> {
+    ifaddrs
         .iter()
         .find(|ifa| ifa.addr().sa_family == AF_LINK && ifa.name() == name)
         .and_then(|ifa| ifa.data())
-        .and_then(|ifa_data| usize::try_from(ifa_data.ifi_mtu).ok());
-    Ok((name.to_string(), mtu))
+        .and_then(|ifa_data| usize::try_from(ifa_data.ifi_mtu).ok())
 }
 
 #[repr(C)]
@@ -195,12 +336,14 @@ fn sockaddr_len(af: AddressFamily) -> Result<usize> {
     Ok(aligned_by(sa_len, ALIGN))
 }
 
-impl From<IpAddr> for SockaddrStorage {
-    fn from(ip: IpAddr) -> Self {
+impl SockaddrStorage {
+    /// Create a socket address for `ip`. For IPv6, `flowinfo` is stored in network byte order, and
+    /// `scope_id` identifies the link of link-local addresses.
+    const fn new(ip: IpAddr, flowinfo: u32, scope_id: u32) -> Self {
         match ip {
             IpAddr::V4(ip) => SockaddrStorage {
                 sin: sockaddr_in {
-                #[cfg(not(target_os = "solaris"))]
+                #[cfg(not(solarish))]
                 #[allow(clippy::cast_possible_truncation)]
                 // `sockaddr_in` len is <= u8::MAX per `const_assert!` above.
                 sin_len: std::mem::size_of::<sockaddr_in>() as u8,
@@ -214,7 +357,7 @@ impl From<IpAddr> for SockaddrStorage {
             },
             IpAddr::V6(ip) => SockaddrStorage {
                 sin6: sockaddr_in6 {
-                #[cfg(not(target_os = "solaris"))]
+                #[cfg(not(solarish))]
                 #[allow(clippy::cast_possible_truncation)]
                 // `sockaddr_in6` len is <= u8::MAX per `const_assert!` above.
                 sin6_len: std::mem::size_of::<sockaddr_in6>() as u8,
@@ -223,9 +366,9 @@ impl From<IpAddr> for SockaddrStorage {
                     s6_addr: ip.octets(),
                 },
                 sin6_port: 0,
-                sin6_flowinfo: 0,
-                sin6_scope_id: 0,
-                #[cfg(target_os = "solaris")]
+                sin6_flowinfo: flowinfo.to_be(),
+                sin6_scope_id: scope_id,
+                #[cfg(solarish)]
                 __sin6_src_id: 0,
                 },
             },
@@ -236,29 +379,73 @@ impl From<IpAddr> for SockaddrStorage {
 #[repr(C)]
 struct RouteMessage {
     rtm: rt_msghdr,
-    sa: SockaddrStorage,
+    /// The destination address, optionally followed by its netmask and the source address.
+    sa: [u8; 3 * SA_SPACE],
 }
 
 impl RouteMessage {
-    fn new(remote: IpAddr, seq: i32) -> Result<Self> {
-        let sa = SockaddrStorage::from(remote);
+    fn new(query: RouteQuery, seq: i32) -> Result<Self> {
+        let remote = query.remote;
         let sa_len = sockaddr_len(match remote {
             IpAddr::V4(_) => AF_INET,
             IpAddr::V6(_) => AF_INET6,
         })?;
-        Ok(Self {
+        // For network queries, add a netmask so that the kernel looks up the route covering the
+        // network.
+        let mask = (query.prefix_len < max_prefix_len(remote)).then(|| {
+            let all_ones = match remote {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::BROADCAST),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(u128::MAX)),
+            };
+            SockaddrStorage::new(network_address(all_ones, query.prefix_len), 0, 0)
+        });
+        let src = query
+            .src
+            .filter(|src| src.is_ipv4() == remote.is_ipv4())
+            .map(|src| SockaddrStorage::new(src, 0, 0));
+        let sa_count = 1 + usize::from(mask.is_some()) + usize::from(src.is_some());
+        let mut rtm_addrs = RTM_ADDRS;
+        if mask.is_some() {
+            rtm_addrs |= RTA_NETMASK;
+        }
+        if src.is_some() {
+            rtm_addrs |= RTA_IFA;
+        }
+        let mut msg = Self {
             rtm: rt_msghdr {
                 #[allow(clippy::cast_possible_truncation)]
-                // `rt_msghdr` len + `ALIGN` is <= u8::MAX per `const_assert!` above.
-                rtm_msglen: (std::mem::size_of::<rt_msghdr>() + sa_len) as u16,
+                // `RouteMessage` len is <= u16::MAX per `const_assert!` above.
+                rtm_msglen: (std::mem::size_of::<rt_msghdr>() + sa_count * sa_len) as u16,
                 rtm_version: RTM_VERSION,
                 rtm_type: RTM_GET,
                 rtm_seq: seq,
-                rtm_addrs: RTM_ADDRS,
+                rtm_addrs,
+                // Unlike FreeBSD FIBs, the table is chosen per message.
+                #[cfg(target_os = "openbsd")]
+                rtm_tableid: query.rdomain.unwrap_or(0),
                 ..Default::default()
             },
-            sa,
-        })
+            sa: [0; 3 * SA_SPACE],
+        };
+        // The socket addresses follow in the order of their `RTA_*` bits.
+        let dst = SockaddrStorage::new(remote, query.flowinfo, query.scope_id());
+        msg.put_sockaddr(0, &dst, sa_len);
+        let mut offset = sa_len;
+        if let Some(mask) = mask {
+            msg.put_sockaddr(offset, &mask, sa_len);
+            offset += sa_len;
+        }
+        if let Some(src) = src {
+            msg.put_sockaddr(offset, &src, sa_len);
+        }
+        Ok(msg)
+    }
+
+    /// Copy the first `len` bytes of `sa` to `offset` in the socket address area.
+    fn put_sockaddr(&mut self, offset: usize, sa: &SockaddrStorage, len: usize) {
+        let len = len.min(std::mem::size_of::<SockaddrStorage>());
+        let bytes = unsafe { slice::from_raw_parts(ptr::from_ref(sa).cast::<u8>(), len) };
+        self.sa[offset..offset + len].copy_from_slice(bytes);
     }
 
     const fn version(&self) -> u8 {
@@ -281,78 +468,1289 @@ impl From<&RouteMessage> for &[u8] {
     }
 }
 
-impl From<&[u8]> for rt_msghdr {
-    fn from(value: &[u8]) -> Self {
-        debug_assert!(value.len() >= std::mem::size_of::<Self>());
-        unsafe { ptr::read_unaligned(value.as_ptr().cast()) }
+impl TryFrom<&[u8]> for rt_msghdr {
+    type Error = Error;
+
+    fn try_from(value: &[u8]) -> Result<Self> {
+        if value.len() < std::mem::size_of::<Self>() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Route message of {} bytes is shorter than its header",
+                    value.len()
+                ),
+            ));
+        }
+        // SAFETY: `value` holds at least the size of `rt_msghdr`, which is read unaligned. It is a
+        // plain C struct of integers, so any bytes are a valid value.
+        Ok(unsafe { ptr::read_unaligned(value.as_ptr().cast()) })
     }
 }
 
-fn if_index_mtu(remote: IpAddr) -> Result<(u16, Option<usize>)> {
-    // Open route socket.
-    let mut fd = RouteSocket::new(PF_ROUTE, AF_UNSPEC)?;
+/// A route socket for route queries, which is kept open between queries, and the `getifaddrs`
+/// snapshot of the interfaces that the queries returned.
+///
+/// Route sockets also receive the routing messages for all changes, which accumulate while the
+/// socket is idle. Queries skip them, since they do not match the sequence number of the query,
+/// but drop the snapshot, so that the next lookup takes a new one. The snapshot is also dropped
+/// once it is older than `IFADDRS_TTL`.
+#[derive(Debug)]
+pub struct Resolver {
+    fd: RouteSocket,
+    ifaddrs: Option<IfAddrsSnapshot>,
+}
 
-    // Send route message.
-    let query_seq = RouteSocket::new_seq();
-    let query = RouteMessage::new(remote, query_seq)?;
-    let query_version = query.version();
-    let query_type = query.kind();
-    fd.write_all((&query).into())?;
+impl Resolver {
+    pub fn new() -> Result<Self> {
+        let fd = RouteSocket::new(PF_ROUTE, AF_UNSPEC)?;
+        fd.set_read_timeout(Some(QUERY_TIMEOUT))?;
+        Ok(Self { fd, ifaddrs: None })
+    }
 
-    // Read route messages.
-    let pid = unsafe { getpid() };
-    loop {
-        let mut buf = vec![
-            0u8;
-            std::mem::size_of::<rt_msghdr>() +
-        // There will never be `RTAX_MAX` sockaddrs attached, but it's a safe upper bound.
-         (RTAX_MAX as usize * std::mem::size_of::<sockaddr_storage>())
-        ];
-        let len = fd.read(&mut buf[..])?;
-        if len < std::mem::size_of::<rt_msghdr>() {
-            return Err(default_err());
-        }
-        let (reply, mut sa) = buf.split_at(std::mem::size_of::<rt_msghdr>());
-        let reply: rt_msghdr = reply.into();
-        if !(reply.rtm_version == query_version
-            && reply.rtm_pid == pid
-            && reply.rtm_seq == query_seq)
+    /// Send a route query and return the result of `parse` for the first reply it accepts.
+    fn query_route<T>(
+        &mut self,
+        query: RouteQuery,
+        parse: impl Fn(&[u8]) -> Result<Option<T>>,
+    ) -> Result<T> {
+        // Send route message.
+        let query = route_query_msg(&self.fd, query)?;
+        self.fd.write_all((&query).into())?;
+
+        // Read route messages.
+        let ifaddrs = &mut self.ifaddrs;
+        read_reply(
+            |buf| {
+                let res = self.fd.read(buf);
+                // A change message, or an error such as lost messages, may invalidate the
+                // snapshot.
+                if !res
+                    .as_ref()
+                    .is_ok_and(|&len| parse_change(&buf[..len]).is_none())
+                {
+                    *ifaddrs = None;
+                }
+                res
+            },
+            Instant::now() + QUERY_TIMEOUT,
+            &query,
+            parse,
+        )
+    }
+
+    /// Return the `getifaddrs` snapshot of the resolver, taking a new one if there is none or it
+    /// is older than `IFADDRS_TTL`.
+    fn ifaddrs(&mut self) -> Result<&IfAddrs> {
+        if !self
+            .ifaddrs
+            .as_ref()
+            .is_some_and(|snapshot| snapshot.taken.elapsed() < IFADDRS_TTL)
         {
+            self.ifaddrs = Some(IfAddrsSnapshot {
+                taken: Instant::now(),
+                ifaddrs: IfAddrs::new()?,
+            });
+        }
+        self.ifaddrs
+            .as_ref()
+            .map(|snapshot| &snapshot.ifaddrs)
+            .ok_or_else(default_err)
+    }
+
+    fn if_index_mtu(&mut self, query: RouteQuery) -> Result<(u32, Option<usize>)> {
+        match query.oif {
+            // `RTM_GET` cannot be constrained to an interface and only reports the best route,
+            // which may go through another one, so look for the most specific route through `oif`
+            // in the routing table instead.
+            #[cfg(not(solarish))]
+            Some(oif) => route_via(query.remote, oif, &routes_impl()?),
+            _ => self.query_route(query, |buf| parse_reply(buf, STRICT)),
+        }
+    }
+
+    /// Return the outgoing interface towards the destination of `query` with its MTU, and the MTU
+    /// of the route, if any.
+    fn interface_and_route_mtu(&mut self, query: RouteQuery) -> Result<(Interface, Option<usize>)> {
+        let (if_index, route_mtu) = self.if_index_mtu(query)?;
+        route_interface(query, if_index, route_mtu, self.ifaddrs()?)
+    }
+
+    pub fn interface_and_mtu(&mut self, query: RouteQuery) -> Result<Interface> {
+        self.interface_and_route_mtu(query).map(with_route_mtu)
+    }
+}
+
+/// Return the interface with index `if_index` in `ifaddrs`, which the route towards the destination
+/// of `query` goes through, and the MTU of that route, `route_mtu`.
+fn route_interface(
+    query: RouteQuery,
+    if_index: u32,
+    route_mtu: Option<usize>,
+    ifaddrs: &IfAddrs,
+) -> Result<(Interface, Option<usize>)> {
+    // On Solaris and illumos, the routing table cannot be enumerated, so only the best route can be
+    // checked.
+    if let Some(oif) = query.oif {
+        if if_index != oif {
+            return Err(not_reachable_via(oif));
+        }
+    }
+    let (if_name, if_mtu) =
+        if_name_mtu(if_index, ifaddrs).map_err(|e| route_if_name_err(if_index, &e))?;
+    // Fall back to the route MTU if the interface does not report one.
+    let if_mtu = if_mtu.or(route_mtu).ok_or_else(default_err)?;
+    let mut interface = Interface::new(if_name, if_mtu, if_index);
+    add_link_info(&mut interface, ifaddrs);
+    Ok((interface, route_mtu))
+}
+
+/// Return `interface` with the route MTU as its MTU, if there is one.
+fn with_route_mtu((interface, route_mtu): (Interface, Option<usize>)) -> Interface {
+    Interface {
+        mtu: route_mtu.unwrap_or(interface.mtu),
+        ..interface
+    }
+}
+
+fn if_index_mtu(query: RouteQuery) -> Result<(u32, Option<usize>)> {
+    Resolver::new()?.if_index_mtu(query)
+}
+
+/// Return the route message for `query`, with a new sequence number for `fd`.
+fn route_query_msg(fd: &RouteSocket, query: RouteQuery) -> Result<RouteMessage> {
+    // The FIB sticks to the socket, so only queries on a socket of their own may set one.
+    #[cfg(target_os = "freebsd")]
+    if let Some(fib) = query.fib {
+        set_fib(fd, fib)?;
+    }
+    RouteMessage::new(query, fd.new_seq())
+}
+
+/// Return a buffer large enough for any reply to a route query.
+fn reply_buf() -> Vec<u8> {
+    vec![
+        0u8;
+        std::mem::size_of::<rt_msghdr>() +
+        // There will never be `RTAX_MAX` sockaddrs attached, but it's a safe upper bound.
+        (RTAX_MAX as usize * std::mem::size_of::<sockaddr_storage>())
+    ]
+}
+
+/// Read messages with `read` until one is the reply to `query`, and return the result of `parse`
+/// for it. Route sockets also receive the messages for all routing changes, which could keep
+/// arriving indefinitely, so give up with a timeout error once `deadline` has passed.
+fn read_reply<T>(
+    mut read: impl FnMut(&mut [u8]) -> Result<usize>,
+    deadline: Instant,
+    query: &RouteMessage,
+    parse: impl Fn(&[u8]) -> Result<Option<T>>,
+) -> Result<T> {
+    let mut buf = reply_buf();
+    loop {
+        let len = read(&mut buf[..])?;
+        if let Some(res) = accept_reply(&buf[..len], query, &parse)? {
+            return Ok(res);
+        }
+        if Instant::now() >= deadline {
+            return Err(routesocket::timed_out_err());
+        }
+    }
+}
+
+/// Return the result of `parse` for `buf` if it holds the reply to `query`, or `None` if more
+/// messages need to be read.
+fn accept_reply<T>(
+    buf: &[u8],
+    query: &RouteMessage,
+    parse: impl Fn(&[u8]) -> Result<Option<T>>,
+) -> Result<Option<T>> {
+    let reply = rt_msghdr::try_from(buf)?;
+    let pid = unsafe { getpid() };
+    if !(reply.rtm_version == query.version()
+        && reply.rtm_pid == pid
+        && reply.rtm_seq == query.rtm.rtm_seq)
+    {
+        return Ok(None);
+    }
+    if reply.rtm_type != query.kind() {
+        return Err(default_err());
+    }
+
+    // This is the reply we are looking for.
+    parse(buf)
+}
+
+/// Make route queries on `fd` use the FIB `fib`.
+#[cfg(target_os = "freebsd")]
+fn set_fib(fd: &RouteSocket, fib: u16) -> Result<()> {
+    let fib = libc::c_int::from(fib);
+    if unsafe {
+        libc::setsockopt(
+            fd.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_SETFIB,
+            ptr::from_ref(&fib).cast(),
+            #[allow(clippy::cast_possible_truncation)] // The size of a `c_int` fits.
+            {
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t
+            },
+        )
+    } == -1
+    {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Read a `T` from the start of `buf`, zero-filling any bytes past the end of `buf`.
+///
+/// # Safety
+///
+/// An all-zero bit pattern must be a valid `T`.
+unsafe fn read_zero_padded<T>(buf: &[u8]) -> T {
+    let mut value = std::mem::zeroed::<T>();
+    let len = buf.len().min(std::mem::size_of::<T>());
+    ptr::copy_nonoverlapping(buf.as_ptr(), ptr::from_mut(&mut value).cast::<u8>(), len);
+    value
+}
+
+/// Return an error if the socket addresses announced by `rtm_addrs` do not exactly fill `sa`.
+#[cfg(not(solarish))]
+fn check_sockaddrs(rtm_addrs: i32, mut sa: &[u8]) -> Result<()> {
+    for i in 0..RTAX_MAX {
+        if (rtm_addrs & (1 << i)) == 0 {
             continue;
         }
-        if reply.rtm_type != query_type {
-            return Err(default_err());
+        let saddr: sockaddr = unsafe { read_zero_padded(sa) };
+        sa = sa
+            .get(aligned_by(saddr.sa_len.into(), ALIGN)..)
+            .ok_or_else(|| {
+                inconsistent_reply_err(format!("Route reply lacks socket address {i}"))
+            })?;
+    }
+    if !sa.is_empty() {
+        return Err(inconsistent_reply_err(format!(
+            "Route reply has {} bytes after its socket addresses",
+            sa.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Extract the interface index and MTU from `buf`, which holds a reply to our route query. If
+/// `strict`, check that the reply exactly fills `buf` and carries the announced socket addresses.
+///
+/// The index is widened to `u32`, the index type used on all platforms.
+fn parse_reply(buf: &[u8], strict: bool) -> Result<Option<(u32, Option<usize>)>> {
+    let reply = rt_msghdr::try_from(buf)?;
+    // Don't look past the end of what was read, in case the reply was truncated.
+    let msg_len = usize::from(reply.rtm_msglen);
+    if msg_len < std::mem::size_of::<rt_msghdr>() || msg_len > buf.len() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Route reply length {msg_len} does not fit the {} bytes read",
+                buf.len()
+            ),
+        ));
+    }
+    let mut sa = &buf[std::mem::size_of::<rt_msghdr>()..msg_len];
+    if strict {
+        if msg_len != buf.len() {
+            return Err(inconsistent_reply_err(format!(
+                "Route reply length {msg_len} does not match the {} bytes read",
+                buf.len()
+            )));
         }
+        // Solaris and illumos socket addresses don't carry their length, so we can't check them
+        // there.
+        #[cfg(not(solarish))]
+        check_sockaddrs(reply.rtm_addrs, sa)?;
+    }
 
-        // This is a reply to our query.
-        // This is the reply we are looking for.
-        // Some BSDs let us get the interface index and MTU directly from the reply.
-        let mtu = (reply.rtm_rmx.rmx_mtu != 0)
-            .then(|| usize::try_from(reply.rtm_rmx.rmx_mtu))
-            .transpose()
-            .map_err(|e: TryFromIntError| unlikely_err(e.to_string()))?;
-        if reply.rtm_index != 0 {
-            // Some BSDs return the interface index directly.
-            return Ok((reply.rtm_index, mtu));
+    // Reject and blackhole routes have an interface, but traffic towards them is never sent.
+    if reply.rtm_flags & RTF_REJECT != 0 {
+        return Err(Error::from_raw_os_error(libc::EHOSTUNREACH));
+    }
+    if reply.rtm_flags & RTF_BLACKHOLE != 0 {
+        return Err(Error::from_raw_os_error(libc::ENETUNREACH));
+    }
+
+    // Some BSDs let us get the interface index and MTU directly from the reply.
+    let mtu = (reply.rtm_rmx.rmx_mtu != 0)
+        .then(|| usize::try_from(reply.rtm_rmx.rmx_mtu))
+        .transpose()
+        .map_err(|e: TryFromIntError| unlikely_err(e.to_string()))?;
+    if reply.rtm_index != 0 {
+        // Some BSDs return the interface index directly.
+        return Ok(Some((reply.rtm_index.into(), mtu)));
+    }
+    // For others, we need to extract it from the sockaddrs.
+    for i in 0..RTAX_MAX {
+        if (reply.rtm_addrs & (1 << i)) == 0 {
+            continue;
         }
-        // For others, we need to extract it from the sockaddrs.
-        for i in 0..RTAX_MAX {
-            if (reply.rtm_addrs & (1 << i)) == 0 {
+        let saddr: sockaddr = unsafe { read_zero_padded(sa) };
+        if saddr.sa_family != AF_LINK {
+            sa = sa
+                .get(sockaddr_len(saddr.sa_family)?..)
+                .ok_or_else(default_err)?;
+            continue;
+        }
+        let sdl: sockaddr_dl = unsafe { read_zero_padded(sa) };
+        return Ok(Some((sdl.sdl_index.into(), mtu)));
+    }
+    Ok(None)
+}
+
+pub fn interface_and_mtu_impl(query: RouteQuery) -> Result<Interface> {
+    Resolver::new()?.interface_and_mtu(query)
+}
+
+/// Like [`interface_and_mtu_impl`], but waiting for the reply on the Tokio reactor.
+#[cfg(feature = "tokio")]
+pub async fn interface_and_mtu_async_impl(query: RouteQuery) -> Result<Interface> {
+    // As in `Resolver::if_index_mtu`, routes through an interface come from the routing table.
+    #[cfg(not(solarish))]
+    if let Some(oif) = query.oif {
+        let (if_index, route_mtu) = route_via(query.remote, oif, &routes_impl()?)?;
+        return route_interface(query, if_index, route_mtu, &IfAddrs::new()?).map(with_route_mtu);
+    }
+    let fd = RouteSocket::new(PF_ROUTE, AF_UNSPEC)?;
+    let msg = route_query_msg(&fd, query)?;
+    let mut fd = AsyncRouteSocket::new(fd)?;
+    fd.write_all((&msg).into())?;
+    // As in `read_reply`, give up once the deadline has passed, even if messages keep arriving.
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+    let mut buf = reply_buf();
+    let (if_index, route_mtu) = loop {
+        let len = fd.read(&mut buf).await?;
+        if let Some(res) = accept_reply(&buf[..len], &msg, |buf| parse_reply(buf, STRICT))? {
+            break res;
+        }
+        if Instant::now() >= deadline {
+            return Err(routesocket::timed_out_err());
+        }
+    };
+    route_interface(query, if_index, route_mtu, &IfAddrs::new()?).map(with_route_mtu)
+}
+
+pub fn interface_and_route_mtu_impl(query: RouteQuery) -> Result<(Interface, Option<usize>)> {
+    Resolver::new()?.interface_and_route_mtu(query)
+}
+
+pub fn interface_index_impl(query: RouteQuery) -> Result<u32> {
+    if_index_mtu(query).map(|(if_index, _)| if_index)
+}
+
+pub fn mtu_snapshot_impl() -> Result<HashMap<u32, usize>> {
+    with_ifaddrs(|ifaddrs| {
+        ifaddrs
+            .iter()
+            .filter(|ifa| ifa.addr().sa_family == AF_LINK)
+            .filter_map(|ifa| {
+                let mtu = usize::try_from(ifa.data()?.ifi_mtu).ok()?;
+                Some((ifa.index()?, mtu))
+            })
+            .collect()
+    })
+}
+
+pub fn interfaces_impl() -> Result<Vec<Interface>> {
+    let mut interfaces: Vec<_> = with_ifaddrs(|ifaddrs| {
+        ifaddrs
+            .iter()
+            .filter(|ifa| ifa.addr().sa_family == AF_LINK)
+            .filter_map(|ifa| {
+                let mtu = usize::try_from(ifa.data()?.ifi_mtu).ok()?;
+                let mut interface = Interface::new(ifa.name(), mtu, ifa.index()?);
+                ifa.set_link_info(&mut interface);
+                Some(interface)
+            })
+            .collect()
+    })?;
+    interfaces.sort_unstable_by_key(|interface| interface.index);
+    Ok(interfaces)
+}
+
+/// Return the interface type of the interface named `name`, from its `AF_LINK` entry in
+/// `ifaddrs`, if it has a conventional MTU.
+fn if_type(name: &str, ifaddrs: &IfAddrs) -> Option<InterfaceType> {
+    let ifi_type = ifaddrs
+        .iter()
+        .find(|ifa| ifa.addr().sa_family == AF_LINK && ifa.name() == name)
+        .and_then(|ifa| ifa.data())
+        .map(|ifa_data| ifa_data.ifi_type);
+    match ifi_type {
+        Some(IFT_ETHER) => Some(InterfaceType::Ethernet),
+        Some(IFT_PPP) => Some(InterfaceType::Pppoe),
+        Some(IFT_GIF) => Some(InterfaceType::SixInFour),
+        Some(IFT_LOOP) => Some(InterfaceType::Loopback),
+        _ => None,
+    }
+}
+
+/// Return the IP address in the socket address `sa` points to, if it is one.
+fn ifa_ip(sa: *const sockaddr) -> Option<IpAddr> {
+    if sa.is_null() {
+        return None;
+    }
+    match unsafe { (*sa).sa_family } {
+        AF_INET => {
+            let sin: sockaddr_in = unsafe { ptr::read_unaligned(sa.cast()) };
+            Some(IpAddr::V4(Ipv4Addr::from(
+                sin.sin_addr.s_addr.to_ne_bytes(),
+            )))
+        }
+        AF_INET6 => {
+            let sin6: sockaddr_in6 = unsafe { ptr::read_unaligned(sa.cast()) };
+            Some(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+        }
+        _ => None,
+    }
+}
+
+/// Return the peer address for the address family of `remote` of the interface with index
+/// `if_index` in `ifaddrs`, if it is a point-to-point interface and has one.
+fn if_peer(if_index: u32, remote: IpAddr, ifaddrs: &IfAddrs) -> Option<IpAddr> {
+    // For point-to-point interfaces, `ifa_dstaddr` is the address of the peer.
+    ifaddrs
+        .iter()
+        .filter(|ifa| u64::from(ifa.ifa_flags) & IFF_POINTOPOINT != 0)
+        .filter(|ifa| ifa_ip(ifa.ifa_addr).is_some_and(|local| local.is_ipv4() == remote.is_ipv4()))
+        .filter(|ifa| ifa.index() == Some(if_index))
+        .find_map(|ifa| ifa_ip(ifa.ifa_dstaddr))
+}
+
+pub fn interface_and_mtu_by_index_impl(index: u32) -> Result<(String, usize)> {
+    let (if_name, mtu) = if_name_mtu(index, &IfAddrs::new()?)?;
+    Ok((if_name, mtu.ok_or_else(default_err)?))
+}
+
+pub fn interface_by_index_impl(index: u32) -> Result<Interface> {
+    let ifaddrs = IfAddrs::new()?;
+    let (name, mtu) = if_name_mtu(index, &ifaddrs)?;
+    let mut interface = Interface::new(name, mtu.ok_or_else(default_err)?, index);
+    add_link_info(&mut interface, &ifaddrs);
+    Ok(interface)
+}
+
+pub fn family_mtus_impl(index: u32) -> Result<(Option<usize>, Option<usize>)> {
+    // There are no per-family interface MTUs, so report the interface MTU for the families that
+    // have an address on the interface.
+    let (name, mtu) = interface_and_mtu_by_index_impl(index)?;
+    let (has_inet, has_inet6) = with_ifaddrs(|ifaddrs| {
+        ifaddrs
+            .iter()
+            .filter(|ifa| !ifa.ifa_addr.is_null() && ifa.name() == name)
+            .fold((false, false), |(inet, inet6), ifa| {
+                let family = ifa.addr().sa_family;
+                (inet || family == AF_INET, inet6 || family == AF_INET6)
+            })
+    })?;
+    Ok((has_inet.then_some(mtu), has_inet6.then_some(mtu)))
+}
+
+#[cfg(not(solarish))]
+pub fn interface_stats_impl(index: u32) -> Result<InterfaceStats> {
+    let ifaddrs = IfAddrs::new()?;
+    let (name, _) = if_name_mtu(index, &ifaddrs)?;
+    let data = ifaddrs
+        .iter()
+        .find(|ifa| ifa.addr().sa_family == AF_LINK && ifa.name() == name)
+        .and_then(|ifa| ifa.data())
+        .ok_or_else(default_err)?;
+    #[allow(clippy::useless_conversion)] // The counters are `u64`s on some platforms.
+    let stats = InterfaceStats {
+        rx_bytes: u64::from(data.ifi_ibytes),
+        tx_bytes: u64::from(data.ifi_obytes),
+        rx_packets: u64::from(data.ifi_ipackets),
+        tx_packets: u64::from(data.ifi_opackets),
+    };
+    Ok(stats)
+}
+
+#[cfg(solarish)]
+pub fn interface_stats_impl(_index: u32) -> Result<InterfaceStats> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "Interface statistics are not supported on Solaris and illumos",
+    ))
+}
+
+pub fn local_addrs_impl() -> Result<Vec<(IpAddr, u32)>> {
+    with_ifaddrs(|ifaddrs| {
+        ifaddrs
+            .iter()
+            .filter_map(|ifa| Some((ifa.ip()?, ifa.index()?)))
+            .collect()
+    })
+}
+
+/// Return the IP address in the socket address at the start of `sa`, if it is one.
+#[cfg(not(solarish))]
+fn sockaddr_ip(sa: &[u8]) -> Option<IpAddr> {
+    let saddr: sockaddr = unsafe { read_zero_padded(sa) };
+    match saddr.sa_family {
+        AF_INET => {
+            let sin: sockaddr_in = unsafe { read_zero_padded(sa) };
+            Some(IpAddr::V4(Ipv4Addr::from(
+                sin.sin_addr.s_addr.to_ne_bytes(),
+            )))
+        }
+        AF_INET6 => {
+            let sin6: sockaddr_in6 = unsafe { read_zero_padded(sa) };
+            Some(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+        }
+        _ => None,
+    }
+}
+
+/// Return the prefix length of the netmask socket address at the start of `sa`, for a destination
+/// like `dst`. The kernel omits trailing zero bytes and may leave the address family unset.
+#[cfg(not(solarish))]
+fn netmask_prefix_len(sa: &[u8], dst: IpAddr) -> u8 {
+    #[allow(clippy::cast_possible_truncation)] // At most 128.
+    match dst {
+        IpAddr::V4(_) => {
+            let sin: sockaddr_in = unsafe { read_zero_padded(sa) };
+            u32::from_be(sin.sin_addr.s_addr).leading_ones() as u8
+        }
+        IpAddr::V6(_) => {
+            let sin6: sockaddr_in6 = unsafe { read_zero_padded(sa) };
+            u128::from_be_bytes(sin6.sin6_addr.s6_addr).leading_ones() as u8
+        }
+    }
+}
+
+/// Parse a route message of a routing table dump into a route entry. `sa` holds the socket
+/// addresses following `rtm`. Returns `None` for routes of families other than IPv4 and IPv6.
+#[cfg(not(solarish))]
+fn parse_route(rtm: &rt_msghdr, mut sa: &[u8]) -> Option<RouteEntry> {
+    let (mut dst, mut gateway, mut netmask) = (None, None, None);
+    for i in 0..RTAX_MAX {
+        if (rtm.rtm_addrs & (1 << i)) == 0 {
+            continue;
+        }
+        let saddr: sockaddr = unsafe { read_zero_padded(sa) };
+        match i {
+            RTAX_DST => dst = sockaddr_ip(sa),
+            // Gateways of directly reachable routes are link-layer addresses.
+            RTAX_GATEWAY => gateway = sockaddr_ip(sa),
+            RTAX_NETMASK => netmask = Some(sa),
+            _ => (),
+        }
+        sa = sa.get(aligned_by(saddr.sa_len.into(), ALIGN)..)?;
+    }
+    let destination = dst?;
+    Some(RouteEntry {
+        destination,
+        // Host routes don't carry a netmask.
+        prefix_len: netmask.map_or_else(
+            || max_prefix_len(destination),
+            |mask| netmask_prefix_len(mask, destination),
+        ),
+        gateway,
+        oif_index: InterfaceIndex(rtm.rtm_index.into()),
+        mtu: usize::try_from(rtm.rtm_rmx.rmx_mtu)
+            .ok()
+            .filter(|&mtu| mtu != 0),
+    })
+}
+
+/// Return the interface index `oif` and the route MTU of the most specific route in `routes` that
+/// covers `remote` and goes through `oif`.
+#[cfg(not(solarish))]
+fn route_via(remote: IpAddr, oif: u32, routes: &[RouteEntry]) -> Result<(u32, Option<usize>)> {
+    routes
+        .iter()
+        .filter(|route| {
+            route.oif_index.0 == oif
+                && route.destination.is_ipv4() == remote.is_ipv4()
+                && network_address(remote, route.prefix_len) == route.destination
+        })
+        .max_by_key(|route| route.prefix_len)
+        .map(|route| (oif, route.mtu))
+        .ok_or_else(|| not_reachable_via(oif))
+}
+
+/// Parse the route messages of a routing table dump in `buf` into route entries.
+#[cfg(not(solarish))]
+fn parse_route_dump(mut buf: &[u8]) -> Result<Vec<RouteEntry>> {
+    let mut routes = Vec::new();
+    while let Ok(rtm) = rt_msghdr::try_from(buf) {
+        let msg_len = usize::from(rtm.rtm_msglen);
+        if msg_len < std::mem::size_of::<rt_msghdr>() || msg_len > buf.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Route message length {msg_len} does not fit the {} bytes left",
+                    buf.len()
+                ),
+            ));
+        }
+        let sa = &buf[std::mem::size_of::<rt_msghdr>()..msg_len];
+        buf = &buf[msg_len..];
+        if rtm.rtm_version != RTM_VERSION {
+            continue;
+        }
+        routes.extend(parse_route(&rtm, sa));
+    }
+    Ok(routes)
+}
+
+#[cfg(not(solarish))]
+pub fn routes_impl() -> Result<Vec<RouteEntry>> {
+    let mut mib = [libc::CTL_NET, PF_ROUTE, 0, AF_UNSPEC, libc::NET_RT_DUMP, 0];
+    #[allow(clippy::cast_possible_truncation)] // Six elements.
+    let mib_len = mib.len() as libc::c_uint;
+    loop {
+        // Ask for the size of the dump first.
+        let mut len = 0;
+        if unsafe {
+            libc::sysctl(
+                mib.as_mut_ptr(),
+                mib_len,
+                ptr::null_mut(),
+                ptr::from_mut(&mut len),
+                ptr::null_mut(),
+                0,
+            )
+        } == -1
+        {
+            return Err(Error::last_os_error());
+        }
+        let mut buf = vec![0u8; len];
+        if unsafe {
+            libc::sysctl(
+                mib.as_mut_ptr(),
+                mib_len,
+                buf.as_mut_ptr().cast(),
+                ptr::from_mut(&mut len),
+                ptr::null_mut(),
+                0,
+            )
+        } == -1
+        {
+            let err = Error::last_os_error();
+            if err.raw_os_error() == Some(libc::ENOMEM) {
+                // The routing table grew in the meantime.
                 continue;
             }
-            let saddr = unsafe { ptr::read_unaligned(sa.as_ptr().cast::<sockaddr>()) };
-            if saddr.sa_family != AF_LINK {
-                (_, sa) = sa.split_at(sockaddr_len(saddr.sa_family)?);
-                continue;
+            return Err(err);
+        }
+        buf.truncate(len);
+        return parse_route_dump(&buf);
+    }
+}
+
+#[cfg(solarish)]
+pub fn routes_impl() -> Result<Vec<RouteEntry>> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "Enumerating routes is not supported on Solaris and illumos",
+    ))
+}
+
+pub fn is_route_ambiguous_impl(query: RouteQuery) -> Result<bool> {
+    // `RTM_GET` only reports the single best route.
+    if_index_mtu(query).map(|_| false)
+}
+
+pub fn is_on_link_impl(query: RouteQuery) -> Result<bool> {
+    // The route has a gateway unless the destination is directly reachable.
+    Resolver::new()?.query_route(query, |buf| {
+        Ok(Some(rt_msghdr::try_from(buf)?.rtm_flags & RTF_GATEWAY == 0))
+    })
+}
+
+pub fn route_details_impl(query: RouteQuery) -> Result<RouteDetails> {
+    let mut resolver = Resolver::new()?;
+    let ((if_index, route_mtu), on_link, gateway) = resolver.query_route(query, |buf| {
+        let Some(index_mtu) = parse_reply(buf, STRICT)? else {
+            return Ok(None);
+        };
+        // The route has a gateway unless the destination is directly reachable.
+        let on_link = rt_msghdr::try_from(buf)?.rtm_flags & RTF_GATEWAY == 0;
+        Ok(Some((index_mtu, on_link, parse_gateway(buf)?)))
+    })?;
+    let ifaddrs = resolver.ifaddrs()?;
+    let (interface, route_mtu) = route_interface(query, if_index, route_mtu, ifaddrs)?;
+    Ok(RouteDetails {
+        interface_type: if_type(&interface.name, ifaddrs),
+        peer: if_peer(if_index, query.remote, ifaddrs),
+        interface,
+        route_mtu,
+        on_link,
+        gateway,
+        // The `RTA_IFA` address in the reply is one of the interface, which is not necessarily the
+        // one the kernel selects as the source, so let it select one for a connected socket.
+        source: Some(connected_source(query.remote)?),
+    })
+}
+
+/// Return the gateway in the route reply in `buf`, unless the destination is directly reachable.
+fn parse_gateway(buf: &[u8]) -> Result<Option<IpAddr>> {
+    let reply = rt_msghdr::try_from(buf)?;
+    if reply.rtm_flags & RTF_GATEWAY == 0 || reply.rtm_addrs & RTA_GATEWAY == 0 {
+        return Ok(None);
+    }
+    let msg_len = usize::from(reply.rtm_msglen).min(buf.len());
+    let mut sa = buf
+        .get(std::mem::size_of::<rt_msghdr>()..msg_len)
+        .ok_or_else(default_err)?;
+    // The socket addresses follow in the order of their `RTA_*` bits, so only the destination
+    // precedes the gateway.
+    if reply.rtm_addrs & RTA_DST != 0 {
+        let saddr: sockaddr = unsafe { read_zero_padded(sa) };
+        sa = sa
+            .get(sockaddr_len(saddr.sa_family)?..)
+            .ok_or_else(default_err)?;
+    }
+    Ok(sockaddr_ip(sa))
+}
+
+pub fn route_prefix_len_impl(query: RouteQuery) -> Result<Option<u8>> {
+    Resolver::new()?.query_route(query, |buf| parse_prefix_len(buf).map(Some))
+}
+
+/// Return the prefix length of the route in the route reply in `buf`.
+#[cfg(not(solarish))]
+fn parse_prefix_len(buf: &[u8]) -> Result<Option<u8>> {
+    let reply = rt_msghdr::try_from(buf)?;
+    let msg_len = usize::from(reply.rtm_msglen).min(buf.len());
+    let sa = buf
+        .get(std::mem::size_of::<rt_msghdr>()..msg_len)
+        .ok_or_else(default_err)?;
+    Ok(parse_route(&reply, sa).map(|route| route.prefix_len))
+}
+
+/// Solaris and illumos socket addresses don't carry their length, so the netmask can't be found.
+#[cfg(solarish)]
+#[allow(clippy::unnecessary_wraps)] // The signature is the same as on the other platforms.
+const fn parse_prefix_len(_buf: &[u8]) -> Result<Option<u8>> {
+    Ok(None)
+}
+
+pub fn change_generation_impl() -> Result<u64> {
+    // A route socket receives all routing messages, including interface changes, but also the
+    // replies to any `RTM_GET` queries, which we ignore.
+    routesocket::change_generation(
+        || RouteSocket::new(PF_ROUTE, AF_UNSPEC),
+        |msg| rt_msghdr::try_from(msg).is_ok_and(|rtm| rtm.rtm_type != RTM_GET),
+    )
+}
+
+/// A route socket that receives the routing messages for all changes.
+#[derive(Debug)]
+pub struct Watcher {
+    fd: RouteSocket,
+}
+
+impl Watcher {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            fd: RouteSocket::new(PF_ROUTE, AF_UNSPEC)?,
+        })
+    }
+
+    pub fn recv(&mut self) -> Result<ChangeEvent> {
+        let mut buf = vec![0u8; 8192];
+        loop {
+            match self.fd.read(&mut buf) {
+                Ok(len) => {
+                    if let Some(event) = parse_change(&buf[..len]) {
+                        return Ok(event);
+                    }
+                }
+                // The kernel dropped messages, so any interface may have changed.
+                Err(e) if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                    return Ok(ChangeEvent::UNKNOWN);
+                }
+                Err(e) => return Err(e),
             }
-            let sdl = unsafe { ptr::read_unaligned(sa.as_ptr().cast::<sockaddr_dl>()) };
-            return Ok((sdl.sdl_index, mtu));
         }
     }
+
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<ChangeEvent>> {
+        self.fd.set_read_timeout(Some(timeout))?;
+        let res = self.recv();
+        self.fd.set_read_timeout(None)?;
+        match res {
+            Ok(event) => Ok(Some(event)),
+            // The read timeout expired.
+            Err(e) if e.kind() == ErrorKind::TimedOut => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Parse an interface, address or route change message into a change event. Returns `None` for
+/// other messages, such as the replies to `RTM_GET` queries of any process.
+fn parse_change(msg: &[u8]) -> Option<ChangeEvent> {
+    // All routing messages start with their length, version and type.
+    match *msg.get(3)? {
+        RTM_IFINFO => {
+            let ifm: if_msghdr = unsafe { read_zero_padded(msg) };
+            Some(ChangeEvent {
+                index: Some(InterfaceIndex(ifm.ifm_index.into())),
+                mtu: usize::try_from(ifm.ifm_data.ifi_mtu)
+                    .ok()
+                    .filter(|&mtu| mtu != 0),
+            })
+        }
+        RTM_NEWADDR | RTM_DELADDR => {
+            let ifam: ifa_msghdr = unsafe { read_zero_padded(msg) };
+            Some(ChangeEvent {
+                index: Some(InterfaceIndex(ifam.ifam_index.into())),
+                mtu: None,
+            })
+        }
+        RTM_ADD | RTM_DELETE | RTM_CHANGE => {
+            let rtm: rt_msghdr = unsafe { read_zero_padded(msg) };
+            Some(ChangeEvent {
+                index: (rtm.rtm_index != 0).then(|| InterfaceIndex(rtm.rtm_index.into())),
+                mtu: None,
+            })
+        }
+        _ => None,
+    }
 }
 
-pub fn interface_and_mtu_impl(remote: IpAddr) -> Result<(String, usize)> {
-    let (if_index, mtu1) = if_index_mtu(remote)?;
-    let (if_name, mtu2) = if_name_mtu(if_index.into())?;
-    Ok((if_name, mtu1.or(mtu2).ok_or_else(default_err)?))
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{ErrorKind, Result},
+        net::{IpAddr, Ipv4Addr, Ipv6Addr},
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        bsd::{
+            bindings::{if_msghdr, rt_msghdr},
+            if_index_mtu, if_name_mtu, interface_index_impl, parse_change, parse_gateway,
+            parse_reply, read_reply, route_via, sockaddr_len, IfAddrPtr, IfAddrs, RouteMessage,
+            SockaddrStorage, AF_INET, RTA_GATEWAY, RTA_IFA, RTA_NETMASK, RTF_BLACKHOLE,
+            RTF_GATEWAY, RTF_REJECT, RTM_ADD, RTM_GET, RTM_IFINFO,
+        },
+        route_if_name_err, ChangeEvent, InterfaceIndex, RouteEntry, RouteQuery,
+    };
+
+    #[test]
+    fn routes_via_interface() {
+        let route = |destination: [u8; 4], prefix_len, oif_index, mtu| RouteEntry {
+            destination: IpAddr::V4(destination.into()),
+            prefix_len,
+            gateway: None,
+            oif_index: InterfaceIndex(oif_index),
+            mtu,
+        };
+        let routes = [
+            route([0, 0, 0, 0], 0, 1, None),
+            route([192, 0, 2, 0], 24, 2, Some(1400)),
+            route([192, 0, 2, 128], 25, 3, None),
+            route([198, 51, 100, 0], 24, 2, None),
+        ];
+        let remote = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 200));
+        // The best route goes through interface 3, but there are routes through 1 and 2 as well.
+        assert_eq!(route_via(remote, 3, &routes).unwrap(), (3, None));
+        assert_eq!(route_via(remote, 2, &routes).unwrap(), (2, Some(1400)));
+        assert_eq!(route_via(remote, 1, &routes).unwrap(), (1, None));
+        assert_eq!(
+            route_via(remote, 4, &routes).unwrap_err().to_string(),
+            crate::not_reachable_via(4).to_string()
+        );
+        // Routes of the other family don't match.
+        let remote = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        assert!(route_via(remote, 1, &routes).is_err());
+    }
+
+    #[test]
+    fn changes_are_parsed() {
+        fn bytes<T>(value: &T) -> &[u8] {
+            unsafe {
+                std::slice::from_raw_parts(
+                    std::ptr::from_ref(value).cast(),
+                    std::mem::size_of::<T>(),
+                )
+            }
+        }
+
+        let mut ifm = if_msghdr {
+            ifm_type: RTM_IFINFO,
+            ifm_index: 2,
+            ..Default::default()
+        };
+        ifm.ifm_data.ifi_mtu = 1400;
+        assert_eq!(
+            parse_change(bytes(&ifm)),
+            Some(ChangeEvent {
+                index: Some(InterfaceIndex(2)),
+                mtu: Some(1400)
+            })
+        );
+        let mut rtm = rt_msghdr {
+            rtm_type: RTM_ADD,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_change(bytes(&rtm)),
+            Some(ChangeEvent {
+                index: None,
+                mtu: None
+            })
+        );
+        // Replies to route queries are not changes.
+        rtm.rtm_type = RTM_GET;
+        assert_eq!(parse_change(bytes(&rtm)), None);
+        assert_eq!(parse_change(&[]), None);
+    }
+
+    #[test]
+    fn non_utf8_name_is_lossy() {
+        let name = b"en\xff0\0";
+        let mut ifa: libc::ifaddrs = unsafe { std::mem::zeroed() };
+        ifa.ifa_name = name.as_ptr().cast_mut().cast();
+        let ptr = IfAddrPtr {
+            ptr: std::ptr::from_mut(&mut ifa),
+            _ref: std::marker::PhantomData,
+        };
+        assert_eq!(ptr.name(), "en\u{fffd}0");
+    }
+
+    #[test]
+    fn flowinfo_is_serialized() {
+        let sa = SockaddrStorage::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 0x0b80_1234, 0);
+        assert_eq!(u32::from_be(unsafe { sa.sin6.sin6_flowinfo }), 0x0b80_1234);
+    }
+
+    #[test]
+    fn netmask_is_serialized() {
+        let host = RouteMessage::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)).into(), 0).unwrap();
+        assert_eq!(host.rtm.rtm_addrs & RTA_NETMASK, 0);
+
+        let query = RouteQuery {
+            prefix_len: 8,
+            ..IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)).into()
+        };
+        let net = RouteMessage::new(query, 0).unwrap();
+        assert_eq!(net.rtm.rtm_addrs & RTA_NETMASK, RTA_NETMASK);
+        let sa_len = net.len() - host.len();
+        let mask = unsafe {
+            std::ptr::read_unaligned(net.sa[sa_len..].as_ptr().cast::<libc::sockaddr_in>())
+        };
+        assert_eq!(mask.sin_addr.s_addr, u32::from_ne_bytes([255, 0, 0, 0]));
+    }
+
+    #[test]
+    fn source_is_serialized() {
+        let remote = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let host = RouteMessage::new(remote.into(), 0).unwrap();
+        assert_eq!(host.rtm.rtm_addrs & RTA_IFA, 0);
+
+        let query = RouteQuery {
+            src: Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))),
+            ..remote.into()
+        };
+        let from = RouteMessage::new(query, 0).unwrap();
+        assert_eq!(from.rtm.rtm_addrs & RTA_IFA, RTA_IFA);
+        let sa_len = from.len() - host.len();
+        let src = unsafe {
+            std::ptr::read_unaligned(from.sa[sa_len..].as_ptr().cast::<libc::sockaddr_in>())
+        };
+        assert_eq!(src.sin_addr.s_addr, u32::from_ne_bytes([10, 0, 0, 2]));
+    }
+
+    #[test]
+    fn index_type_is_u32() {
+        // Interface indices are `u32` from the route reply to the name lookup, even though the BSD
+        // route messages carry them as `u16`.
+        type IndexMtu = (u32, Option<usize>);
+        type NameMtu = (String, Option<usize>);
+        let _: fn(RouteQuery) -> Result<IndexMtu> = if_index_mtu;
+        let _: fn(&[u8], bool) -> Result<Option<IndexMtu>> = parse_reply;
+        let _: fn(u32, &IfAddrs) -> Result<NameMtu> = if_name_mtu;
+        let _: fn(RouteQuery) -> Result<u32> = interface_index_impl;
+    }
+
+    #[test]
+    fn bad_index_in_error() {
+        let err = if_name_mtu(u32::MAX, &IfAddrs::new().unwrap())
+            .map_err(|e| route_if_name_err(u32::MAX, &e))
+            .unwrap_err();
+        assert!(err.to_string().contains(&u32::MAX.to_string()), "{err}");
+    }
+
+    #[test]
+    fn truncated_reply() {
+        let reply = rt_msghdr {
+            #[allow(clippy::cast_possible_truncation)] // Small enough.
+            rtm_msglen: (std::mem::size_of::<rt_msghdr>() + 64) as u16,
+            rtm_index: 1,
+            ..Default::default()
+        };
+        let buf = unsafe {
+            std::slice::from_raw_parts(
+                std::ptr::from_ref(&reply).cast::<u8>(),
+                std::mem::size_of::<rt_msghdr>(),
+            )
+        };
+        let err = parse_reply(buf, false).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        // A reply that is shorter than its header.
+        let err = parse_reply(&buf[..buf.len() - 1], false).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn unreachable_routes() {
+        for (flags, errno) in [
+            (RTF_REJECT, libc::EHOSTUNREACH),
+            (RTF_BLACKHOLE, libc::ENETUNREACH),
+        ] {
+            let reply = rt_msghdr {
+                #[allow(clippy::cast_possible_truncation)] // Small enough.
+                rtm_msglen: std::mem::size_of::<rt_msghdr>() as u16,
+                rtm_flags: flags,
+                rtm_index: 1,
+                ..Default::default()
+            };
+            let buf = reply_bytes(&reply, &[]);
+            assert_eq!(
+                parse_reply(&buf, false).unwrap_err().raw_os_error(),
+                Some(errno)
+            );
+        }
+    }
+
+    /// Serialize `reply` followed by `sa`, leaving `rtm_msglen` as is.
+    fn reply_bytes(reply: &rt_msghdr, sa: &[u8]) -> Vec<u8> {
+        let hdr = unsafe {
+            std::slice::from_raw_parts(
+                std::ptr::from_ref(reply).cast::<u8>(),
+                std::mem::size_of::<rt_msghdr>(),
+            )
+        };
+        [hdr, sa].concat()
+    }
+
+    #[test]
+    fn unrelated_messages_time_out() {
+        let query = RouteMessage::new(IpAddr::V4(Ipv4Addr::LOCALHOST).into(), 1).unwrap();
+        // A message about some other routing change, which never matches the query.
+        let other = rt_msghdr {
+            #[allow(clippy::cast_possible_truncation)] // Small enough.
+            rtm_msglen: std::mem::size_of::<rt_msghdr>() as u16,
+            rtm_version: query.version(),
+            rtm_type: RTM_ADD,
+            rtm_seq: query.rtm.rtm_seq + 1,
+            ..Default::default()
+        };
+        let msg = reply_bytes(&other, &[]);
+        let mut reads = 0;
+        let err = read_reply(
+            |buf| {
+                reads += 1;
+                buf[..msg.len()].copy_from_slice(&msg);
+                Ok(msg.len())
+            },
+            Instant::now() + Duration::from_millis(10),
+            &query,
+            |_| Ok(Some(())),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(reads > 1);
+    }
+
+    #[test]
+    fn strict_rejects_short_msglen() {
+        let reply = rt_msghdr {
+            #[allow(clippy::cast_possible_truncation)] // Small enough.
+            rtm_msglen: std::mem::size_of::<rt_msghdr>() as u16,
+            rtm_index: 1,
+            ..Default::default()
+        };
+        let buf = reply_bytes(&reply, &[0; 8]);
+        assert_eq!(parse_reply(&buf, false).unwrap(), Some((1, None)));
+        let err = parse_reply(&buf, true).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn gateway_follows_destination() {
+        let sa_len = sockaddr_len(AF_INET).unwrap();
+        let mut sa = vec![0; 2 * sa_len];
+        for (i, ip) in [Ipv4Addr::new(198, 18, 0, 1), Ipv4Addr::new(192, 0, 2, 2)]
+            .into_iter()
+            .enumerate()
+        {
+            let sin = SockaddrStorage::new(IpAddr::V4(ip), 0, 0);
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    std::ptr::from_ref(&sin).cast::<u8>(),
+                    std::mem::size_of::<libc::sockaddr_in>(),
+                )
+            };
+            sa[i * sa_len..i * sa_len + bytes.len()].copy_from_slice(bytes);
+        }
+        let mut reply = rt_msghdr {
+            #[allow(clippy::cast_possible_truncation)] // Small enough.
+            rtm_msglen: (std::mem::size_of::<rt_msghdr>() + sa.len()) as u16,
+            rtm_addrs: libc::RTA_DST | RTA_GATEWAY,
+            rtm_flags: RTF_GATEWAY,
+            rtm_index: 1,
+            ..Default::default()
+        };
+        let gateway = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+        assert_eq!(
+            parse_gateway(&reply_bytes(&reply, &sa)).unwrap(),
+            Some(gateway)
+        );
+
+        // Directly reachable destinations have a link-layer gateway, which is not reported.
+        reply.rtm_flags = 0;
+        assert_eq!(parse_gateway(&reply_bytes(&reply, &sa)).unwrap(), None);
+    }
+
+    #[test]
+    #[cfg(not(solarish))]
+    fn strict_rejects_missing_sockaddr() {
+        // Announce a destination and a gateway, but only include the destination.
+        let sin = SockaddrStorage::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0, 0);
+        let sa = unsafe {
+            std::slice::from_raw_parts(
+                std::ptr::from_ref(&sin).cast::<u8>(),
+                std::mem::size_of::<libc::sockaddr_in>(),
+            )
+        };
+        let reply = rt_msghdr {
+            #[allow(clippy::cast_possible_truncation)] // Small enough.
+            rtm_msglen: (std::mem::size_of::<rt_msghdr>() + sa.len()) as u16,
+            rtm_addrs: libc::RTA_DST | libc::RTA_GATEWAY,
+            rtm_index: 1,
+            ..Default::default()
+        };
+        let buf = reply_bytes(&reply, sa);
+        assert_eq!(parse_reply(&buf, false).unwrap(), Some((1, None)));
+        let err = parse_reply(&buf, true).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    /// The checked-in bindings that the `vendored-bindings` feature uses instead of bindgen.
+    #[cfg(all(
+        target_os = "macos",
+        feature = "bindgen",
+        not(feature = "vendored-bindings")
+    ))]
+    #[allow(dead_code, non_camel_case_types, clippy::struct_field_names)]
+    mod vendored {
+        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/bindings/macos.rs"));
+    }
+
+    #[test]
+    #[cfg(all(
+        target_os = "macos",
+        feature = "bindgen",
+        not(feature = "vendored-bindings")
+    ))]
+    fn vendored_bindings_match() {
+        use crate::bsd::bindings;
+
+        assert_same_layout!(
+            bindings::rt_metrics,
+            vendored::rt_metrics,
+            [
+                rmx_locks,
+                rmx_mtu,
+                rmx_hopcount,
+                rmx_expire,
+                rmx_recvpipe,
+                rmx_sendpipe,
+                rmx_ssthresh,
+                rmx_rtt,
+                rmx_rttvar,
+                rmx_pksent,
+                rmx_state,
+                rmx_filler
+            ]
+        );
+        assert_same_layout!(
+            bindings::rt_msghdr,
+            vendored::rt_msghdr,
+            [
+                rtm_msglen,
+                rtm_version,
+                rtm_type,
+                rtm_index,
+                rtm_flags,
+                rtm_addrs,
+                rtm_pid,
+                rtm_seq,
+                rtm_errno,
+                rtm_use,
+                rtm_inits,
+                rtm_rmx
+            ]
+        );
+        assert_same_layout!(
+            bindings::if_data,
+            vendored::if_data,
+            [ifi_type, ifi_mtu, ifi_lastchange, ifi_reserved2]
+        );
+        assert_same_layout!(
+            bindings::if_msghdr,
+            vendored::if_msghdr,
+            [
+                ifm_msglen,
+                ifm_version,
+                ifm_type,
+                ifm_addrs,
+                ifm_flags,
+                ifm_index,
+                ifm_data
+            ]
+        );
+        assert_same_layout!(
+            bindings::ifa_msghdr,
+            vendored::ifa_msghdr,
+            [
+                ifam_msglen,
+                ifam_version,
+                ifam_type,
+                ifam_addrs,
+                ifam_flags,
+                ifam_index,
+                ifam_metric
+            ]
+        );
+        for (generated, vendored) in [
+            (bindings::RTM_VERSION, vendored::RTM_VERSION),
+            (bindings::RTM_ADD, vendored::RTM_ADD),
+            (bindings::RTM_DELETE, vendored::RTM_DELETE),
+            (bindings::RTM_CHANGE, vendored::RTM_CHANGE),
+            (bindings::RTM_GET, vendored::RTM_GET),
+            (bindings::RTM_NEWADDR, vendored::RTM_NEWADDR),
+            (bindings::RTM_DELADDR, vendored::RTM_DELADDR),
+            (bindings::RTM_IFINFO, vendored::RTM_IFINFO),
+            (bindings::RTA_DST, vendored::RTA_DST),
+            (bindings::RTA_GATEWAY, vendored::RTA_GATEWAY),
+            (bindings::RTA_NETMASK, vendored::RTA_NETMASK),
+            (bindings::RTA_IFP, vendored::RTA_IFP),
+            (bindings::RTA_IFA, vendored::RTA_IFA),
+            (bindings::RTAX_DST, vendored::RTAX_DST),
+            (bindings::RTAX_GATEWAY, vendored::RTAX_GATEWAY),
+            (bindings::RTAX_NETMASK, vendored::RTAX_NETMASK),
+            (bindings::RTAX_MAX, vendored::RTAX_MAX),
+            (bindings::RTF_GATEWAY, vendored::RTF_GATEWAY),
+            (bindings::RTF_REJECT, vendored::RTF_REJECT),
+            (bindings::RTF_BLACKHOLE, vendored::RTF_BLACKHOLE),
+            (bindings::IFT_ETHER, vendored::IFT_ETHER),
+            (bindings::IFT_PPP, vendored::IFT_PPP),
+            (bindings::IFT_GIF, vendored::IFT_GIF),
+            (bindings::IFT_LOOP, vendored::IFT_LOOP),
+        ] {
+            assert_eq!(generated, vendored);
+        }
+    }
 }
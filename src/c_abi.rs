@@ -0,0 +1,160 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A C ABI for [`crate::interface_and_mtu`]. See `include/mtu.h` for the corresponding header, and
+//! `tests/c_abi.c` for a C caller.
+
+use std::{
+    io::{Error, ErrorKind},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    ptr,
+};
+
+use libc::{c_char, c_int, EINVAL, EIO, ENOENT, ERANGE};
+
+use crate::{interface_and_mtu, Interface};
+
+#[cfg(not(target_os = "windows"))]
+const AF_INET: c_int = libc::AF_INET;
+#[cfg(not(target_os = "windows"))]
+const AF_INET6: c_int = libc::AF_INET6;
+#[cfg(target_os = "windows")]
+const AF_INET: c_int = 2;
+#[cfg(target_os = "windows")]
+const AF_INET6: c_int = 23;
+
+/// Map an error to an errno-like code.
+fn errno(err: &Error) -> c_int {
+    err.raw_os_error().unwrap_or_else(|| match err.kind() {
+        ErrorKind::NotFound => ENOENT,
+        ErrorKind::InvalidInput => EINVAL,
+        _ => EIO,
+    })
+}
+
+/// Return the name and MTU of the outgoing network interface towards a remote destination.
+///
+/// `af` is `AF_INET` or `AF_INET6`, and `addr` points to the 4- or 16-byte address in network byte
+/// order. On success, the NUL-terminated interface name is written to `name_out` (which has room
+/// for `name_len` bytes, including the terminating NUL), the MTU is written to `mtu_out`, and 0 is
+/// returned. Otherwise, an errno-like code is returned: `EINVAL` for invalid arguments, `ERANGE` if
+/// `name_out` is too small, and `ENOENT` or an OS error code if the MTU cannot be determined.
+///
+/// # Safety
+///
+/// `addr` must point to at least 4 (for `AF_INET`) or 16 (for `AF_INET6`) readable bytes,
+/// `name_out` must point to at least `name_len` writable bytes, and `mtu_out` must be valid for
+/// writes. `name_out` and `mtu_out` may be null if the caller is not interested in them.
+#[no_mangle]
+pub unsafe extern "C" fn mtu_interface_and_mtu(
+    af: c_int,
+    addr: *const u8,
+    name_out: *mut c_char,
+    name_len: usize,
+    mtu_out: *mut usize,
+) -> c_int {
+    if addr.is_null() {
+        return EINVAL;
+    }
+    let remote = match af {
+        AF_INET => IpAddr::V4(Ipv4Addr::from(ptr::read_unaligned(addr.cast::<[u8; 4]>()))),
+        AF_INET6 => IpAddr::V6(Ipv6Addr::from(ptr::read_unaligned(addr.cast::<[u8; 16]>()))),
+        _ => return EINVAL,
+    };
+    let Interface { name, mtu, .. } = match interface_and_mtu(remote) {
+        Ok(res) => res,
+        Err(err) => return errno(&err),
+    };
+    if !name_out.is_null() {
+        if name.len() >= name_len {
+            return ERANGE;
+        }
+        ptr::copy_nonoverlapping(name.as_ptr(), name_out.cast::<u8>(), name.len());
+        *name_out.add(name.len()) = 0;
+    }
+    if !mtu_out.is_null() {
+        *mtu_out = mtu;
+    }
+    0
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        ffi::CStr,
+        net::{IpAddr, Ipv4Addr, Ipv6Addr},
+        ptr,
+    };
+
+    use libc::{c_char, c_int, EINVAL, ERANGE};
+
+    use super::{AF_INET, AF_INET6};
+    use crate::interface_and_mtu;
+
+    // Call through the exported symbol. CI also links and runs the C caller in `tests/c_abi.c`.
+    extern "C" {
+        fn mtu_interface_and_mtu(
+            af: c_int,
+            addr: *const u8,
+            name_out: *mut c_char,
+            name_len: usize,
+            mtu_out: *mut usize,
+        ) -> c_int;
+    }
+
+    fn check_loopback(af: c_int, addr: &[u8], remote: IpAddr) {
+        let mut name: [c_char; 64] = [0; 64];
+        let mut mtu = 0;
+        let res = unsafe {
+            mtu_interface_and_mtu(af, addr.as_ptr(), name.as_mut_ptr(), name.len(), &mut mtu)
+        };
+        assert_eq!(res, 0);
+        let name = unsafe { CStr::from_ptr(name.as_ptr()) }.to_str().unwrap();
+        let interface = interface_and_mtu(remote).unwrap();
+        assert_eq!((name, mtu), (interface.name.as_str(), interface.mtu));
+    }
+
+    #[test]
+    fn loopback_v4() {
+        let ip = Ipv4Addr::LOCALHOST;
+        check_loopback(AF_INET, &ip.octets(), IpAddr::V4(ip));
+    }
+
+    #[test]
+    fn loopback_v6() {
+        let ip = Ipv6Addr::LOCALHOST;
+        check_loopback(AF_INET6, &ip.octets(), IpAddr::V6(ip));
+    }
+
+    #[test]
+    fn invalid_args() {
+        let addr = Ipv4Addr::LOCALHOST.octets();
+        let res = unsafe {
+            mtu_interface_and_mtu(-1, addr.as_ptr(), ptr::null_mut(), 0, ptr::null_mut())
+        };
+        assert_eq!(res, EINVAL);
+        let res = unsafe {
+            mtu_interface_and_mtu(AF_INET, ptr::null(), ptr::null_mut(), 0, ptr::null_mut())
+        };
+        assert_eq!(res, EINVAL);
+    }
+
+    #[test]
+    fn name_too_short() {
+        let addr = Ipv4Addr::LOCALHOST.octets();
+        let mut name: [c_char; 1] = [0; 1];
+        let res = unsafe {
+            mtu_interface_and_mtu(
+                AF_INET,
+                addr.as_ptr(),
+                name.as_mut_ptr(),
+                name.len(),
+                ptr::null_mut(),
+            )
+        };
+        assert_eq!(res, ERANGE);
+    }
+}
@@ -0,0 +1,36 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Common MTU values, for example as fallbacks for [`crate::interface_and_mtu_or`] or to sanity
+//! check the MTUs returned by this crate.
+//!
+//! ```
+//! # use std::net::{IpAddr, Ipv6Addr};
+//! use mtu::consts::IPV6_MIN_MTU;
+//!
+//! let mtu = mtu::interface_and_mtu_or(IpAddr::V6(Ipv6Addr::LOCALHOST), IPV6_MIN_MTU)
+//!     .unwrap()
+//!     .mtu;
+//! assert!(mtu >= IPV6_MIN_MTU);
+//! ```
+
+/// The MTU of Ethernet links, see RFC 894.
+pub const ETHERNET_MTU: usize = 1500;
+
+/// The smallest MTU of any link that carries IPv6, see RFC 8200, Section 5.
+pub const IPV6_MIN_MTU: usize = 1280;
+
+/// The size of the largest datagram that every IPv4 host must accept, see RFC 791.
+///
+/// IPv4 links may have MTUs down to 68 bytes, but paths with an MTU below 576 bytes are rare, which
+/// makes this the conventional conservative MTU for IPv4.
+pub const IPV4_MIN_MTU: usize = 576;
+
+/// The MTU of loopback interfaces on Linux and Android.
+///
+/// This is not standardized. Loopback interfaces on macOS and the BSDs have an MTU of 16,384 bytes,
+/// and Windows reports even larger values than Linux.
+pub const LOOPBACK_TYPICAL: usize = 65536;
@@ -9,43 +9,114 @@
 //!
 //! # Usage
 //!
-//! This crate exports a single function `interface_and_mtu` that returns the name and
+//! The main entry point is `interface_and_mtu`, which returns the `Interface` on which traffic
+//! towards a remote destination identified by an `IpAddr` leaves the host. An `Interface` carries
+//! the name, index and
 //! [maximum transmission unit (MTU)](https://en.wikipedia.org/wiki/Maximum_transmission_unit)
-//! of the outgoing network interface towards a remote destination identified by an `IpAddr`.
+//! of the interface, and, where the operating system reports them, its link-layer address, flags,
+//! kind and link speed.
+//!
+//! Variants of `interface_and_mtu` constrain the lookup, for example to a local source address
+//! (`interface_and_mtu_from`), an outgoing interface (`interface_and_mtu_via_index`) or a routing
+//! table (`interface_and_mtu_in_table`, `interface_and_mtu_in_fib`,
+//! `interface_and_mtu_in_rdomain`), or start from a connected socket
+//! (`interface_and_mtu_from_raw_fd`, `interface_and_mtu_from_raw_socket`). Use `MtuResolver` or
+//! `MtuCache` to amortize repeated lookups.
+//!
+//! Beyond the interface, the crate exposes:
+//!
+//! * `route_info` and `explain` for the next hop, source address and route MTU of a destination;
+//! * `max_datagram_size`, `max_udp_payload` and `quic_initial_mtu` to turn an MTU into a UDP
+//!   payload size;
+//! * `list_interfaces`, `InterfaceHandle` and `interface_stats` for the interfaces of the host;
+//! * `MtuWatcher`, `change_generation` and `changed` to detect routing and MTU changes.
 //!
 //! # Example
 //! ```
 //! # use std::net::{IpAddr, Ipv4Addr};
 //! let destination = IpAddr::V4(Ipv4Addr::LOCALHOST);
-//! let (name, mtu): (String, usize) = mtu::interface_and_mtu(destination).unwrap();
-//! println!("MTU towards {destination} is {mtu} on {name}");
+//! let interface = mtu::interface_and_mtu(destination).unwrap();
+//! println!(
+//!     "MTU towards {destination} is {} on {}",
+//!     interface.mtu, interface.name
+//! );
 //! ```
 //!
 //! # Supported Platforms
 //!
 //! * Linux
+//! * Android
 //! * macOS
 //! * Windows
 //! * FreeBSD
+//! * DragonFly BSD
 //! * NetBSD
 //! * OpenBSD
 //! * Solaris
+//! * illumos
+//!
+//! Android shares the implementation of Linux. CI builds and lints it for all Android
+//! architectures, and runs the unit tests on an x86-64 Android emulator.
 //!
 //! # Notes
 //!
 //! The returned MTU may exceed the maximum IP packet size of 65,535 bytes on some platforms for
 //! some remote destinations. (For example, loopback destinations on Windows.)
 //!
+//! Use `interface_and_mtu_normalized` to cap the MTU at 65,535 bytes.
+//!
 //! The returned interface name is obtained from the operating system.
 //!
+//! No platform requires elevated privileges for lookups. Sandboxes may still deny them, in which
+//! case `interface_and_mtu2` returns `MtuError::PermissionDenied` with the OS error code.
+//!
+//! When traffic goes through a proxy, the relevant MTU is the one towards the proxy, not towards
+//! the ultimate destination, so pass the address of the proxy to `interface_and_mtu`. Use
+//! `proxy_hint` to also record the ultimate destination in the result, for diagnostics.
+//!
+//! Enable the `strict` feature to validate the consistency of every reply from the operating system
+//! and fail with `ErrorKind::InvalidData` on any mismatch, instead of parsing replies on a
+//! best-effort basis.
+//!
+//! Enable the `core-only` feature to make the `parse` module public. It contains the route message
+//! parsing helpers, which only depend on `core` and can be used with a custom transport.
+//!
+//! Enable the `mock` feature to make `set_mock` and `clear_mock` available, which replace the
+//! operating system lookup in `interface_and_mtu` with a custom function. This is meant for testing
+//! code that depends on the MTU, and should not be enabled in production builds.
+//!
+//! Enable the `serde` feature to derive `Serialize` and `Deserialize` for `Interface`,
+//! `InterfaceFlags` and `InterfaceKind`, for example to include them in telemetry.
+//!
+//! Enable the `tokio` feature to make `interface_and_mtu_async` available, which waits for the
+//! operating system on the Tokio runtime instead of blocking the calling thread, and
+//! `watch_stream`, which yields the changes of the local network interfaces as an async stream.
+//!
+//! Enable the `vendored-bindings` feature to use the checked-in bindings in `bindings/` on Linux,
+//! Android and macOS instead of generating them with bindgen at build time, which needs libclang
+//! and the system headers. The `gecko` feature enables it. Disable the default `bindgen` feature
+//! to also drop the build dependency on bindgen. There are no checked-in bindings for the BSDs and
+//! Solaris, which always need the `bindgen` feature.
+//!
 //! # Contributing
 //!
 //! We're happy to receive PRs that improve this crate. Please take a look at our [community
 //! guidelines](CODE_OF_CONDUCT.md) beforehand.
 
+#[cfg(not(target_os = "windows"))]
+use std::ffi::CStr;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::os::fd::AsFd;
+#[cfg(not(target_os = "windows"))]
+use std::os::fd::{AsRawFd as _, BorrowedFd};
+#[cfg(target_os = "windows")]
+use std::os::windows::io::BorrowedSocket;
 use std::{
+    collections::HashMap,
+    ffi::CString,
     io::{Error, ErrorKind, Result},
-    net::IpAddr,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket},
+    time::{Duration, Instant},
 };
 
 #[cfg(not(target_os = "windows"))]
@@ -57,6 +128,44 @@ macro_rules! asserted_const_with_type {
     };
 }
 
+/// Assert that the structs `$a` and `$b` have the same size and alignment, and that the listed
+/// fields are at the same offsets and have the same sizes in both.
+#[cfg(all(
+    test,
+    feature = "bindgen",
+    not(feature = "vendored-bindings"),
+    any(target_os = "linux", target_os = "android", target_os = "macos")
+))]
+macro_rules! assert_same_layout {
+    ($a:ty, $b:ty, [$($field:ident),+ $(,)?]) => {{
+        const fn pointee_size<T>(_: *const T) -> usize {
+            std::mem::size_of::<T>()
+        }
+
+        let name = stringify!($a);
+        assert_eq!(std::mem::size_of::<$a>(), std::mem::size_of::<$b>(), "size of {name}");
+        assert_eq!(std::mem::align_of::<$a>(), std::mem::align_of::<$b>(), "alignment of {name}");
+        let a = std::mem::MaybeUninit::<$a>::uninit();
+        let b = std::mem::MaybeUninit::<$b>::uninit();
+        $(
+            let field = stringify!($field);
+            // SAFETY: `addr_of!` does not read the uninitialized fields.
+            let (fa, fb) = unsafe {
+                (
+                    std::ptr::addr_of!((*a.as_ptr()).$field),
+                    std::ptr::addr_of!((*b.as_ptr()).$field),
+                )
+            };
+            // SAFETY: Both pointers are into the same struct.
+            let (oa, ob) = unsafe {
+                (fa.byte_offset_from(a.as_ptr()), fb.byte_offset_from(b.as_ptr()))
+            };
+            assert_eq!(oa, ob, "offset of {name}::{field}");
+            assert_eq!(pointee_size(fa), pointee_size(fb), "size of {name}::{field}");
+        )+
+    }};
+}
+
 #[cfg(any(target_os = "macos", bsd))]
 mod bsd;
 
@@ -69,131 +178,4803 @@ mod windows;
 #[cfg(not(target_os = "windows"))]
 mod routesocket;
 
+#[cfg(any(target_os = "macos", bsd, target_os = "linux", target_os = "android"))]
+mod linklocal;
+
+mod pktinfo;
+
+pub mod consts;
+
+#[cfg(feature = "core-only")]
+pub mod parse;
+#[cfg(not(feature = "core-only"))]
+mod parse;
+
+#[cfg(feature = "c_abi")]
+mod c_abi;
+
+#[cfg(feature = "mock")]
+mod mock;
+
+#[cfg(all(feature = "tokio", any(target_os = "macos", bsd)))]
+use bsd::interface_and_mtu_async_impl;
 #[cfg(any(target_os = "macos", bsd))]
-use bsd::interface_and_mtu_impl;
+use bsd::{
+    change_generation_impl, family_mtus_impl, interface_and_mtu_by_index_impl,
+    interface_and_mtu_impl, interface_and_route_mtu_impl, interface_by_index_impl,
+    interface_index_impl, interface_stats_impl, interfaces_impl, is_on_link_impl,
+    is_route_ambiguous_impl, local_addrs_impl, mtu_snapshot_impl, route_details_impl,
+    route_prefix_len_impl, routes_impl, Resolver, Watcher,
+};
+#[cfg(all(feature = "tokio", any(target_os = "linux", target_os = "android")))]
+use linux::interface_and_mtu_async_impl;
 #[cfg(any(target_os = "linux", target_os = "android"))]
-use linux::interface_and_mtu_impl;
-#[cfg(target_os = "windows")]
+use linux::{
+    change_generation_impl, family_mtus_impl, interface_and_mtu_by_index_impl,
+    interface_and_mtu_impl, interface_and_mtu_in_table_impl, interface_and_route_mtu_impl,
+    interface_by_index_impl, interface_index_impl, interface_stats_impl, interfaces_impl,
+    is_on_link_impl, is_route_ambiguous_impl, local_addrs_impl, mtu_snapshot_impl,
+    route_details_impl, route_prefix_len_impl, routes_impl, source_and_mtu_via_impl, Resolver,
+    Watcher,
+};
+#[cfg(feature = "mock")]
+pub use mock::{clear_mock, set_mock};
+#[cfg(all(feature = "tokio", target_os = "windows"))]
+use windows::interface_and_mtu_async_impl;
+#[cfg(all(test, feature = "mock", target_os = "windows"))]
 use windows::interface_and_mtu_impl;
+#[cfg(target_os = "windows")]
+use windows::{
+    change_generation_impl, family_mtus_impl, interface_and_mtu_by_index_impl,
+    interface_and_route_mtu_impl, interface_by_index_impl, interface_index_impl,
+    interface_stats_impl, interfaces_impl, is_on_link_impl, is_route_ambiguous_impl,
+    local_addrs_impl, mtu_snapshot_impl, route_details_impl, route_prefix_len_impl, routes_impl,
+    socket_ip, Resolver, Watcher,
+};
+
+/// The parameters of a query for the outgoing interface towards a remote destination.
+#[derive(Clone, Copy, Debug)]
+struct RouteQuery {
+    /// The remote destination.
+    remote: IpAddr,
+    /// The IPv6 traffic class and flow label, as returned by [`std::net::SocketAddrV6::flowinfo`].
+    /// Ignored for IPv4.
+    flowinfo: u32,
+    /// The prefix length of the destination, which is the full address length unless the query is
+    /// for a network.
+    #[cfg_attr(target_os = "windows", allow(dead_code))] // No prefix-aware route query there.
+    prefix_len: u8,
+    /// The index of the interface the route needs to go through, if any.
+    oif: Option<u32>,
+    /// The source address of the traffic, if any. Only set to an address of the same family as
+    /// `remote`.
+    src: Option<IpAddr>,
+    /// The FIB to query instead of the one of the process, if any.
+    #[cfg(target_os = "freebsd")]
+    fib: Option<u16>,
+    /// The OpenBSD routing table to query instead of the default one, if any.
+    #[cfg(target_os = "openbsd")]
+    rdomain: Option<u16>,
+}
+
+impl From<IpAddr> for RouteQuery {
+    fn from(remote: IpAddr) -> Self {
+        Self {
+            remote,
+            flowinfo: 0,
+            prefix_len: max_prefix_len(remote),
+            oif: None,
+            src: None,
+            #[cfg(target_os = "freebsd")]
+            fib: None,
+            #[cfg(target_os = "openbsd")]
+            rdomain: None,
+        }
+    }
+}
+
+impl RouteQuery {
+    /// Return the query with IPv4-mapped IPv6 addresses replaced by the IPv4 addresses they map,
+    /// since that is where a dual-stack socket sends traffic for them. The prefix length shrinks
+    /// accordingly.
+    fn canonical(self) -> Self {
+        let remote = self.remote.to_canonical();
+        Self {
+            remote,
+            prefix_len: self
+                .prefix_len
+                .saturating_sub(max_prefix_len(self.remote) - max_prefix_len(remote)),
+            src: self.src.map(|src| src.to_canonical()),
+            ..self
+        }
+    }
+
+    /// Return the destination if it is an IPv4 link-local address and the query is not constrained
+    /// to an interface, so that [`linklocal`] needs to look for the interface on its link.
+    #[cfg(any(target_os = "macos", bsd, target_os = "linux", target_os = "android"))]
+    const fn link_local_v4(&self) -> Option<Ipv4Addr> {
+        match self.remote {
+            IpAddr::V4(ip) if ip.is_link_local() && self.oif.is_none() => Some(ip),
+            _ => None,
+        }
+    }
+
+    /// Return the IPv6 scope ID of the destination, which is the index of the interface the route
+    /// needs to go through for destinations with link scope, and 0 otherwise. Link-local addresses
+    /// are only unique per link, so the kernel needs the scope ID to find their route.
+    ///
+    /// On Linux, the interface index is passed as `RTA_OIF` instead.
+    #[cfg(any(target_os = "macos", bsd, target_os = "windows"))]
+    fn scope_id(&self) -> u32 {
+        match self.remote {
+            IpAddr::V6(ip) if has_link_scope(ip) => self.oif.unwrap_or(0),
+            _ => 0,
+        }
+    }
+}
+
+/// Return whether `ip` is a unicast link-local address or an interface-local or link-local
+/// multicast address.
+#[cfg(any(target_os = "macos", bsd, target_os = "windows"))]
+const fn has_link_scope(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    first & 0xffc0 == 0xfe80 || (first & 0xff00 == 0xff00 && matches!(first & 0xf, 1 | 2))
+}
+
+/// Return the address length of `ip` in bits.
+const fn max_prefix_len(ip: IpAddr) -> u8 {
+    match ip {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// Clear all but the first `prefix_len` bits of `ip`.
+fn network_address(ip: IpAddr, prefix_len: u8) -> IpAddr {
+    let host_bits = u32::from(max_prefix_len(ip).saturating_sub(prefix_len));
+    match ip {
+        IpAddr::V4(ip) => {
+            IpAddr::V4((u32::from(ip) & u32::MAX.checked_shl(host_bits).unwrap_or(0)).into())
+        }
+        IpAddr::V6(ip) => {
+            IpAddr::V6((u128::from(ip) & u128::MAX.checked_shl(host_bits).unwrap_or(0)).into())
+        }
+    }
+}
 
 /// Prepare a default error.
 fn default_err() -> Error {
     Error::new(ErrorKind::NotFound, "Local interface MTU not found")
 }
 
+/// The payload of errors for destinations without a usable route, which [`error_code`] maps to
+/// its own category.
+#[derive(Debug)]
+struct NoRoute(String);
+
+impl std::fmt::Display for NoRoute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NoRoute {}
+
+/// The payload of errors for host names that cannot be resolved, which [`MtuError`] maps to
+/// [`MtuError::Resolve`].
+#[derive(Debug)]
+struct ResolveFailed {
+    host: String,
+    err: Error,
+}
+
+impl std::fmt::Display for ResolveFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot resolve {}: {}", self.host, self.err)
+    }
+}
+
+impl std::error::Error for ResolveFailed {}
+
+/// Prepare an error for when `host` cannot be resolved, keeping the kind of `err`.
+fn resolve_err(host: &str, err: Error) -> Error {
+    Error::new(
+        err.kind(),
+        ResolveFailed {
+            host: host.to_string(),
+            err,
+        },
+    )
+}
+
+/// Prepare an error for a destination without a usable route.
+#[cfg(not(any(target_os = "ios", target_os = "tvos", target_os = "visionos")))]
+fn no_route_err(msg: String) -> Error {
+    Error::new(ErrorKind::NotFound, NoRoute(msg))
+}
+
+/// Prepare an error for when a destination is not reachable via the interface with index `oif`.
+#[cfg(not(any(target_os = "ios", target_os = "tvos", target_os = "visionos")))]
+fn not_reachable_via(oif: u32) -> Error {
+    no_route_err(format!("Destination not reachable via interface {oif}"))
+}
+
+/// Add context to an error resolving the name of the interface with index `index`, as returned in
+/// a route reply.
+#[cfg(any(target_os = "macos", bsd, target_os = "linux", target_os = "android"))]
+fn route_if_name_err(index: impl std::fmt::Display, err: &Error) -> Error {
+    Error::new(
+        err.kind(),
+        format!("Cannot resolve name of interface {index} from route reply: {err}"),
+    )
+}
+
+/// Whether to validate the consistency of replies from the operating system.
+#[cfg(any(target_os = "macos", bsd, target_os = "linux", target_os = "android"))]
+const STRICT: bool = cfg!(feature = "strict");
+
+/// Prepare an error for a reply that failed strict validation.
+#[cfg(any(target_os = "macos", bsd, target_os = "linux", target_os = "android"))]
+fn inconsistent_reply_err(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
 /// Prepare an error for cases that "should never happen".
 #[cfg(not(target_os = "windows"))]
 fn unlikely_err(msg: String) -> Error {
     debug_assert!(false, "{msg}");
-    Error::new(ErrorKind::Other, msg)
-}
-
-/// Align `size` to the next multiple of `align` (which needs to be a power of two).
-#[cfg(not(target_os = "windows"))]
-const fn aligned_by(size: usize, align: usize) -> usize {
-    if size == 0 {
-        align
-    } else {
-        1 + ((size - 1) | (align - 1))
-    }
+    Error::other(msg)
 }
 
 // Platforms currently not supported.
 //
 // See <https://github.com/mozilla/mtu/issues/82>.
 #[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
-pub fn interface_and_mtu_impl(remote: IpAddr) -> Result<(String, usize)> {
+pub fn interface_and_mtu_impl(query: RouteQuery) -> Result<Interface> {
     return Err(default_err());
 }
 
-/// Return the name and maximum transmission unit (MTU) of the outgoing network interface towards a
-/// remote destination identified by an [`IpAddr`],
+#[cfg(all(
+    feature = "tokio",
+    any(target_os = "ios", target_os = "tvos", target_os = "visionos")
+))]
+pub async fn interface_and_mtu_async_impl(query: RouteQuery) -> Result<Interface> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn interface_and_route_mtu_impl(query: RouteQuery) -> Result<(Interface, Option<usize>)> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn interface_and_mtu_by_index_impl(index: u32) -> Result<(String, usize)> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn interface_by_index_impl(index: u32) -> Result<Interface> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn change_generation_impl() -> Result<u64> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn is_route_ambiguous_impl(query: RouteQuery) -> Result<bool> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn is_on_link_impl(query: RouteQuery) -> Result<bool> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn interface_index_impl(query: RouteQuery) -> Result<u32> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn mtu_snapshot_impl() -> Result<HashMap<u32, usize>> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn route_details_impl(query: RouteQuery) -> Result<RouteDetails> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn routes_impl() -> Result<Vec<RouteEntry>> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn route_prefix_len_impl(query: RouteQuery) -> Result<Option<u8>> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn family_mtus_impl(index: u32) -> Result<(Option<usize>, Option<usize>)> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn interface_stats_impl(index: u32) -> Result<InterfaceStats> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn interfaces_impl() -> Result<Vec<Interface>> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+pub fn local_addrs_impl() -> Result<Vec<(IpAddr, u32)>> {
+    return Err(default_err());
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+#[derive(Debug)]
+pub struct Resolver;
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+impl Resolver {
+    pub fn new() -> Result<Self> {
+        return Err(default_err());
+    }
+
+    pub fn interface_and_mtu(&mut self, query: RouteQuery) -> Result<Interface> {
+        return Err(default_err());
+    }
+}
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+#[derive(Debug)]
+pub struct Watcher;
+
+#[cfg(any(target_os = "ios", target_os = "tvos", target_os = "visionos"))]
+impl Watcher {
+    pub fn new() -> Result<Self> {
+        return Err(default_err());
+    }
+
+    pub fn recv(&mut self) -> Result<ChangeEvent> {
+        return Err(default_err());
+    }
+
+    pub fn recv_timeout(&mut self, _timeout: Duration) -> Result<Option<ChangeEvent>> {
+        return Err(default_err());
+    }
+}
+
+/// The index of a network interface.
 ///
-/// The returned MTU may exceed the maximum IP packet size of 65,535 bytes on some platforms for
-/// some remote destinations. (For example, loopback destinations on Windows.)
+/// Interface indices are unsigned in all supported kernel ABIs and fit a `u32` everywhere. This
+/// type wraps such a `u32`, so that it cannot be confused with other integers such as file
+/// descriptors, and is what [`Interface::index`], [`InterfaceHandle`], [`ChangeEvent`] and the
+/// functions taking or returning an index use. Convert with [`From`] in either direction, for
+/// example to use an index as the scope ID of an IPv6 socket address.
 ///
-/// The returned interface name is obtained from the operating system.
+/// With the `serde` feature, an index serializes as the number returned by
+/// [`InterfaceIndex::get`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct InterfaceIndex(u32);
+
+impl InterfaceIndex {
+    /// Return the index with the given value.
+    ///
+    /// This does not check whether the interface exists.
+    #[must_use]
+    pub const fn new(index: u32) -> Self {
+        Self(index)
+    }
+
+    /// Return the value of the index.
+    #[must_use]
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Return the interface with this index, see [`Interface::from_index`].
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if there is no interface with this index or its name or MTU
+    /// cannot be determined.
+    pub fn interface(self) -> Result<Interface> {
+        Interface::from_index(self)
+    }
+}
+
+impl From<u32> for InterfaceIndex {
+    fn from(index: u32) -> Self {
+        Self(index)
+    }
+}
+
+impl From<InterfaceIndex> for u32 {
+    fn from(index: InterfaceIndex) -> Self {
+        index.0
+    }
+}
+
+impl From<InterfaceIndex> for InterfaceHandle {
+    fn from(index: InterfaceIndex) -> Self {
+        Self::from_index(index)
+    }
+}
+
+impl std::fmt::Display for InterfaceIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The name, index and maximum transmission unit (MTU) of a network interface.
 ///
-/// # Errors
+/// Two values are equal if all their fields are equal, which makes it easy to detect changes
+/// between successive lookups, see [`changed`].
 ///
-/// This function returns an error if the local interface MTU cannot be determined.
-pub fn interface_and_mtu(remote: IpAddr) -> Result<(String, usize)> {
-    interface_and_mtu_impl(remote)
+/// With the `serde` feature, an interface serializes as a map keyed by its field names, which are
+/// stable: `name`, `mtu`, `index`, `mac`, `flags`, `kind` and `speed_bps`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct Interface {
+    /// The name of the interface, as obtained from the operating system.
+    pub name: String,
+    /// The MTU of the interface.
+    pub mtu: usize,
+    /// The index of the interface, which can be passed to `SO_BINDTODEVICE`, `IP_BOUND_IF` and
+    /// similar socket options. This is 0, which is never a valid index, if it is not known, such
+    /// as for values converted from a name and MTU.
+    pub index: InterfaceIndex,
+    /// The link-layer (MAC) address of the interface, or `None` if it has none, such as loopback
+    /// and tunnel interfaces, or if it is not known.
+    pub mac: Option<[u8; 6]>,
+    /// The state and capabilities of the interface. This is empty if they are not known, such as
+    /// for values converted from a name and MTU.
+    pub flags: InterfaceFlags,
+    /// The kind of medium of the interface, or `None` if it is not known, such as for values
+    /// converted from a name and MTU.
+    pub kind: Option<InterfaceKind>,
+    /// The link speed of the interface in bits per second, or `None` if it is not known. This is
+    /// best-effort: on Linux, it is the speed that Ethernet drivers report in sysfs, on macOS and
+    /// the BSDs, the `ifi_baudrate` of the interface, and on Windows, its transmit link speed.
+    /// Many virtual and wireless interfaces report no speed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub speed_bps: Option<u64>,
 }
 
-#[cfg(test)]
-mod test {
-    use std::{
-        env,
-        net::{IpAddr, Ipv4Addr, Ipv6Addr},
-    };
+impl Interface {
+    /// Return an interface with the given name, MTU and index, whose link-layer details are not
+    /// known yet.
+    const fn new(name: String, mtu: usize, index: u32) -> Self {
+        Self {
+            name,
+            mtu,
+            index: InterfaceIndex(index),
+            mac: None,
+            flags: InterfaceFlags::empty(),
+            kind: None,
+            speed_bps: None,
+        }
+    }
 
-    use crate::interface_and_mtu;
+    /// Return the name and MTU of the interface with the given index.
+    ///
+    /// This is for callers that already know the outgoing interface, for example from their own
+    /// route lookup, and only need its name and MTU. Use [`interface_index`] to obtain the index
+    /// of the outgoing interface towards a destination.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if there is no interface with the given index or its name or
+    /// MTU cannot be determined.
+    pub fn from_index(index: InterfaceIndex) -> Result<Self> {
+        interface_by_index_impl(index.0)
+    }
+}
 
-    #[derive(Debug)]
-    struct NameMtu<'a>(Option<&'a str>, usize);
+impl From<(String, usize)> for Interface {
+    fn from((name, mtu): (String, usize)) -> Self {
+        Self::new(name, mtu, 0)
+    }
+}
 
-    impl PartialEq<NameMtu<'_>> for (String, usize) {
-        fn eq(&self, other: &NameMtu<'_>) -> bool {
-            other.0.map_or(true, |name| name == self.0) && other.1 == self.1
-        }
+impl From<Interface> for (String, usize) {
+    fn from(interface: Interface) -> Self {
+        (interface.name, interface.mtu)
     }
+}
 
-    #[cfg(any(target_os = "macos", target_os = "freebsd",))]
-    const LOOPBACK: &[NameMtu] = &[NameMtu(Some("lo0"), 16_384), NameMtu(Some("lo0"), 16_384)];
-    #[cfg(any(target_os = "linux", target_os = "android"))]
-    const LOOPBACK: &[NameMtu] = &[NameMtu(Some("lo"), 65_536), NameMtu(Some("lo"), 65_536)];
-    #[cfg(target_os = "windows")]
-    const LOOPBACK: &[NameMtu] = &[
-        NameMtu(Some("loopback_0"), 4_294_967_295),
-        NameMtu(Some("loopback_0"), 4_294_967_295),
-    ];
-    #[cfg(target_os = "openbsd")]
-    const LOOPBACK: &[NameMtu] = &[NameMtu(Some("lo0"), 32_768), NameMtu(Some("lo0"), 32_768)];
-    #[cfg(target_os = "netbsd")]
-    const LOOPBACK: &[NameMtu] = &[NameMtu(Some("lo0"), 33_624), NameMtu(Some("lo0"), 33_624)];
-    #[cfg(target_os = "solaris")]
-    // Note: Different loopback MTUs for IPv4 and IPv6?!
-    const LOOPBACK: &[NameMtu] = &[NameMtu(Some("lo0"), 8_232), NameMtu(Some("lo0"), 8_252)];
+/// The state and capabilities of a network interface, see [`Interface::flags`].
+///
+/// Flags combine with `|` and are tested with [`InterfaceFlags::contains`]. On Windows,
+/// [`InterfaceFlags::MULTICAST`] is not reported.
+///
+/// With the `serde` feature, the flags serialize as the number returned by
+/// [`InterfaceFlags::bits`]. The values of the flags are stable.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct InterfaceFlags(u32);
 
-    // Non-loopback interface names are unpredictable, so we only check the MTU.
-    const INET: NameMtu = NameMtu(None, 1_500);
+impl InterfaceFlags {
+    /// The interface is administratively up.
+    pub const UP: Self = Self(1);
+    /// The interface is operational, such as when it has a carrier.
+    pub const RUNNING: Self = Self(1 << 1);
+    /// The interface is a loopback interface.
+    pub const LOOPBACK: Self = Self(1 << 2);
+    /// The interface is a point-to-point link, such as a tunnel or PPP.
+    pub const POINTOPOINT: Self = Self(1 << 3);
+    /// The interface supports multicast.
+    pub const MULTICAST: Self = Self(1 << 4);
 
-    #[test]
-    fn loopback_v4() {
-        assert_eq!(
-            interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap(),
-            LOOPBACK[0]
-        );
+    /// Return a value with no flags set.
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self(0)
     }
 
-    #[test]
-    fn loopback_v6() {
-        assert_eq!(
-            interface_and_mtu(IpAddr::V6(Ipv6Addr::LOCALHOST)).unwrap(),
-            LOOPBACK[1]
-        );
+    /// Return the raw bits of the flags.
+    #[must_use]
+    pub const fn bits(self) -> u32 {
+        self.0
     }
 
-    #[test]
-    fn inet_v4() {
-        assert_eq!(
-            interface_and_mtu(IpAddr::V4(Ipv4Addr::new(
-                104, 16, 132, 229 // cloudflare.com
-            )))
-            .unwrap(),
-            INET
-        );
+    /// Return whether no flags are set.
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
     }
 
-    #[test]
-    fn inet_v6() {
-        match interface_and_mtu(IpAddr::V6(Ipv6Addr::new(
-            0x2606, 0x4700, 0, 0, 0, 0, 0x6810, 0x84e5, // cloudflare.com
-        ))) {
+    /// Return whether all flags in `other` are set.
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Decode the `IFF_*` flags of an interface, as reported in `ifa_flags` or `ifi_flags`.
+    #[cfg(not(target_os = "windows"))]
+    #[allow(clippy::cast_sign_loss)] // The interface flag bits are positive.
+    fn from_iff(iff: u64) -> Self {
+        [
+            (libc::IFF_UP, Self::UP),
+            (libc::IFF_RUNNING, Self::RUNNING),
+            (libc::IFF_LOOPBACK, Self::LOOPBACK),
+            (libc::IFF_POINTOPOINT, Self::POINTOPOINT),
+            (libc::IFF_MULTICAST, Self::MULTICAST),
+        ]
+        .into_iter()
+        .filter(|&(iff_bit, _)| iff & iff_bit as u64 != 0)
+        .fold(Self::empty(), |flags, (_, flag)| flags | flag)
+    }
+}
+
+impl std::ops::BitOr for InterfaceFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for InterfaceFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl std::ops::BitAnd for InterfaceFlags {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+/// The kind of medium of a network interface, see [`Interface::kind`].
+///
+/// Some platforms, including Linux and macOS, give Wi-Fi interfaces the Ethernet link type, so
+/// they are reported as [`InterfaceKind::Ethernet`].
+///
+/// With the `serde` feature, the kinds serialize as their lowercase names, such as `"wifi"`, and
+/// [`InterfaceKind::Other`] as `{"other": type}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum InterfaceKind {
+    Loopback,
+    Ethernet,
+    WiFi,
+    /// A cellular data connection.
+    Cellular,
+    /// A tunnel, such as IP-in-IP, GRE, or a `tun` device used by a VPN.
+    Tunnel,
+    /// Any other kind, with the type reported by the operating system: an `ARPHRD_*` value on
+    /// Linux and Android, and an IANA `ifType` value elsewhere.
+    Other(u32),
+}
+
+/// IANA `ifType` values, which the BSD `IFT_*` and Windows `IF_TYPE_*` constants follow.
+#[cfg(any(target_os = "macos", bsd, target_os = "windows"))]
+mod if_type {
+    pub const ETHERNET_CSMACD: u32 = 6;
+    pub const SOFTWARE_LOOPBACK: u32 = 24;
+    pub const GIF: u32 = 55;
+    pub const IEEE80211: u32 = 71;
+    pub const TUNNEL: u32 = 131;
+    pub const STF: u32 = 215;
+    pub const WWANPP: u32 = 243;
+    pub const WWANPP2: u32 = 244;
+    /// Apple's `IFT_CELLULAR`, which is not an IANA value.
+    #[cfg(target_os = "macos")]
+    pub const CELLULAR: u32 = 0xff;
+}
+
+#[cfg(any(target_os = "macos", bsd, target_os = "windows"))]
+impl InterfaceKind {
+    /// Return the kind of interface for an IANA `ifType` value.
+    const fn from_if_type(raw: u32) -> Self {
+        match raw {
+            if_type::SOFTWARE_LOOPBACK => Self::Loopback,
+            if_type::ETHERNET_CSMACD => Self::Ethernet,
+            if_type::IEEE80211 => Self::WiFi,
+            if_type::WWANPP | if_type::WWANPP2 => Self::Cellular,
+            #[cfg(target_os = "macos")]
+            if_type::CELLULAR => Self::Cellular,
+            if_type::GIF | if_type::STF | if_type::TUNNEL => Self::Tunnel,
+            _ => Self::Other(raw),
+        }
+    }
+}
+
+/// Interface types with a conventional MTU, which [`route_info`] falls back to when the operating
+/// system reports an MTU of zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(target_os = "windows", allow(dead_code))] // The type is not looked up there.
+enum InterfaceType {
+    Ethernet,
+    /// PPP, which is most commonly PPP over Ethernet.
+    Pppoe,
+    /// IPv6 in IPv4 tunnels.
+    SixInFour,
+    Loopback,
+}
+
+impl InterfaceType {
+    const fn default_mtu(self) -> usize {
+        match self {
+            Self::Ethernet => consts::ETHERNET_MTU,
+            // The eight bytes of PPPoE and PPP headers come out of the Ethernet MTU.
+            Self::Pppoe => 1492,
+            // The 20-byte IPv4 header comes out of the Ethernet MTU.
+            Self::SixInFour => 1480,
+            Self::Loopback => consts::LOOPBACK_TYPICAL,
+        }
+    }
+}
+
+/// If `interface` has an MTU of zero and `kind` is known, replace the MTU with the conventional one
+/// for `kind`. Returns whether the MTU was replaced.
+fn fallback_mtu(interface: &mut Interface, kind: Option<InterfaceType>) -> bool {
+    match kind {
+        Some(kind) if interface.mtu == 0 => {
+            interface.mtu = kind.default_mtu();
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Details of the route towards a remote destination, as returned by [`route_info`] and
+/// [`proxy_hint`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct RouteInfo {
+    /// The outgoing interface and its MTU.
+    pub interface: Interface,
+    /// Whether `interface.mtu` is a conventional default for the type of the interface instead of
+    /// the value reported by the operating system, see [`route_info`].
+    pub mtu_is_default: bool,
+    /// The remote address the route was looked up for.
+    pub remote: IpAddr,
+    /// The ultimate destination of the traffic, if it differs from `remote`, for example because
+    /// the traffic goes through a proxy. This is only carried along for diagnostics and does not
+    /// affect the lookup.
+    pub destination: Option<SocketAddr>,
+    /// The address of the peer at the other end of the outgoing interface, if it is a
+    /// point-to-point link such as a PPP or VPN tunnel. This is always `None` on Windows.
+    pub peer: Option<IpAddr>,
+    /// The next hop towards `remote`, or `None` if it is on-link, see [`is_on_link`].
+    pub gateway: Option<IpAddr>,
+    /// The source address that the operating system selects for traffic towards `remote`, which
+    /// can be passed to [`interface_and_mtu_from`]. This is `None` on Linux if the route has no
+    /// preferred source address.
+    pub source: Option<IpAddr>,
+}
+
+/// Return details of the route towards a remote destination identified by an [`IpAddr`].
+///
+/// Some virtual interfaces report an MTU of zero. For those, the interface MTU is replaced by a
+/// conventional default for the interface type, if the type is known: 1500 for Ethernet, 1492 for
+/// PPP (as commonly run over Ethernet), 1480 for IPv6-in-IPv4 tunnels and 65536 for loopback. The
+/// result then has [`RouteInfo::mtu_is_default`] set. If the type is not known, or on Windows, the
+/// MTU stays zero. [`interface_and_mtu`] always returns the MTU reported by the operating system.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU, the peer of a point-to-point
+/// interface, the next hop or the source address cannot be determined.
+pub fn route_info(remote: IpAddr) -> Result<RouteInfo> {
+    let mut details = resolve_route(
+        remote.into(),
+        route_details_impl,
+        RouteDetails::from_interface,
+    )?;
+    // As in `interface_and_mtu`, the route MTU takes precedence on macOS and the BSDs.
+    #[cfg(any(target_os = "macos", bsd))]
+    if let Some(route_mtu) = details.route_mtu {
+        details.interface.mtu = route_mtu;
+    }
+    let mtu_is_default =
+        details.interface.mtu == 0 && fallback_mtu(&mut details.interface, details.interface_type);
+    Ok(RouteInfo {
+        interface: details.interface,
+        mtu_is_default,
+        remote,
+        destination: None,
+        peer: details.peer,
+        gateway: details.gateway,
+        source: details.source,
+    })
+}
+
+/// The details of the route towards a remote destination that [`route_info`] and [`explain`]
+/// report, all taken from the reply to a single route query.
+struct RouteDetails {
+    /// The outgoing interface, with the MTU of the link.
+    interface: Interface,
+    /// The type of `interface`, if it has a conventional MTU.
+    interface_type: Option<InterfaceType>,
+    /// The MTU of the route, if it has one.
+    route_mtu: Option<usize>,
+    /// Whether the destination is directly reachable.
+    on_link: bool,
+    /// The next hop, unless the destination is on-link.
+    gateway: Option<IpAddr>,
+    /// The source address that the operating system selects, if known.
+    source: Option<IpAddr>,
+    /// The address of the peer at the other end of `interface`, if it is a point-to-point link.
+    peer: Option<IpAddr>,
+}
+
+impl RouteDetails {
+    /// Return the details for `interface`, which was found without a route query. Apart from
+    /// mocked ones, only IPv4 link-local destinations are, and they are always on-link.
+    const fn from_interface(interface: Interface) -> Self {
+        Self {
+            interface,
+            interface_type: None,
+            route_mtu: None,
+            on_link: true,
+            gateway: None,
+            source: None,
+            peer: None,
+        }
+    }
+}
+
+/// Return details of the route towards a proxy at `proxy`, through which traffic towards
+/// `destination` is sent.
+///
+/// The interface and MTU are those towards the proxy, exactly as returned by [`interface_and_mtu`]
+/// for its address, since that is the path the traffic takes. `destination` is recorded in the
+/// result for logging.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU towards the proxy cannot be
+/// determined.
+pub fn proxy_hint(proxy: SocketAddr, destination: SocketAddr) -> Result<RouteInfo> {
+    Ok(RouteInfo {
+        destination: Some(destination),
+        ..route_info(proxy.ip())?
+    })
+}
+
+/// Return the name and maximum transmission unit (MTU) of the outgoing network interface towards a
+/// remote destination identified by an [`IpAddr`],
+///
+/// The returned MTU may exceed the maximum IP packet size of 65,535 bytes on some platforms for
+/// some remote destinations. (For example, loopback destinations on Windows.)
+///
+/// The returned interface name is obtained from the operating system.
+///
+/// IPv4 link-local destinations (169.254.0.0/16) are always on-link. On Linux, macOS and the BSDs,
+/// the returned interface for them is one with a link-local address on a subnet containing
+/// `remote`, even if the routing table has no matching route. If several interfaces qualify, the
+/// destination may be reachable via any of them; the one selected by the routing table is then
+/// preferred, followed by the one with the lowest interface index. On Windows, the routing table
+/// already contains an on-link route for every interface with a link-local address.
+///
+/// IPv4-mapped IPv6 destinations, such as `::ffff:192.0.2.1`, are looked up as the IPv4 address
+/// they map, since that is where a dual-stack socket sends traffic for them.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined. On macOS and
+/// the BSDs, it also returns an error if the route towards `remote` is a reject or blackhole route.
+pub fn interface_and_mtu(remote: IpAddr) -> Result<Interface> {
+    MtuResolver::new()?.resolve(remote)
+}
+
+/// The interface name that [`interface_and_mtu_or`] returns with the fallback MTU.
+const UNKNOWN_INTERFACE: &str = "unknown";
+
+/// Like [`interface_and_mtu`], but returns the name `"unknown"` with the MTU `fallback` if the
+/// lookup is denied.
+///
+/// This is meant for sandboxed processes, where opening the routing socket or querying it may be
+/// denied, for example by seccomp with `EPERM`, `EACCES` or `ENOSYS`. Callers can then continue
+/// with a conservative MTU, such as the IPv6 minimum of 1,280 bytes in [`consts::IPV6_MIN_MTU`],
+/// instead of failing.
+///
+/// # Errors
+///
+/// This function returns any error of [`interface_and_mtu`] that does not deny the lookup.
+pub fn interface_and_mtu_or(remote: IpAddr, fallback: usize) -> Result<Interface> {
+    or_fallback(interface_and_mtu(remote), fallback)
+}
+
+/// Whether `err` is how a sandbox denies a lookup.
+fn is_denied(err: &Error) -> bool {
+    #[cfg(unix)]
+    if matches!(
+        err.raw_os_error(),
+        Some(libc::EPERM | libc::EACCES | libc::ENOSYS)
+    ) {
+        return true;
+    }
+    err.kind() == ErrorKind::PermissionDenied
+}
+
+/// Return the interface in `res`, or an unknown interface with MTU `fallback` if `res` is an error
+/// that denies the lookup.
+fn or_fallback(res: Result<Interface>, fallback: usize) -> Result<Interface> {
+    match res {
+        Err(err) if is_denied(&err) => {
+            Ok(Interface::from((UNKNOWN_INTERFACE.to_string(), fallback)))
+        }
+        res => res,
+    }
+}
+
+/// Like [`interface_and_mtu`], but with an error that tells apart why the lookup failed.
+///
+/// # Errors
+///
+/// This function returns an [`MtuError`] if the local interface MTU cannot be determined.
+pub fn interface_and_mtu2(remote: IpAddr) -> std::result::Result<Interface, MtuError> {
+    interface_and_mtu(remote).map_err(MtuError::from)
+}
+
+/// A resolver for the outgoing network interface towards remote destinations, which keeps the
+/// routing socket it needs open between lookups.
+///
+/// [`interface_and_mtu`] opens a new routing socket for every lookup. Callers that look up MTUs
+/// frequently, for example on every path validation, can avoid that by resolving through a
+/// long-lived [`MtuResolver`]. On Windows, lookups need no socket, and this is the same as calling
+/// [`interface_and_mtu`].
+#[derive(Debug)]
+pub struct MtuResolver(Resolver);
+
+impl MtuResolver {
+    /// Create a resolver and open its routing socket.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the routing socket cannot be opened.
+    pub fn new() -> Result<Self> {
+        Resolver::new().map(Self)
+    }
+
+    /// Return the outgoing network interface towards `remote`, as [`interface_and_mtu`] does,
+    /// using the routing socket of the resolver.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the local interface MTU cannot be determined, see
+    /// [`interface_and_mtu`].
+    pub fn resolve(&mut self, remote: IpAddr) -> Result<Interface> {
+        self.resolve_query(remote.into())
+    }
+
+    /// Return the outgoing network interface towards the destination of `query`, using the routing
+    /// socket of the resolver.
+    fn resolve_query(&mut self, query: RouteQuery) -> Result<Interface> {
+        resolve_route(
+            query,
+            |query| self.0.interface_and_mtu(query),
+            |interface| interface,
+        )
+    }
+}
+
+/// Return the outgoing network interface towards the destination of `query`, opening a routing
+/// socket for the lookup.
+fn resolve_query(query: RouteQuery) -> Result<Interface> {
+    MtuResolver::new()?.resolve_query(query)
+}
+
+/// Look up the route towards the destination of `query` with `route`, after the steps that all
+/// lookups share: IPv4-mapped IPv6 addresses are canonicalized, the mock installed with the `mock`
+/// feature replaces the operating system lookup, and IPv4 link-local destinations are looked up on
+/// their link, see [`interface_and_mtu`]. The interfaces found by the latter two steps are
+/// converted with `from_interface`.
+#[cfg_attr(
+    not(any(
+        feature = "mock",
+        target_os = "macos",
+        bsd,
+        target_os = "linux",
+        target_os = "android"
+    )),
+    allow(unused_variables)
+)] // Without a mock, only the link-local lookup finds interfaces.
+fn resolve_route<T>(
+    query: RouteQuery,
+    route: impl FnOnce(RouteQuery) -> Result<T>,
+    from_interface: impl FnOnce(Interface) -> T,
+) -> Result<T> {
+    let query = query.canonical();
+    #[cfg(feature = "mock")]
+    if let Some(res) = mock::mocked(query.remote) {
+        return res.map(|res| from_interface(res.into()));
+    }
+    #[cfg(any(target_os = "macos", bsd, target_os = "linux", target_os = "android"))]
+    if let Some(ip) = query.link_local_v4() {
+        return linklocal::interface_and_mtu(ip, query).map(from_interface);
+    }
+    route(query)
+}
+
+/// A cache of the names and MTUs of the outgoing network interfaces towards remote destinations,
+/// for callers that look up the same few destinations repeatedly.
+///
+/// [`MtuCache::get`] returns the result of an earlier lookup of the same destination while it is
+/// younger than the time-to-live of the cache, and otherwise looks it up again with
+/// [`interface_and_mtu`]. Expired entries are evicted whenever the cache needs to look up a
+/// destination. Failed lookups are not cached.
+#[derive(Debug)]
+pub struct MtuCache {
+    ttl: Duration,
+    entries: HashMap<IpAddr, (Instant, Interface)>,
+    /// Return the current time. Replaced in tests.
+    now: fn() -> Instant,
+    /// Look up the interface towards a destination. Replaced in tests.
+    lookup: fn(IpAddr) -> Result<Interface>,
+}
+
+impl MtuCache {
+    /// Create an empty cache whose entries expire after `ttl`.
+    #[must_use]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: HashMap::new(),
+            now: Instant::now,
+            lookup: interface_and_mtu,
+        }
+    }
+
+    /// Return the name and MTU of the outgoing network interface towards `remote`, from the cache
+    /// if it has an entry younger than its time-to-live, and as [`interface_and_mtu`] does
+    /// otherwise.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the local interface MTU cannot be determined, see
+    /// [`interface_and_mtu`].
+    pub fn get(&mut self, remote: IpAddr) -> Result<Interface> {
+        let now = (self.now)();
+        let ttl = self.ttl;
+        let fresh = |at: Instant| now.saturating_duration_since(at) < ttl;
+        if let Some((_, entry)) = self.entries.get(&remote).filter(|(at, _)| fresh(*at)) {
+            return Ok(entry.clone());
+        }
+        self.entries.retain(|_, (at, _)| fresh(*at));
+        let entry = (self.lookup)(remote)?;
+        self.entries.insert(remote, (now, entry.clone()));
+        Ok(entry)
+    }
+}
+
+/// Like [`interface_and_mtu`], but waiting for the operating system on the Tokio runtime instead of
+/// blocking the calling thread.
+///
+/// On Linux, macOS and the BSDs, the routing socket is registered with the reactor of the runtime,
+/// and the lookup fails with an [`ErrorKind::TimedOut`] error if the kernel does not reply within
+/// one second. Since the IP Helper functions have no asynchronous variant, the lookup instead runs
+/// on the blocking thread pool on Windows, as do IPv4 link-local lookups on all platforms.
+///
+/// This function is only available with the `tokio` feature. It must be called from within a Tokio
+/// runtime with its I/O and time drivers enabled.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined, see
+/// [`interface_and_mtu`].
+#[cfg(feature = "tokio")]
+pub async fn interface_and_mtu_async(remote: IpAddr) -> Result<Interface> {
+    let query = RouteQuery::from(remote).canonical();
+    #[cfg(feature = "mock")]
+    if let Some(res) = mock::mocked(query.remote) {
+        return res.map(Interface::from);
+    }
+    #[cfg(any(target_os = "macos", bsd, target_os = "linux", target_os = "android"))]
+    if let Some(ip) = query.link_local_v4() {
+        return tokio::task::spawn_blocking(move || linklocal::interface_and_mtu(ip, query))
+            .await
+            .map_err(Error::other)?;
+    }
+    interface_and_mtu_async_impl(query).await
+}
+
+/// Return the names and MTUs of the outgoing network interfaces towards each of `dests`, in the
+/// same order, looking them up over a single routing socket.
+///
+/// This is meant for discovering the MTUs towards a set of candidate servers, for example for
+/// Happy Eyeballs, without opening a routing socket for each of them. Each destination has its own
+/// result, so that a failed lookup does not fail the whole batch. If the routing socket cannot be
+/// opened, every destination has that error.
+#[must_use]
+pub fn interface_and_mtu_batch(dests: &[IpAddr]) -> Vec<Result<Interface>> {
+    match MtuResolver::new() {
+        Ok(mut resolver) => dests.iter().map(|&dest| resolver.resolve(dest)).collect(),
+        Err(e) => dests
+            .iter()
+            .map(|_| {
+                Err(e.raw_os_error().map_or_else(
+                    || Error::new(e.kind(), e.to_string()),
+                    Error::from_raw_os_error,
+                ))
+            })
+            .collect(),
+    }
+}
+
+/// Like [`interface_and_mtu`], but for anything that converts into an [`IpAddr`], such as an
+/// [`std::net::Ipv4Addr`] or an [`Ipv6Addr`].
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined.
+pub fn interface_and_mtu_for(dest: impl Into<IpAddr>) -> Result<Interface> {
+    interface_and_mtu(dest.into())
+}
+
+/// Like [`interface_and_mtu`], but for a [`SocketAddr`], [`std::net::SocketAddrV4`] or
+/// [`std::net::SocketAddrV6`], as returned by `recv_from` or `peer_addr`.
+///
+/// The port is irrelevant to routing and ignored. A nonzero IPv6 scope ID, as set for link-local
+/// destinations, constrains the route to the interface with that index, as for
+/// [`interface_and_mtu_via_index`].
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined.
+pub fn interface_and_mtu_for_socket_addr(dest: impl Into<SocketAddr>) -> Result<Interface> {
+    match dest.into() {
+        SocketAddr::V6(dest) if dest.scope_id() != 0 => resolve_query(RouteQuery {
+            oif: Some(dest.scope_id()),
+            ..IpAddr::V6(*dest.ip()).into()
+        }),
+        dest => interface_and_mtu(dest.ip()),
+    }
+}
+
+/// Like [`interface_and_mtu`], but for a host name such as `"example.com"`, which is resolved with
+/// [`ToSocketAddrs`](std::net::ToSocketAddrs) first. IP address literals are accepted as well.
+///
+/// The resolved addresses are tried in the order the resolver returns them, and the first one with
+/// a route determines the result. This picks the address family that actually routes when the
+/// name has both IPv4 and IPv6 addresses.
+///
+/// # Errors
+///
+/// This function returns an error that converts into [`MtuError::Resolve`] if `host` cannot be
+/// resolved or has no addresses. Otherwise, if none of its addresses has a local interface MTU, it
+/// returns the error for the first address.
+pub fn interface_and_mtu_for_host(host: &str) -> Result<Interface> {
+    use std::net::ToSocketAddrs as _;
+
+    let addrs = (host, 0)
+        .to_socket_addrs()
+        .map_err(|err| resolve_err(host, err))?;
+    let mut first_err = None;
+    for addr in addrs {
+        match interface_and_mtu_for_socket_addr(addr) {
+            Ok(interface) => return Ok(interface),
+            Err(err) => {
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    Err(first_err.unwrap_or_else(|| {
+        resolve_err(
+            host,
+            Error::new(ErrorKind::NotFound, "Host name has no addresses"),
+        )
+    }))
+}
+
+/// The maximum size of an IP packet.
+const MAX_IP_PACKET_SIZE: usize = u16::MAX as usize;
+
+/// Like [`interface_and_mtu`], but normalizes the returned MTU to at most 65,535 bytes, the maximum
+/// IP packet size.
+///
+/// This maps platform-specific loopback MTUs that exceed the maximum IP packet size, such as
+/// 4,294,967,295 on Windows or 65,536 on Linux, to 65,535, so that callers get consistent values
+/// across platforms. Use [`interface_and_mtu`] to obtain the raw value.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined.
+pub fn interface_and_mtu_normalized(remote: IpAddr) -> Result<Interface> {
+    interface_and_mtu_clamped(remote, MAX_IP_PACKET_SIZE)
+}
+
+/// Return the name of the outgoing network interface towards a remote destination identified by
+/// an [`IpAddr`], with its IPv4 and IPv6 MTUs.
+///
+/// The IPv6 MTU of an interface can differ from its IPv4 MTU, for example when it is configured
+/// separately or lowered by router advertisements. The MTU of a family is `None` if the family is
+/// not enabled on the interface. On Linux, the IPv4 MTU is the link MTU. On Windows, each family
+/// has its own MTU. On macOS and the BSDs, which have no per-family MTUs, both are the interface
+/// MTU for the families that have an address on the interface.
+///
+/// # Errors
+///
+/// This function returns an error if the outgoing interface or its MTUs cannot be determined.
+pub fn interface_mtus(remote: IpAddr) -> Result<(String, Option<usize>, Option<usize>)> {
+    let Interface { name, index, .. } = interface_and_mtu(remote)?;
+    let (inet, inet6) = family_mtus_impl(index.0)?;
+    Ok((name, inet, inet6))
+}
+
+/// Like [`interface_and_mtu`], but caps the returned MTU at `max`, such as the size of the largest
+/// buffer the caller is willing to allocate.
+///
+/// Windows reports an MTU of 4,294,967,295 (`u32::MAX`) for its loopback interface, because
+/// loopback traffic never leaves the host and so is not limited by a link. Sizing buffers with that
+/// value would attempt a 4 GiB allocation. Use [`interface_and_mtu`] to obtain the raw value, and
+/// [`interface_and_mtu_normalized`] to cap it at the maximum IP packet size of 65,535 bytes.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined.
+pub fn interface_and_mtu_clamped(remote: IpAddr, max: usize) -> Result<Interface> {
+    let mut interface = interface_and_mtu(remote)?;
+    interface.mtu = interface.mtu.min(max);
+    Ok(interface)
+}
+
+/// Like [`interface_and_mtu`], but never returns an MTU below the IPv6 minimum of 1,280 bytes (see
+/// [`consts::IPV6_MIN_MTU`]) for IPv6 destinations.
+///
+/// Some tunnel interfaces report such MTUs, although IPv6 requires every link to carry packets of
+/// at least 1,280 bytes, and QUIC and TCP cannot work with them. If `enforce_ipv6_min` is `true`,
+/// the MTU is raised to 1,280 bytes, on the assumption that the link fragments and reassembles
+/// packets below the IPv6 layer, as RFC 8200 requires. If it is `false`, such an MTU is an error
+/// instead. IPv4 destinations, including IPv4-mapped IPv6 ones, get the MTU as reported.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined, or, if
+/// `enforce_ipv6_min` is `false`, if it is below 1,280 bytes for an IPv6 destination.
+pub fn interface_and_mtu_ipv6_min(remote: IpAddr, enforce_ipv6_min: bool) -> Result<Interface> {
+    with_ipv6_min_mtu(remote, interface_and_mtu(remote)?, enforce_ipv6_min)
+}
+
+/// Apply the IPv6 minimum MTU to `interface`, the outgoing interface towards `remote`, as described
+/// for [`interface_and_mtu_ipv6_min`].
+fn with_ipv6_min_mtu(
+    remote: IpAddr,
+    mut interface: Interface,
+    enforce_ipv6_min: bool,
+) -> Result<Interface> {
+    if remote.to_canonical().is_ipv4() || interface.mtu >= consts::IPV6_MIN_MTU {
+        return Ok(interface);
+    }
+    if !enforce_ipv6_min {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "MTU {} of interface {} is below the IPv6 minimum of {} towards {remote}",
+                interface.mtu,
+                interface.name,
+                consts::IPV6_MIN_MTU
+            ),
+        ));
+    }
+    interface.mtu = consts::IPV6_MIN_MTU;
+    Ok(interface)
+}
+
+/// Return the name and maximum transmission unit (MTU) of the outgoing network interface towards a
+/// remote IPv6 destination, taking the given IPv6 traffic class and flow label into account.
+///
+/// `flowinfo` has the same format as [`std::net::SocketAddrV6::flowinfo`], i.e., the traffic
+/// class in bits 20 to 27 and the flow label in bits 0 to 19. This matters only on systems where
+/// policy routing selects routes based on these fields, and most systems ignore it. On Linux, only
+/// the traffic class is passed to the route query, since the kernel does not accept a flow label
+/// there.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined.
+pub fn interface_and_mtu_with_flowinfo(remote: Ipv6Addr, flowinfo: u32) -> Result<Interface> {
+    resolve_query(RouteQuery {
+        flowinfo,
+        ..IpAddr::V6(remote).into()
+    })
+}
+
+/// The kind of IPv6 source address to prefer, see [`interface_and_mtu_with_source_preference`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SourcePreference {
+    /// Prefer a temporary address, as generated by privacy extensions (RFC 8981).
+    Temporary,
+    /// Prefer a stable address.
+    Stable,
+    /// Prefer a cryptographically generated address (RFC 3972).
+    Cga,
+}
+
+/// Return the name and maximum transmission unit (MTU) of the outgoing network interface towards a
+/// remote IPv6 destination, for traffic from a source address of the preferred kind.
+///
+/// This is best-effort and only has an effect on Linux. There, the kernel first selects a source
+/// address towards `remote` as it would for a socket with the corresponding `IPV6_ADDR_PREFERENCES`
+/// option, and the route query then uses that source, so that source-specific routes are taken
+/// into account. Linux does not support cryptographically generated addresses and ignores
+/// [`SourcePreference::Cga`]. On other platforms, the preference is ignored and this is the same as
+/// [`interface_and_mtu`].
+///
+/// # Errors
+///
+/// This function returns an error if no source address can be selected towards `remote` or the
+/// local interface MTU cannot be determined.
+pub fn interface_and_mtu_with_source_preference(
+    remote: Ipv6Addr,
+    preference: SourcePreference,
+) -> Result<Interface> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let src = Some(IpAddr::V6(linux::preferred_source(remote, preference)?));
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let src = {
+        _ = preference;
+        None
+    };
+    resolve_query(RouteQuery {
+        src,
+        ..IpAddr::V6(remote).into()
+    })
+}
+
+/// Which MTU to return when the route towards a destination carries its own MTU metric, see
+/// [`interface_and_mtu_with_preference`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MtuPreference {
+    /// The smaller of the route and interface MTUs.
+    #[default]
+    Smaller,
+    /// The route MTU, which may reflect the result of path MTU discovery.
+    RouteMetric,
+    /// The interface MTU.
+    Interface,
+}
+
+impl MtuPreference {
+    /// Select an MTU according to this preference from the interface MTU `if_mtu` and the route
+    /// MTU `route_mtu`, if any.
+    fn select(self, if_mtu: usize, route_mtu: Option<usize>) -> usize {
+        match (self, route_mtu) {
+            (Self::Smaller, Some(route_mtu)) => if_mtu.min(route_mtu),
+            (Self::RouteMetric, Some(route_mtu)) => route_mtu,
+            (Self::Interface, _) | (_, None) => if_mtu,
+        }
+    }
+}
+
+/// Like [`interface_and_mtu`], but chooses between the route and interface MTUs according to
+/// `preference`.
+///
+/// Routes can carry an MTU metric that differs from the MTU of their interface, for example when
+/// configured with `ip route ... mtu lock` on Linux, or when path MTU discovery lowered it on the
+/// BSDs. If the route has no MTU metric, the interface MTU is returned regardless of `preference`.
+/// Windows routes never carry an MTU metric, and neither do the interfaces on the link of IPv4
+/// link-local destinations, which are looked up as for [`interface_and_mtu`].
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined.
+pub fn interface_and_mtu_with_preference(
+    remote: IpAddr,
+    preference: MtuPreference,
+) -> Result<Interface> {
+    let (mut interface, route_mtu) =
+        resolve_route(remote.into(), interface_and_route_mtu_impl, |interface| {
+            (interface, None)
+        })?;
+    interface.mtu = preference.select(interface.mtu, route_mtu);
+    Ok(interface)
+}
+
+/// Like [`interface_and_mtu`], but queries the routing table of the given FreeBSD FIB instead of
+/// the one of the process, see `setfib(2)`.
+///
+/// This is the FreeBSD analog of Linux routing tables. [`interface_and_mtu`] uses the FIB of the
+/// process.
+///
+/// # Errors
+///
+/// This function returns an [`ErrorKind::NotFound`] error if `remote` is not reachable in `fib`,
+/// and another error if `fib` does not exist or the local interface MTU cannot be determined.
+#[cfg(target_os = "freebsd")]
+pub fn interface_and_mtu_in_fib(remote: IpAddr, fib: u16) -> Result<Interface> {
+    resolve_query(RouteQuery {
+        fib: Some(fib),
+        ..remote.into()
+    })
+    .map_err(|err| {
+        if err.raw_os_error() == Some(libc::ESRCH) {
+            no_route_err(format!("Destination {remote} not reachable in FIB {fib}"))
+        } else {
+            err
+        }
+    })
+}
+
+/// Like [`interface_and_mtu`], but looks up `remote` in the Linux routing table with ID `table`,
+/// such as the table of a VRF, instead of following the routing policy rules, see `ip-rule(8)`.
+///
+/// [`interface_and_mtu`] follows the routing policy rules, which usually select the main table.
+/// The kernel cannot look up a route in a given table, so this reads the routing tables and picks
+/// the most specific route of `table` that covers `remote`, preferring the lowest metric among
+/// equally specific routes. Unlike a kernel lookup, this ignores source addresses and marks, and
+/// uses the first next hop of multipath routes.
+///
+/// # Errors
+///
+/// This function returns an [`ErrorKind::NotFound`] error if `remote` is not reachable in `table`,
+/// including when the selected route is an unreachable, prohibit, blackhole or throw route, and
+/// another error if the routing tables cannot be read or the local interface MTU cannot be
+/// determined.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn interface_and_mtu_in_table(remote: IpAddr, table: u32) -> Result<Interface> {
+    interface_and_mtu_in_table_impl(remote, table)
+}
+
+/// Like [`interface_and_mtu`], but queries the OpenBSD routing table with ID `rdomain`, such as the
+/// one of a routing domain, see `rdomain(4)`.
+///
+/// This is the OpenBSD analog of Linux routing tables. [`interface_and_mtu`] queries routing table
+/// 0.
+///
+/// # Errors
+///
+/// This function returns an [`ErrorKind::NotFound`] error if `remote` is not reachable in
+/// `rdomain`, and another error if `rdomain` does not exist or the local interface MTU cannot be
+/// determined.
+#[cfg(target_os = "openbsd")]
+pub fn interface_and_mtu_in_rdomain(remote: IpAddr, rdomain: u16) -> Result<Interface> {
+    resolve_query(RouteQuery {
+        rdomain: Some(rdomain),
+        ..remote.into()
+    })
+    .map_err(|err| {
+        if err.raw_os_error() == Some(libc::ESRCH) {
+            no_route_err(format!(
+                "Destination {remote} not reachable in routing table {rdomain}"
+            ))
+        } else {
+            err
+        }
+    })
+}
+
+/// Return the index of the outgoing network interface towards a remote destination identified by
+/// an [`IpAddr`].
+///
+/// This only queries the routing table, which makes it cheaper than [`interface_and_mtu`]. Combine
+/// it with [`mtu_snapshot`] to look up the MTUs of many destinations.
+///
+/// # Errors
+///
+/// This function returns an error if there is no route towards `remote`.
+pub fn interface_index(remote: IpAddr) -> Result<InterfaceIndex> {
+    interface_index_impl(remote.into()).map(InterfaceIndex)
+}
+
+/// Return a map from the index of every local network interface to its maximum transmission unit
+/// (MTU), built from a single enumeration of the interfaces.
+///
+/// Packet-processing loops that see many destinations can combine this with [`interface_index`],
+/// to replace a per-destination MTU query with a map lookup. The snapshot is not updated, so it
+/// becomes stale when interfaces are added, removed or change their MTU; use [`change_generation`]
+/// to find out when to take a new one. On Windows, where an interface has an MTU per address
+/// family, the smaller of the two is used.
+///
+/// # Errors
+///
+/// This function returns an error if the local interfaces cannot be enumerated.
+pub fn mtu_snapshot() -> Result<HashMap<InterfaceIndex, usize>> {
+    Ok(mtu_snapshot_impl()?
+        .into_iter()
+        .map(|(index, mtu)| (InterfaceIndex(index), mtu))
+        .collect())
+}
+
+/// Return the name, index and maximum transmission unit (MTU) of every local network interface,
+/// ordered by index.
+///
+/// This is meant for diagnostics. Interfaces whose MTU cannot be determined are skipped. On
+/// Windows, where an interface has an MTU per address family, it is listed once with the smaller
+/// of the two, as in [`mtu_snapshot`].
+///
+/// # Errors
+///
+/// This function returns an error if the local interfaces cannot be enumerated.
+pub fn list_interfaces() -> Result<Vec<Interface>> {
+    interfaces_impl()
+}
+
+/// Return the name and maximum transmission unit (MTU) of the outgoing network interface towards a
+/// remote network identified by an [`IpAddr`] and a prefix length, such as `10.0.0.0/8`.
+///
+/// Any host bits set in `net` are ignored. On Linux and the BSDs, the prefix length is passed to
+/// the route query, so that the matched route is the one covering the network. On Windows, the
+/// route towards the network address is used.
+///
+/// # Errors
+///
+/// This function returns an [`ErrorKind::InvalidInput`] error if `prefix_len` exceeds the address
+/// length of `net`, and another error if the local interface MTU cannot be determined.
+pub fn interface_and_mtu_for_prefix(net: IpAddr, prefix_len: u8) -> Result<Interface> {
+    if prefix_len > max_prefix_len(net) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Prefix length {prefix_len} out of range for {net}"),
+        ));
+    }
+    resolve_query(RouteQuery {
+        prefix_len,
+        ..network_address(net, prefix_len).into()
+    })
+}
+
+/// Like [`interface_and_mtu`], but for traffic from the local address `local`.
+///
+/// The route query takes `local` into account, so that source-specific routes are honored. On
+/// Linux, `local` is passed as the source of the route query. On the BSDs, it is passed as the
+/// interface address of the route query. On Windows, `GetBestRoute2` is asked for the best route
+/// from `local`.
+///
+/// # Errors
+///
+/// This function returns an [`ErrorKind::InvalidInput`] error if `local` and `remote` are not of
+/// the same address family, an [`ErrorKind::NotFound`] error if `local` is not an address of any
+/// local interface, and another error if the local interface MTU cannot be determined.
+pub fn interface_and_mtu_from(local: IpAddr, remote: IpAddr) -> Result<Interface> {
+    if local.to_canonical().is_ipv4() != remote.to_canonical().is_ipv4() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Source {local} and destination {remote} are of different address families"),
+        ));
+    }
+    if !local_addrs_impl()?
+        .iter()
+        .any(|(addr, _)| *addr == local.to_canonical())
+    {
+        return Err(default_err());
+    }
+    resolve_query(RouteQuery {
+        src: Some(local),
+        ..remote.into()
+    })
+}
+
+/// Like [`interface_and_mtu`], but constrained to routes through the interface with index
+/// `oif_index`.
+///
+/// This is for callers that already track interface indices. On Linux, the index is passed to the
+/// route query. On Windows, `GetBestRoute2` is asked for the best route through that interface. On
+/// the BSDs, which cannot constrain the route query, the best route needs to go through that
+/// interface.
+///
+/// # Errors
+///
+/// This function returns an [`ErrorKind::NotFound`] error if `remote` is not reachable via the
+/// given interface, and another error if the local interface MTU cannot be determined.
+pub fn interface_and_mtu_via_index(remote: IpAddr, oif_index: InterfaceIndex) -> Result<Interface> {
+    resolve_query(RouteQuery {
+        oif: Some(oif_index.0),
+        ..remote.into()
+    })
+}
+
+/// Return the name and maximum transmission unit (MTU) of the network interface on which a packet
+/// was received, as indicated by a packet information control message.
+///
+/// `cmsg` must hold exactly one control message, starting with its `cmsghdr` (`WSACMSGHDR` on
+/// Windows), as returned by `recvmsg` (`WSARecvMsg` on Windows) in the control buffer. The
+/// following control messages are supported:
+///
+/// * `IPPROTO_IPV6`/`IPV6_PKTINFO`, carrying an `in6_pktinfo`, on all platforms.
+/// * `IPPROTO_IP`/`IP_PKTINFO`, carrying an `in_pktinfo`, on Linux, macOS, Solaris, illumos and
+///   Windows.
+/// * `IPPROTO_IP`/`IP_RECVIF`, carrying a `sockaddr_dl`, on macOS, FreeBSD, DragonFly BSD, NetBSD
+///   and OpenBSD.
+///
+/// # Errors
+///
+/// This function returns an [`ErrorKind::InvalidInput`] error if `cmsg` is not a supported
+/// control message, and another error if the local interface MTU cannot be determined.
+pub fn interface_and_mtu_from_pktinfo(cmsg: &[u8]) -> Result<Interface> {
+    Interface::from_index(InterfaceIndex(pktinfo::if_index(cmsg)?))
+}
+
+/// Return the name and maximum transmission unit (MTU) of the network interface with the given
+/// index.
+///
+/// `index` is the interface index native to the operating system, as in [`Interface::index`].
+/// This lets callers that cached the index from an earlier [`interface_and_mtu`] call cheaply
+/// refresh the MTU, for example after a link change, without another route query. It is the same
+/// as [`Interface::from_index`], but only returns the name and MTU.
+///
+/// # Errors
+///
+/// This function returns an error if there is no interface with the given index or its name or
+/// MTU cannot be determined.
+pub fn mtu_for_interface_index(index: InterfaceIndex) -> Result<(String, usize)> {
+    Interface::from_index(index).map(Into::into)
+}
+
+/// Return the largest payload of a link-layer frame on the network interface with the given index,
+/// which is its maximum transmission unit (MTU).
+///
+/// This is meant for raw link-layer sockets (`AF_PACKET` on Linux, `AF_LINK` or BPF on the BSDs)
+/// bound to that interface. The payload is the whole IP packet, including the IP header and any
+/// transport header, so callers that send UDP datagrams need to subtract those, see
+/// [`max_udp_payload`]. It does not include the link-layer header, such as the 14-byte Ethernet
+/// header, which senders that build complete frames need to add to obtain the largest frame they
+/// can send. It is the same as [`InterfaceHandle::mtu`] for a handle with that index.
+///
+/// # Errors
+///
+/// This function returns an error if there is no interface with the given index or its MTU cannot
+/// be determined.
+pub fn max_frame_payload(index: InterfaceIndex) -> Result<usize> {
+    InterfaceHandle::from_index(index).mtu()
+}
+
+/// Return whether more than one equally-preferred route could carry traffic towards a remote
+/// destination identified by an [`IpAddr`].
+///
+/// If this returns `true`, the interface and MTU returned by [`interface_and_mtu`] may change
+/// between calls, for example because the kernel balances flows across several next hops. On
+/// Linux, this checks whether the matching routing table entry is a multipath route, which is also
+/// how the kernel represents several IPv6 routes with the same metric. This requires Linux 4.13 or
+/// later, and older kernels always report `false`. On other platforms, the operating system only
+/// reports the single best route, and this always returns `false`.
+///
+/// # Errors
+///
+/// This function returns an error if there is no route towards `remote`.
+pub fn is_route_ambiguous(remote: IpAddr) -> Result<bool> {
+    is_route_ambiguous_impl(remote.into())
+}
+
+/// Return whether a remote destination identified by an [`IpAddr`] is on-link, i.e., directly
+/// reachable without going through a gateway.
+///
+/// For on-link destinations, the full MTU of the outgoing interface is likely usable, while for
+/// destinations behind a gateway, the path MTU may be smaller. Loopback destinations are on-link.
+///
+/// # Errors
+///
+/// This function returns an error if there is no route towards `remote`.
+pub fn is_on_link(remote: IpAddr) -> Result<bool> {
+    is_on_link_impl(remote.into())
+}
+
+/// An entry of a routing table, as returned by [`routes`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct RouteEntry {
+    /// The destination network address. This is the unspecified address for default routes.
+    pub destination: IpAddr,
+    /// The prefix length of the destination network.
+    pub prefix_len: u8,
+    /// The next hop, unless the destination is directly reachable.
+    pub gateway: Option<IpAddr>,
+    /// The index of the outgoing interface. For multipath routes on Linux, this is the interface
+    /// of the first next hop.
+    pub oif_index: InterfaceIndex,
+    /// The MTU of the route, if it has one that overrides the interface MTU. This is always `None`
+    /// on Windows, which has no per-route MTUs.
+    pub mtu: Option<usize>,
+}
+
+/// Return the entries of the routing tables of the system, for diagnostic purposes.
+///
+/// On Linux, this includes the routes of all routing tables, including the local table. On macOS
+/// and the BSDs, it includes the routes of the default routing table. Enumerating routes is not
+/// supported on Solaris and illumos.
+///
+/// # Errors
+///
+/// This function returns an error if the routing tables cannot be read.
+pub fn routes() -> Result<Vec<RouteEntry>> {
+    routes_impl()
+}
+
+/// Map "not a socket" errors to [`ErrorKind::InvalidInput`].
+fn not_a_socket(err: Error) -> Error {
+    #[cfg(not(target_os = "windows"))]
+    const ENOTSOCK: i32 = libc::ENOTSOCK;
+    #[cfg(target_os = "windows")]
+    const ENOTSOCK: i32 = 10038; // WSAENOTSOCK
+    if err.raw_os_error() == Some(ENOTSOCK) {
+        Error::new(ErrorKind::InvalidInput, err)
+    } else {
+        err
+    }
+}
+
+/// Return the maximum transmission unit (MTU) of the network interface with the given name.
+///
+/// This is the MTU that applies to a socket pinned to that device, for example via
+/// `SO_BINDTODEVICE` on Linux, regardless of its destination. For such sockets, it is simpler and
+/// more accurate than a route query.
+///
+/// # Errors
+///
+/// This function returns an [`ErrorKind::InvalidInput`] error if `name` contains a NUL byte, an
+/// [`ErrorKind::NotFound`] error if there is no interface with that name, and another error if its
+/// MTU cannot be determined.
+pub fn mtu_for_bound_device(name: &str) -> Result<usize> {
+    InterfaceHandle::from_name(name)?.mtu()
+}
+
+/// Return the maximum transmission unit (MTU) of the network interface with the given name,
+/// without a route query.
+///
+/// This is for callers that already know the interface they are sending on, for example from
+/// their own configuration. It is the same as [`mtu_for_bound_device`].
+///
+/// # Errors
+///
+/// This function returns an error if there is no interface with that name, or its MTU cannot be
+/// determined, see [`mtu_for_bound_device`].
+pub fn mtu_for_interface_name(name: &str) -> Result<usize> {
+    mtu_for_bound_device(name)
+}
+
+/// Return the source address and maximum transmission unit (MTU) for traffic towards a remote
+/// destination identified by an [`IpAddr`] that is sent out of the interface named `oif`.
+///
+/// The operating system selects the source address on that interface. On Linux, the route query is
+/// pinned to the interface, and the source address is the preferred source of the resulting route.
+/// Elsewhere, the best route needs to go through that interface, and the source address is the
+/// one that the operating system selects for a UDP socket connected to `remote`.
+///
+/// # Errors
+///
+/// This function returns an [`ErrorKind::NotFound`] error if there is no interface named `oif`
+/// or `remote` is not reachable via it, and another error if the source address or MTU cannot be
+/// determined.
+#[cfg(not(target_os = "windows"))]
+pub fn interface_and_mtu_via_name_auto_src(remote: IpAddr, oif: &str) -> Result<(IpAddr, usize)> {
+    let query = RouteQuery {
+        oif: Some(InterfaceHandle::from_name(oif)?.index().0),
+        ..remote.into()
+    };
+    source_and_mtu_via_impl(query)
+}
+
+/// Return the source address and MTU towards `query.remote`, if the best route goes through
+/// `query.oif`.
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "windows")))]
+fn source_and_mtu_via_impl(query: RouteQuery) -> Result<(IpAddr, usize)> {
+    let mtu = resolve_query(query)?.mtu;
+    Ok((connected_source(query.remote)?, mtu))
+}
+
+/// Return the source address that the operating system selects for a UDP socket connected to
+/// `remote`.
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "windows")))]
+fn connected_source(remote: IpAddr) -> Result<IpAddr> {
+    Ok(connected_socket(remote)?.local_addr()?.ip())
+}
+
+/// Return a UDP socket connected to `remote`.
+#[cfg(not(target_os = "windows"))]
+fn connected_socket(remote: IpAddr) -> Result<UdpSocket> {
+    let unspecified = match remote {
+        IpAddr::V4(_) => IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    // Connecting a UDP socket does not send anything, but makes the kernel select a route and a
+    // source address. The port does not matter.
+    let socket = UdpSocket::bind((unspecified, 0))?;
+    socket.connect((remote, 9))?;
+    Ok(socket)
+}
+
+/// Return the index of the interface named `name`, or `None` if there is none.
+#[cfg(not(target_os = "windows"))]
+fn interface_index_by_name(name: &CStr) -> Result<Option<u32>> {
+    // SAFETY: `name` is a NUL-terminated string that outlives the call.
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if index != 0 {
+        return Ok(Some(index));
+    }
+    // A return value of 0 also reports other failures, such as when the socket that the C library
+    // uses for the lookup cannot be opened.
+    let err = Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::ENODEV | libc::ENXIO) => Ok(None),
+        _ => Err(err),
+    }
+}
+
+/// A handle to a network interface that remains valid when the interface is renamed.
+///
+/// The handle holds the interface index. [`InterfaceHandle::name`] and [`InterfaceHandle::mtu`]
+/// look up the current values by that index on every call, so they only fail once the interface
+/// has been removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InterfaceHandle {
+    index: InterfaceIndex,
+}
+
+impl InterfaceHandle {
+    /// Return a handle to the interface with the given index.
+    ///
+    /// This does not check whether the interface exists.
+    #[must_use]
+    pub const fn from_index(index: InterfaceIndex) -> Self {
+        Self { index }
+    }
+
+    /// Return a handle to the interface with the given name.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an [`ErrorKind::InvalidInput`] error if `name` contains a NUL byte,
+    /// an [`ErrorKind::NotFound`] error if there is no interface with that name, and the OS error
+    /// if the lookup fails otherwise.
+    pub fn from_name(name: &str) -> Result<Self> {
+        let c_name = CString::new(name).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        #[cfg(not(target_os = "windows"))]
+        let index = interface_index_by_name(&c_name)?;
+        #[cfg(target_os = "windows")]
+        let index = windows::interface_index_by_name(&c_name);
+        index
+            .map(|index| Self::from_index(InterfaceIndex(index)))
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("No interface named {name}")))
+    }
+
+    /// Return the index of the interface.
+    #[must_use]
+    pub const fn index(&self) -> InterfaceIndex {
+        self.index
+    }
+
+    /// Return the current name of the interface.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the interface no longer exists or its name cannot be
+    /// determined.
+    pub fn name(&self) -> Result<String> {
+        interface_and_mtu_by_index_impl(self.index.0).map(|(name, _)| name)
+    }
+
+    /// Return the current maximum transmission unit (MTU) of the interface.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the interface no longer exists or its MTU cannot be
+    /// determined.
+    pub fn mtu(&self) -> Result<usize> {
+        interface_and_mtu_by_index_impl(self.index.0).map(|(_, mtu)| mtu)
+    }
+}
+
+/// The traffic counters of a network interface, as returned by [`interface_stats`].
+///
+/// The counters are cumulative since the interface was created or the system booted, and may wrap
+/// around. Compare two samples to obtain the traffic in between.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct InterfaceStats {
+    /// The number of bytes received.
+    pub rx_bytes: u64,
+    /// The number of bytes sent.
+    pub tx_bytes: u64,
+    /// The number of packets received.
+    pub rx_packets: u64,
+    /// The number of packets sent.
+    pub tx_packets: u64,
+}
+
+/// Return the byte and packet counters of a network interface, identified by a handle obtained
+/// from its index or, except on Windows, its name.
+///
+/// On Linux, these are the 64-bit link statistics of the interface. On macOS and the BSDs, they are
+/// the counters in its `if_data`, which are only 32 bits wide on macOS and therefore wrap after
+/// 4 GiB. On Windows, the packet counters include unicast and non-unicast packets. Interface
+/// statistics are not supported on Solaris and illumos.
+///
+/// ```
+/// # use std::net::{IpAddr, Ipv4Addr};
+/// use mtu::{interface_and_mtu, interface_stats, InterfaceHandle};
+///
+/// let loopback = interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+/// let stats = interface_stats(InterfaceHandle::from_index(loopback.index)).unwrap();
+/// println!("{}: {} bytes received", loopback.name, stats.rx_bytes);
+/// ```
+///
+/// # Errors
+///
+/// This function returns an error if the interface does not exist or its counters cannot be read.
+pub fn interface_stats(interface: InterfaceHandle) -> Result<InterfaceStats> {
+    interface_stats_impl(interface.index.0)
+}
+
+/// Return the IP address of the peer of the socket `fd`, or its local address if `peer` is false.
+#[cfg(not(target_os = "windows"))]
+fn socket_ip(fd: BorrowedFd<'_>, peer: bool) -> Result<IpAddr> {
+    // SAFETY: All-zero bytes are a valid `sockaddr_storage`.
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    #[allow(clippy::cast_possible_truncation)] // It is 128 bytes.
+    let mut len = std::mem::size_of_val(&storage) as libc::socklen_t;
+    let name = std::ptr::from_mut(&mut storage).cast::<libc::sockaddr>();
+    // SAFETY: `fd` is open for the lifetime of the borrow, and `storage` has room for the `len`
+    // bytes that are written at most, which fit any socket address.
+    let res = unsafe {
+        if peer {
+            libc::getpeername(fd.as_raw_fd(), name, std::ptr::from_mut(&mut len))
+        } else {
+            libc::getsockname(fd.as_raw_fd(), name, std::ptr::from_mut(&mut len))
+        }
+    };
+    if res == -1 {
+        return Err(Error::last_os_error());
+    }
+    match i32::from(storage.ss_family) {
+        libc::AF_INET => {
+            // SAFETY: The address of an `AF_INET` socket is a `sockaddr_in`, which fits in and is
+            // no more aligned than `storage`.
+            let sin = unsafe { &*std::ptr::from_ref(&storage).cast::<libc::sockaddr_in>() };
+            Ok(IpAddr::V4(Ipv4Addr::from(u32::from_be(
+                sin.sin_addr.s_addr,
+            ))))
+        }
+        libc::AF_INET6 => {
+            // SAFETY: The address of an `AF_INET6` socket is a `sockaddr_in6`, which fits in and
+            // is no more aligned than `storage`.
+            let sin6 = unsafe { &*std::ptr::from_ref(&storage).cast::<libc::sockaddr_in6>() };
+            Ok(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+        }
+        _ => Err(Error::new(ErrorKind::InvalidInput, "Not an IP socket")),
+    }
+}
+
+/// Return the outgoing interface of a socket for which `socket_ip` returns the peer or local
+/// address. A connected socket routes towards its peer, and a socket that is only bound to a
+/// local address sends from the interface with that address.
+fn socket_interface(socket_ip: impl Fn(bool) -> Result<IpAddr>) -> Result<Interface> {
+    #[cfg(not(target_os = "windows"))]
+    const ENOTCONN: i32 = libc::ENOTCONN;
+    #[cfg(target_os = "windows")]
+    const ENOTCONN: i32 = 10057; // WSAENOTCONN
+    let err = match socket_ip(true).map_err(not_a_socket) {
+        Ok(peer) => return interface_and_mtu(peer),
+        Err(err) if err.raw_os_error() == Some(ENOTCONN) => err,
+        Err(err) => return Err(err),
+    };
+    let local = socket_ip(false)?.to_canonical();
+    if local.is_unspecified() {
+        return Err(err);
+    }
+    let (_, index) = local_addrs_impl()?
+        .into_iter()
+        .find(|(addr, _)| *addr == local)
+        .ok_or_else(default_err)?;
+    Interface::from_index(InterfaceIndex(index))
+}
+
+/// Return the name and maximum transmission unit (MTU) of the outgoing network interface of a
+/// socket.
+///
+/// On Linux, if the socket is bound to a device via `SO_BINDTODEVICE`, that device is returned.
+/// Otherwise, if the socket is connected, the outgoing interface is determined by routing towards
+/// its peer address, and if it is only bound to a local address, it is the interface with that
+/// address.
+///
+/// # Errors
+///
+/// This function returns an [`ErrorKind::InvalidInput`] error if `fd` is not a socket, and
+/// another error if the socket is neither connected nor bound to a local address, or the local
+/// interface MTU cannot be determined.
+#[cfg(not(target_os = "windows"))]
+pub fn interface_and_mtu_from_raw_fd(fd: BorrowedFd<'_>) -> Result<Interface> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if let Some(index) = linux::bound_device_index(fd).map_err(not_a_socket)? {
+        return Interface::from_index(InterfaceIndex(index));
+    }
+    socket_interface(|peer| socket_ip(fd, peer))
+}
+
+/// Return the name and maximum transmission unit (MTU) of the outgoing network interface of a
+/// socket.
+///
+/// If the socket is connected, the outgoing interface is determined by routing towards its peer
+/// address, and if it is only bound to a local address, it is the interface with that address.
+///
+/// # Errors
+///
+/// This function returns an [`ErrorKind::InvalidInput`] error if `socket` is not a socket, and
+/// another error if the socket is neither connected nor bound to a local address, or the local
+/// interface MTU cannot be determined.
+#[cfg(target_os = "windows")]
+pub fn interface_and_mtu_from_raw_socket(socket: BorrowedSocket<'_>) -> Result<Interface> {
+    socket_interface(|peer| socket_ip(socket, peer))
+}
+
+/// An extension trait to return the path MTU of a connected socket.
+///
+/// Once a socket is connected, the kernel has chosen its outgoing interface, and resolving the
+/// peer address again can race with route changes or disagree with that choice. On Linux, the path
+/// MTU is the one the kernel has cached for the socket, via `IP_MTU` or `IPV6_MTU`, which reflects
+/// any path MTU discovery that has run, and is at most 65,535 bytes for IPv4. On the other
+/// platforms, it is the MTU of the outgoing interface towards the peer address of the socket, as
+/// returned by [`interface_and_mtu`].
+pub trait PathMtuExt {
+    /// Return the path MTU of the socket.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the socket is not connected or its path MTU cannot be
+    /// determined.
+    fn path_mtu(&self) -> Result<usize>;
+}
+
+impl PathMtuExt for UdpSocket {
+    fn path_mtu(&self) -> Result<usize> {
+        socket_path_mtu(self, self.peer_addr()?)
+    }
+}
+
+impl PathMtuExt for TcpStream {
+    fn path_mtu(&self) -> Result<usize> {
+        socket_path_mtu(self, self.peer_addr()?)
+    }
+}
+
+/// Return the path MTU of `socket`, which is connected to `peer`. The family of `peer` is that of
+/// the socket, including for IPv4-mapped IPv6 addresses.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn socket_path_mtu(socket: &impl AsFd, peer: SocketAddr) -> Result<usize> {
+    linux::path_mtu(socket.as_fd(), peer.is_ipv6())
+}
+
+/// Return the path MTU of `socket`, which is connected to `peer`, from the outgoing interface
+/// towards `peer`.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn socket_path_mtu<S>(_socket: &S, peer: SocketAddr) -> Result<usize> {
+    interface_and_mtu(peer.ip()).map(|interface| interface.mtu)
+}
+
+/// Return the path maximum transmission unit (MTU) towards a remote destination identified by an
+/// [`IpAddr`].
+///
+/// Unlike [`interface_and_mtu`], which returns the MTU of the outgoing link, this is the MTU that
+/// the operating system currently uses for the path towards `remote`. It can be smaller than the
+/// link MTU, for example after path MTU discovery or when the route has an MTU of its own, which
+/// makes it useful as a lower bound for DPLPMTUD in QUIC. On Linux, it is the MTU that the kernel
+/// reports via `IP_MTU` or `IPV6_MTU` for a temporary UDP socket connected to `remote`. On macOS
+/// and the BSDs, it is the MTU of the route towards `remote`. This is zero if the path MTU is
+/// unknown, which is always the case on Windows. IPv4-mapped IPv6 addresses are treated as the IPv4
+/// addresses they map.
+///
+/// # Errors
+///
+/// This function returns an error if there is no route towards `remote` or the path MTU cannot be
+/// determined.
+pub fn path_mtu(remote: IpAddr) -> Result<usize> {
+    let remote = remote.to_canonical();
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        connected_socket(remote)?.path_mtu()
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    interface_and_route_mtu_impl(remote.into()).map(|(_, mtu)| mtu.unwrap_or(0))
+}
+
+/// Return a best-effort generation counter for the local network interfaces and routes, which
+/// callers can poll cheaply to decide whether to invalidate any cached MTU information.
+///
+/// The counter is monotonic within a process, and starts at an arbitrary value. Whenever it
+/// differs from a previously returned value, something may have changed since. On Linux and the
+/// BSDs, the first call starts a background thread that monitors a netlink or route socket for
+/// interface and route changes. On Windows, there is no such monitor, and each call compares the
+/// current interface table against the one seen by the previous call.
+///
+/// # Errors
+///
+/// This function returns an error if the monitor cannot be started or the interface table cannot
+/// be obtained.
+pub fn change_generation() -> Result<u64> {
+    change_generation_impl()
+}
+
+/// Look up the outgoing network interface towards a remote destination identified by an
+/// [`IpAddr`] again, and return it if it differs from `prev`.
+///
+/// This returns `None` if neither the name nor the MTU of the interface changed. It pairs well with
+/// [`change_generation`], which tells when a new lookup may be worthwhile.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined.
+pub fn changed(prev: &Interface, remote: IpAddr) -> Result<Option<Interface>> {
+    let new = interface_and_mtu(remote)?;
+    Ok((new != *prev).then_some(new))
+}
+
+/// A change to a local network interface or route, as returned by [`MtuWatcher::recv`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChangeEvent {
+    /// The index of the interface that changed, or that a changed route goes through. This is
+    /// `None` if the change cannot be attributed to a single interface, for example because
+    /// notifications were lost, in which case any interface may have changed.
+    pub index: Option<InterfaceIndex>,
+    /// The MTU of the interface after the change, if the change reports one. This is `None` for
+    /// removed interfaces and for changes that do not carry the interface MTU, such as route
+    /// changes; use [`mtu_for_interface_index`] to look it up.
+    pub mtu: Option<usize>,
+}
+
+impl ChangeEvent {
+    /// The event for a change that cannot be attributed to a single interface.
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", bsd))]
+    const UNKNOWN: Self = Self {
+        index: None,
+        mtu: None,
+    };
+}
+
+/// A watcher for changes to the local network interfaces and routes, such as a handoff from Wi-Fi
+/// to cellular or a changed MTU.
+///
+/// This is meant for long-lived processes that want to be told when the outgoing interface or its
+/// MTU may have changed, rather than polling [`change_generation`]. On Linux, the watcher reads
+/// the notifications of a netlink socket subscribed to link and route changes. On macOS and the
+/// BSDs, it reads the unsolicited interface, address and route messages of a routing socket. On
+/// Windows, it registers for interface change notifications via `NotifyIpInterfaceChange`.
+///
+/// Events are best-effort hints to look up the MTU towards a destination again, see [`changed`].
+#[derive(Debug)]
+pub struct MtuWatcher(Watcher);
+
+impl MtuWatcher {
+    /// Create a watcher and start receiving change notifications. Only changes after this call
+    /// are reported.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the notifications cannot be subscribed to.
+    pub fn new() -> Result<Self> {
+        Watcher::new().map(Self)
+    }
+
+    /// Wait for the next change and return it.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if receiving the next notification fails.
+    pub fn recv(&mut self) -> Result<ChangeEvent> {
+        self.0.recv()
+    }
+
+    /// Like [`MtuWatcher::recv`], but return `None` if no change arrives within `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if receiving the next notification fails.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<ChangeEvent>> {
+        self.0.recv_timeout(timeout)
+    }
+}
+
+/// A change to a local network interface, as yielded by [`watch_stream`].
+#[cfg(feature = "tokio")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InterfaceChange {
+    /// The interface with this index was added.
+    Added {
+        /// The index of the interface.
+        index: InterfaceIndex,
+    },
+    /// The interface with this index was removed.
+    Removed {
+        /// The index of the interface.
+        index: InterfaceIndex,
+    },
+    /// The MTU of the interface with this index changed from `old` to `new`.
+    MtuChanged {
+        /// The index of the interface.
+        index: InterfaceIndex,
+        /// The MTU before the change.
+        old: usize,
+        /// The MTU after the change.
+        new: usize,
+    },
+    /// The interface with this index, or an address or route of it, changed without a change of
+    /// its MTU, for example because it went down or up.
+    StateChanged {
+        /// The index of the interface.
+        index: InterfaceIndex,
+    },
+}
+
+/// Compare the MTUs of the interfaces in `current` with those in `snapshot` and return the
+/// resulting changes, updating `snapshot` to match. If `event_index` is `Some`, only that interface
+/// is compared, and a change event without an MTU change yields [`InterfaceChange::StateChanged`].
+#[cfg(feature = "tokio")]
+fn interface_changes(
+    snapshot: &mut HashMap<InterfaceIndex, usize>,
+    current: &HashMap<InterfaceIndex, usize>,
+    event_index: Option<InterfaceIndex>,
+) -> Vec<InterfaceChange> {
+    let indices: std::collections::BTreeSet<InterfaceIndex> = event_index.map_or_else(
+        || snapshot.keys().chain(current.keys()).copied().collect(),
+        |index| [index].into(),
+    );
+    let mut changes = Vec::new();
+    for index in indices {
+        let change = match (snapshot.get(&index), current.get(&index)) {
+            (None, Some(_)) => InterfaceChange::Added { index },
+            (Some(_), None) => InterfaceChange::Removed { index },
+            (Some(&old), Some(&new)) if old != new => {
+                InterfaceChange::MtuChanged { index, old, new }
+            }
+            // An interface that is unchanged after an event that was not attributed to it.
+            (Some(_), Some(_)) if event_index.is_none() => continue,
+            (Some(_), Some(_)) => InterfaceChange::StateChanged { index },
+            (None, None) => continue,
+        };
+        match current.get(&index) {
+            Some(&mtu) => snapshot.insert(index, mtu),
+            None => snapshot.remove(&index),
+        };
+        changes.push(change);
+    }
+    changes
+}
+
+/// Return the MTUs of the interfaces that `event` may have changed. For an event that is attributed
+/// to an interface, this is the MTU of that interface, as carried by the event or otherwise looked
+/// up, and empty if the interface is gone. For other events, it is the MTUs of all interfaces.
+#[cfg(feature = "tokio")]
+fn event_mtus(event: ChangeEvent) -> Result<HashMap<InterfaceIndex, usize>> {
+    let Some(index) = event.index else {
+        return mtu_snapshot();
+    };
+    let mtu = event
+        .mtu
+        .or_else(|| mtu_for_interface_index(index).ok().map(|(_, mtu)| mtu));
+    Ok(mtu.map(|mtu| (index, mtu)).into_iter().collect())
+}
+
+/// How long the background thread of [`watch_stream`] waits for a change before checking whether
+/// the stream was dropped.
+#[cfg(feature = "tokio")]
+const WATCH_STREAM_POLL: Duration = Duration::from_millis(100);
+
+/// The stream returned by [`watch_stream`].
+#[cfg(feature = "tokio")]
+struct WatchStream(tokio::sync::mpsc::UnboundedReceiver<Result<InterfaceChange>>);
+
+#[cfg(feature = "tokio")]
+impl futures_core::Stream for WatchStream {
+    type Item = Result<InterfaceChange>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+/// Return a stream of the changes to the local network interfaces: interfaces that are added or
+/// removed, that change their MTU, or that otherwise change state.
+///
+/// The stream is built on an [`MtuWatcher`], which a background thread waits on. The thread keeps
+/// a snapshot of the interface MTUs, see [`mtu_snapshot`]. For a change notification of an
+/// interface, it compares the MTU that the notification carries, or that it looks up for that
+/// interface, against the snapshot, and for other notifications, it compares all interfaces. It
+/// then yields the differences. Only changes after this call are reported. The stream ends after
+/// yielding an error. When the stream is dropped, the background thread exits within 100 ms.
+///
+/// This function is only available with the `tokio` feature.
+#[cfg(feature = "tokio")]
+pub fn watch_stream() -> impl futures_core::Stream<Item = Result<InterfaceChange>> {
+    let (sender, changes) = tokio::sync::mpsc::unbounded_channel();
+    let (ready, started) = std::sync::mpsc::channel();
+    let spawn_sender = sender.clone();
+    let spawned = std::thread::Builder::new()
+        .name("mtu-watch".to_string())
+        .spawn(move || {
+            // The watcher is created on this thread, because it is not `Send` on all platforms.
+            let watcher = MtuWatcher::new().and_then(|watcher| Ok((watcher, mtu_snapshot()?)));
+            _ = ready.send(());
+            let (mut watcher, mut snapshot) = match watcher {
+                Ok(watcher) => watcher,
+                Err(e) => {
+                    _ = spawn_sender.send(Err(e));
+                    return;
+                }
+            };
+            loop {
+                let event = match watcher.recv_timeout(WATCH_STREAM_POLL) {
+                    Ok(Some(event)) => event,
+                    Ok(None) if spawn_sender.is_closed() => return,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        _ = spawn_sender.send(Err(e));
+                        return;
+                    }
+                };
+                let changes = event_mtus(event)
+                    .map(|current| interface_changes(&mut snapshot, &current, event.index));
+                match changes {
+                    Ok(changes) => {
+                        if changes
+                            .into_iter()
+                            .any(|change| spawn_sender.send(Ok(change)).is_err())
+                        {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        _ = spawn_sender.send(Err(e));
+                        return;
+                    }
+                }
+                if spawn_sender.is_closed() {
+                    return;
+                }
+            }
+        });
+    match spawned {
+        // Wait until the watcher is subscribed, so that no later change is missed.
+        Ok(_) => _ = started.recv(),
+        Err(e) => _ = sender.send(Err(e)),
+    }
+    WatchStream(changes)
+}
+
+/// Size of the IPv4 header (without options) plus the UDP header.
+const IPV4_UDP_HEADER_LEN: usize = 20 + 8;
+/// Size of the IPv6 header (without extension headers) plus the UDP header.
+const IPV6_UDP_HEADER_LEN: usize = 40 + 8;
+
+/// Return the largest UDP payload that can be sent towards a remote destination identified by an
+/// [`IpAddr`], depending on whether the caller permits IP fragmentation.
+///
+/// When `fragmentation` is `false` (i.e., the "don't fragment" bit is set, as is always the case
+/// for QUIC), the result is the MTU of the outgoing interface minus the IP and UDP header sizes.
+/// When `fragmentation` is `true`, datagrams are not bounded by the interface MTU, and the result
+/// is the maximum UDP payload that fits into a single IP packet, i.e., 65,507 bytes for IPv4 and
+/// 65,527 bytes for IPv6.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined.
+pub fn max_datagram_size(remote: IpAddr, fragmentation: bool) -> Result<usize> {
+    if fragmentation {
+        return Ok(max_fragmented_udp_payload(remote));
+    }
+    let mtu = interface_and_mtu(remote)?.mtu;
+    Ok(max_udp_payload(mtu, remote))
+}
+
+/// Return the IP and UDP header length and the largest UDP payload that fits into a single IP
+/// packet towards `remote`.
+const fn udp_header_len_and_max_payload(remote: IpAddr) -> (usize, usize) {
+    match remote {
+        // The IPv4 total length field includes the IPv4 header.
+        IpAddr::V4(_) => (
+            IPV4_UDP_HEADER_LEN,
+            MAX_IP_PACKET_SIZE - IPV4_UDP_HEADER_LEN,
+        ),
+        // The IPv6 payload length field excludes the IPv6 header.
+        IpAddr::V6(_) => (
+            IPV6_UDP_HEADER_LEN,
+            MAX_IP_PACKET_SIZE + 40 - IPV6_UDP_HEADER_LEN,
+        ),
+    }
+}
+
+/// Return the largest UDP payload that fits into a single IP packet towards `remote`.
+const fn max_fragmented_udp_payload(remote: IpAddr) -> usize {
+    udp_header_len_and_max_payload(remote).1
+}
+
+/// Return the largest UDP payload that fits into a single unfragmented IP packet of the family of
+/// `ip` on an interface with the given `mtu`.
+///
+/// This is `mtu` minus the IP header without options or extension headers and the UDP header, i.e.,
+/// 28 bytes for IPv4 and 48 bytes for IPv6, or zero if `mtu` is smaller. It is capped at the
+/// largest UDP payload of any IP packet, i.e., 65,507 bytes for IPv4 and 65,527 bytes for IPv6.
+///
+/// # Example
+///
+/// ```
+/// # use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+/// assert_eq!(
+///     mtu::max_udp_payload(1500, IpAddr::V4(Ipv4Addr::LOCALHOST)),
+///     1472
+/// );
+/// assert_eq!(
+///     mtu::max_udp_payload(1500, IpAddr::V6(Ipv6Addr::LOCALHOST)),
+///     1452
+/// );
+/// ```
+#[must_use]
+pub const fn max_udp_payload(mtu: usize, ip: IpAddr) -> usize {
+    let (header_len, max_payload) = udp_header_len_and_max_payload(ip);
+    let payload = mtu.saturating_sub(header_len);
+    if payload < max_payload {
+        payload
+    } else {
+        max_payload
+    }
+}
+
+/// Like [`max_udp_payload`], for IPv4.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(mtu::max_udp_payload_v4(1500), 1472);
+/// assert_eq!(mtu::max_udp_payload_v4(20), 0);
+/// ```
+#[must_use]
+pub const fn max_udp_payload_v4(mtu: usize) -> usize {
+    max_udp_payload(mtu, IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+}
+
+/// Like [`max_udp_payload`], for IPv6.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(mtu::max_udp_payload_v6(1500), 1452);
+/// assert_eq!(mtu::max_udp_payload_v6(40), 0);
+/// ```
+#[must_use]
+pub const fn max_udp_payload_v6(mtu: usize) -> usize {
+    max_udp_payload(mtu, IpAddr::V6(Ipv6Addr::UNSPECIFIED))
+}
+
+/// The smallest UDP payload of a QUIC Initial packet, see RFC 9000, Section 14.1.
+const QUIC_MIN_INITIAL_SIZE: usize = 1200;
+/// The IP packet size that caps QUIC Initial packets, so that they also fit on common paths with
+/// an Ethernet MTU.
+const QUIC_INITIAL_MAX_IP_PACKET_SIZE: usize = consts::ETHERNET_MTU;
+
+/// Return the UDP payload size to use for QUIC Initial packets towards a remote destination
+/// identified by an [`IpAddr`].
+///
+/// This is the usable UDP payload of the outgoing interface, as returned by [`max_datagram_size`]
+/// without fragmentation, capped to what fits into a 1,500-byte IP packet (1,472 bytes for IPv4
+/// and 1,452 bytes for IPv6). It is never less than the 1,200 bytes that QUIC requires for Initial
+/// packets, and is 1,200 if the interface MTU cannot be determined.
+#[must_use]
+pub fn quic_initial_mtu(remote: IpAddr) -> usize {
+    quic_initial_size(remote, max_datagram_size(remote, false))
+}
+
+/// Clamp the result of [`max_datagram_size`] towards `remote` to the QUIC Initial packet size.
+fn quic_initial_size(remote: IpAddr, max_datagram_size: Result<usize>) -> usize {
+    let cap = max_udp_payload(QUIC_INITIAL_MAX_IP_PACKET_SIZE, remote);
+    max_datagram_size.map_or(QUIC_MIN_INITIAL_SIZE, |size| {
+        size.min(cap).max(QUIC_MIN_INITIAL_SIZE)
+    })
+}
+
+/// Return the number of segments that a UDP payload of `total_payload` bytes splits into when sent
+/// with generic segmentation offload (GSO) towards a remote destination identified by an
+/// [`IpAddr`].
+///
+/// Each segment is at most the usable UDP payload of the outgoing interface, as returned by
+/// [`max_datagram_size`] without fragmentation. If the interface MTU exceeds the maximum IP packet
+/// size, as for loopback interfaces on some platforms, segments are only bounded by the maximum
+/// UDP payload, so any payload that fits into a single datagram is one segment. An empty payload
+/// has no segments.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined, or if it is too
+/// small to carry any UDP payload.
+pub fn gso_segments(total_payload: usize, remote: IpAddr) -> Result<usize> {
+    let mtu = interface_and_mtu(remote)?.mtu;
+    gso_segment_count(total_payload, remote, mtu)
+}
+
+/// Return the number of segments that `total_payload` splits into towards `remote` on an interface
+/// with the given `mtu`.
+fn gso_segment_count(total_payload: usize, remote: IpAddr, mtu: usize) -> Result<usize> {
+    let segment_size = max_udp_payload(mtu, remote);
+    if segment_size == 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("MTU {mtu} too small for UDP towards {remote}"),
+        ));
+    }
+    Ok(total_payload.div_ceil(segment_size))
+}
+
+/// The OS error codes that indicate that a destination has no usable route.
+#[cfg(not(target_os = "windows"))]
+const NO_ROUTE_OS_ERRORS: [i32; 2] = [libc::ENETUNREACH, libc::EHOSTUNREACH];
+/// The OS error codes that indicate that a destination has no usable route, i.e.,
+/// `WSAENETUNREACH`, `WSAEHOSTUNREACH`, `ERROR_NETWORK_UNREACHABLE` and `ERROR_HOST_UNREACHABLE`.
+#[cfg(target_os = "windows")]
+const NO_ROUTE_OS_ERRORS: [i32; 4] = [10_051, 10_065, 1_231, 1_232];
+
+/// The OS error codes that indicate a timeout.
+#[cfg(not(target_os = "windows"))]
+const TIMED_OUT_OS_ERRORS: [i32; 1] = [libc::ETIMEDOUT];
+/// The OS error codes that indicate a timeout, i.e., `WSAETIMEDOUT` and `ERROR_TIMEOUT`.
+#[cfg(target_os = "windows")]
+const TIMED_OUT_OS_ERRORS: [i32; 2] = [10_060, 1_460];
+
+/// The OS error codes that indicate that the operating system denied an operation.
+#[cfg(not(target_os = "windows"))]
+const PERMISSION_DENIED_OS_ERRORS: [i32; 2] = [libc::EACCES, libc::EPERM];
+/// The OS error codes that indicate that the operating system denied an operation, i.e.,
+/// `WSAEACCES` and `ERROR_ACCESS_DENIED`.
+#[cfg(target_os = "windows")]
+const PERMISSION_DENIED_OS_ERRORS: [i32; 2] = [10_013, 5];
+
+/// Return the members of the outgoing network interface towards a remote destination identified by
+/// an [`IpAddr`], if it is a bond or team.
+///
+/// The MTU of a bond or team interface is authoritative, but a member with a smaller MTU indicates
+/// a misconfiguration that may limit the path. The members are ordered by interface index. If the
+/// outgoing interface is not a bond or team, or has no members, it is returned itself. Bonds and
+/// teams are only detected on Linux; on other platforms, this always returns the outgoing
+/// interface.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU cannot be determined.
+pub fn bond_member_mtus(remote: IpAddr) -> Result<Vec<Interface>> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let members = linux::bond_member_mtus_impl(RouteQuery::from(remote).canonical());
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let members = interface_and_mtu(remote).map(|interface| vec![interface]);
+    members
+}
+
+/// Return a stable numeric code for the category of an error returned by this crate, for
+/// structured logging and foreign function interfaces.
+///
+/// | Code | Category |
+/// |-----:|----------|
+/// | 1 | No route towards the destination |
+/// | 2 | Interface or MTU not found |
+/// | 3 | Timeout |
+/// | 4 | Unsupported platform or operation |
+/// | 5 | Permission denied |
+/// | 0 | Any other error |
+///
+/// The categories are independent of OS error values, so that the codes are the same on all
+/// platforms. OS errors map to the category they indicate, such as `ETIMEDOUT` and `WSAETIMEDOUT`
+/// to 3, or `EACCES`, `EPERM` and `WSAEACCES` to 5, and to 0 otherwise. Use
+/// [`Error::raw_os_error`] for the platform-specific code of those.
+#[must_use]
+pub fn error_code(err: &Error) -> i32 {
+    if err
+        .get_ref()
+        .is_some_and(|inner| inner.downcast_ref::<NoRoute>().is_some())
+    {
+        return 1;
+    }
+    match err.raw_os_error() {
+        Some(code) if NO_ROUTE_OS_ERRORS.contains(&code) => 1,
+        Some(code) if TIMED_OUT_OS_ERRORS.contains(&code) => 3,
+        Some(code) if PERMISSION_DENIED_OS_ERRORS.contains(&code) => 5,
+        _ => match err.kind() {
+            ErrorKind::NotFound => 2,
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => 3,
+            ErrorKind::Unsupported => 4,
+            ErrorKind::PermissionDenied => 5,
+            _ => 0,
+        },
+    }
+}
+
+/// The error returned by [`interface_and_mtu2`], which tells apart the reasons why a lookup fails.
+///
+/// This lets callers decide whether to fall back to a default MTU. It converts from and into an
+/// [`Error`], so that it can be used alongside the other functions of this crate.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MtuError {
+    /// There is no usable route towards the destination.
+    NoRoute,
+    /// The platform or operation is not supported.
+    Unsupported,
+    /// A call into the operating system failed. The [`Error`] holds the OS error code, if any.
+    Io(Error),
+    /// A reply from the operating system could not be parsed.
+    Parse(String),
+    /// The outgoing interface or its MTU was not found.
+    NotFound,
+    /// A host name could not be resolved, see [`interface_and_mtu_for_host`]. The [`Error`] holds
+    /// the error of the resolver.
+    Resolve(Error),
+    /// The operating system denied the lookup, for example with `EPERM` or `EACCES`. The [`Error`]
+    /// holds the OS error code.
+    ///
+    /// No platform needs elevated privileges for lookups, but sandboxes may deny opening the
+    /// routing socket on Linux, Android, macOS and the BSDs, or the IP Helper calls on Windows.
+    PermissionDenied(Error),
+}
+
+impl std::fmt::Display for MtuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoRoute => f.write_str("No route to destination"),
+            Self::Unsupported => f.write_str("MTU lookup not supported"),
+            // The error is the source, so that it is not repeated when printing the chain.
+            Self::Io(_) => f.write_str("MTU lookup failed"),
+            Self::Parse(msg) => write!(f, "Cannot parse reply: {msg}"),
+            Self::NotFound => f.write_str("Local interface MTU not found"),
+            Self::Resolve(_) => f.write_str("Host name resolution failed"),
+            Self::PermissionDenied(_) => f.write_str("MTU lookup denied"),
+        }
+    }
+}
+
+impl std::error::Error for MtuError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) | Self::Resolve(err) | Self::PermissionDenied(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for MtuError {
+    /// Classify `err`, using the same categories as [`error_code`]. Errors reported by the
+    /// operating system, such as Netlink `NLMSG_ERROR` codes or Windows error codes, are kept as
+    /// [`MtuError::Io`], unless they indicate a missing route, an unsupported operation or a
+    /// denied one. Errors resolving a host name become [`MtuError::Resolve`].
+    fn from(err: Error) -> Self {
+        if err
+            .get_ref()
+            .is_some_and(|inner| inner.downcast_ref::<ResolveFailed>().is_some())
+        {
+            return Self::Resolve(err);
+        }
+        match error_code(&err) {
+            1 => return Self::NoRoute,
+            5 => return Self::PermissionDenied(err),
+            _ => (),
+        }
+        match err.kind() {
+            ErrorKind::Unsupported => Self::Unsupported,
+            _ if err.raw_os_error().is_some() => Self::Io(err),
+            ErrorKind::NotFound => Self::NotFound,
+            ErrorKind::InvalidData => Self::Parse(err.to_string()),
+            _ => Self::Io(err),
+        }
+    }
+}
+
+impl From<MtuError> for Error {
+    fn from(err: MtuError) -> Self {
+        match err {
+            MtuError::NoRoute => no_route_err(err.to_string()),
+            MtuError::Unsupported => Self::new(ErrorKind::Unsupported, err.to_string()),
+            MtuError::Io(err) | MtuError::Resolve(err) | MtuError::PermissionDenied(err) => err,
+            MtuError::Parse(msg) => Self::new(ErrorKind::InvalidData, msg),
+            MtuError::NotFound => default_err(),
+        }
+    }
+}
+
+/// The details of a routing decision that [`explain`] describes.
+struct RouteExplanation {
+    remote: IpAddr,
+    interface: Interface,
+    /// The prefix length of the route, if known.
+    prefix_len: Option<u8>,
+    on_link: bool,
+    gateway: Option<IpAddr>,
+    source: Option<IpAddr>,
+    route_mtu: Option<usize>,
+}
+
+impl std::fmt::Display for RouteExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Interface {
+            name,
+            mtu,
+            index,
+            flags,
+            kind,
+            ..
+        } = &self.interface;
+        write!(
+            f,
+            "Destination {} routes via interface {name} (index {index}",
+            self.remote
+        )?;
+        match kind {
+            Some(InterfaceKind::Loopback) => write!(f, ", loopback")?,
+            Some(InterfaceKind::Ethernet) => write!(f, ", ethernet")?,
+            Some(InterfaceKind::WiFi) => write!(f, ", wifi")?,
+            Some(InterfaceKind::Cellular) => write!(f, ", cellular")?,
+            Some(InterfaceKind::Tunnel) => write!(f, ", tunnel")?,
+            Some(InterfaceKind::Other(raw)) => write!(f, ", type {raw}")?,
+            None => (),
+        }
+        if flags.contains(InterfaceFlags::UP) {
+            write!(f, ", up)")?;
+        } else {
+            write!(f, ", down)")?;
+        }
+        match self.prefix_len {
+            Some(0) => writeln!(f, " on a /0 default route.")?,
+            Some(prefix_len) => writeln!(f, " on a /{prefix_len} route.")?,
+            None => writeln!(f, ".")?,
+        }
+        match (self.on_link, self.gateway) {
+            (true, _) => write!(f, "Destination is on-link")?,
+            (false, Some(gateway)) => write!(f, "Next hop is gateway {gateway}")?,
+            (false, None) => write!(f, "Next hop is a gateway")?,
+        }
+        match self.source {
+            Some(source) => writeln!(f, ", source address {source}.")?,
+            None => writeln!(f, ", source address not known.")?,
+        }
+        write!(f, "Link MTU {mtu}")?;
+        if let Some(route_mtu) = self.route_mtu {
+            write!(f, ", route MTU {route_mtu}")?;
+        }
+        write!(
+            f,
+            ", usable UDP payload {}.",
+            max_udp_payload(
+                MtuPreference::Smaller.select(*mtu, self.route_mtu),
+                self.remote
+            )
+        )
+    }
+}
+
+/// Return a human-readable, multi-line explanation of the routing decision towards a remote
+/// destination identified by an [`IpAddr`], for diagnostic purposes.
+///
+/// The explanation names the outgoing interface with its index, kind and whether it is up, the
+/// prefix length of the route, whether the destination is on-link or the gateway it is reached
+/// through, the source address, and the interface and route MTUs together with the resulting usable
+/// UDP payload. The exact format is not stable and may change
+/// as more details become available.
+///
+/// # Errors
+///
+/// This function returns an error if the local interface MTU, the prefix length of the route, the
+/// next hop or the source address cannot be determined.
+pub fn explain(remote: IpAddr) -> Result<String> {
+    let query = RouteQuery::from(remote);
+    let RouteDetails {
+        interface,
+        route_mtu,
+        on_link,
+        gateway,
+        source,
+        ..
+    } = resolve_route(query, route_details_impl, RouteDetails::from_interface)?;
+    Ok(RouteExplanation {
+        remote,
+        interface,
+        prefix_len: route_prefix_len_impl(query)?,
+        on_link,
+        gateway,
+        source,
+        route_mtu,
+    }
+    .to_string())
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(target_os = "windows"))]
+    use std::os::fd::AsFd as _;
+    #[cfg(target_os = "windows")]
+    use std::os::windows::io::AsSocket as _;
+    use std::{
+        env,
+        net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket},
+    };
+
+    use crate::{
+        bond_member_mtus, change_generation, changed, explain, gso_segment_count, gso_segments,
+        interface_and_mtu, interface_and_mtu_for_prefix, interface_and_mtu_normalized,
+        interface_and_mtu_with_flowinfo, interface_and_mtu_with_preference, interface_index,
+        is_on_link, is_route_ambiguous, max_datagram_size, max_frame_payload, mtu_snapshot,
+        quic_initial_mtu, quic_initial_size, routes, Interface, InterfaceFlags, InterfaceHandle,
+        InterfaceIndex, InterfaceKind, MtuPreference, RouteQuery,
+    };
+    #[cfg(not(target_os = "windows"))]
+    use crate::{interface_and_mtu_via_index, interface_and_mtu_via_name_auto_src};
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    use crate::{interface_and_mtu_with_source_preference, SourcePreference};
+
+    #[derive(Debug)]
+    struct NameMtu<'a>(Option<&'a str>, usize);
+
+    impl PartialEq<NameMtu<'_>> for (String, usize) {
+        fn eq(&self, other: &NameMtu<'_>) -> bool {
+            other.0.map_or(true, |name| name == self.0) && other.1 == self.1
+        }
+    }
+
+    impl PartialEq<NameMtu<'_>> for Interface {
+        fn eq(&self, other: &NameMtu<'_>) -> bool {
+            other.0.map_or(true, |name| name == self.name) && other.1 == self.mtu
+        }
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "freebsd", target_os = "dragonfly"))]
+    const LOOPBACK: &[NameMtu] = &[NameMtu(Some("lo0"), 16_384), NameMtu(Some("lo0"), 16_384)];
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const LOOPBACK: &[NameMtu] = &[NameMtu(Some("lo"), 65_536), NameMtu(Some("lo"), 65_536)];
+    #[cfg(target_os = "windows")]
+    const LOOPBACK: &[NameMtu] = &[
+        NameMtu(Some("loopback_0"), 4_294_967_295),
+        NameMtu(Some("loopback_0"), 4_294_967_295),
+    ];
+    #[cfg(target_os = "openbsd")]
+    const LOOPBACK: &[NameMtu] = &[NameMtu(Some("lo0"), 32_768), NameMtu(Some("lo0"), 32_768)];
+    #[cfg(target_os = "netbsd")]
+    const LOOPBACK: &[NameMtu] = &[NameMtu(Some("lo0"), 33_624), NameMtu(Some("lo0"), 33_624)];
+    #[cfg(solarish)]
+    // Note: Different loopback MTUs for IPv4 and IPv6?!
+    const LOOPBACK: &[NameMtu] = &[NameMtu(Some("lo0"), 8_232), NameMtu(Some("lo0"), 8_252)];
+
+    // Non-loopback interface names are unpredictable, so we only check the MTU.
+    const INET: NameMtu = NameMtu(None, 1_500);
+
+    #[test]
+    fn loopback_v4() {
+        assert_eq!(
+            interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap(),
+            LOOPBACK[0]
+        );
+    }
+
+    #[test]
+    fn concurrent_lookups() {
+        let remotes = [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ];
+        let expected = remotes.map(|remote| interface_and_mtu(remote).unwrap());
+        std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..32)
+                .map(|_| {
+                    scope.spawn(|| {
+                        for _ in 0..16 {
+                            for (remote, expected) in remotes.iter().zip(&expected) {
+                                assert_eq!(&interface_and_mtu(*remote).unwrap(), expected);
+                            }
+                        }
+                    })
+                })
+                .collect();
+            for thread in threads {
+                thread.join().unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn loopback_has_no_mac() {
+        for remote in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ] {
+            let interface = interface_and_mtu(remote).unwrap();
+            assert_eq!(interface.mac, None);
+            assert_eq!(Interface::from_index(interface.index).unwrap().mac, None);
+        }
+    }
+
+    #[test]
+    fn loopback_flags() {
+        let loopback = InterfaceFlags::UP | InterfaceFlags::LOOPBACK;
+        for remote in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ] {
+            let interface = interface_and_mtu(remote).unwrap();
+            assert!(interface.flags.contains(loopback));
+            assert!(!interface.flags.contains(InterfaceFlags::POINTOPOINT));
+            let by_index = Interface::from_index(interface.index).unwrap();
+            assert_eq!(by_index.flags, interface.flags);
+            let listed = crate::list_interfaces()
+                .unwrap()
+                .into_iter()
+                .find(|listed| listed.index == interface.index)
+                .unwrap();
+            assert_eq!(listed.flags, interface.flags);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip() {
+        let interface = Interface {
+            mac: Some([0x02, 0x00, 0x5e, 0x00, 0x53, 0x01]),
+            flags: InterfaceFlags::UP | InterfaceFlags::RUNNING,
+            kind: Some(InterfaceKind::WiFi),
+            speed_bps: Some(866_700_000),
+            ..Interface::new("wlan0".to_string(), 1500, 3)
+        };
+        let mut json = serde_json::to_value(&interface).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "name": "wlan0",
+                "mtu": 1500,
+                "index": 3,
+                "mac": [2, 0, 0x5e, 0, 0x53, 1],
+                "flags": 3,
+                "kind": "wifi",
+                "speed_bps": 866_700_000,
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<Interface>(json.clone()).unwrap(),
+            interface
+        );
+        // Values serialized before there was a speed still deserialize.
+        json.as_object_mut().unwrap().remove("speed_bps");
+        assert_eq!(
+            serde_json::from_value::<Interface>(json).unwrap().speed_bps,
+            None
+        );
+
+        let other = Interface {
+            kind: Some(InterfaceKind::Other(512)),
+            ..interface
+        };
+        let json = serde_json::to_string(&other).unwrap();
+        assert!(json.contains(r#""kind":{"other":512}"#), "{json}");
+        assert_eq!(serde_json::from_str::<Interface>(&json).unwrap(), other);
+    }
+
+    #[test]
+    fn loopback_kind() {
+        let interface = interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        assert_eq!(interface.kind, Some(InterfaceKind::Loopback));
+        let by_index = Interface::from_index(interface.index).unwrap();
+        assert_eq!(by_index.kind, interface.kind);
+        let listed = crate::list_interfaces()
+            .unwrap()
+            .into_iter()
+            .find(|listed| listed.index == interface.index)
+            .unwrap();
+        assert_eq!(listed.kind, interface.kind);
+        assert_eq!(Interface::from(("lo".to_string(), 65536)).kind, None);
+    }
+
+    #[test]
+    fn interface_flags() {
+        let flags = InterfaceFlags::UP | InterfaceFlags::MULTICAST;
+        assert!(flags.contains(InterfaceFlags::UP));
+        assert!(flags.contains(InterfaceFlags::UP | InterfaceFlags::MULTICAST));
+        assert!(!flags.contains(InterfaceFlags::UP | InterfaceFlags::RUNNING));
+        assert_eq!(flags & InterfaceFlags::RUNNING, InterfaceFlags::empty());
+        assert!(InterfaceFlags::default().is_empty());
+        assert!(Interface::from(("lo".to_string(), 65536)).flags.is_empty());
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            #[allow(clippy::cast_sign_loss)] // The interface flag bits are positive.
+            let iff = (libc::IFF_UP | libc::IFF_POINTOPOINT | libc::IFF_NOARP) as u64;
+            assert_eq!(
+                InterfaceFlags::from_iff(iff),
+                InterfaceFlags::UP | InterfaceFlags::POINTOPOINT
+            );
+        }
+    }
+
+    #[test]
+    fn interface_from_index() {
+        for remote in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ] {
+            let index = interface_index(remote).unwrap();
+            assert_eq!(interface_and_mtu(remote).unwrap().index, index);
+            assert_eq!(
+                Interface::from_index(index).unwrap(),
+                interface_and_mtu(remote).unwrap()
+            );
+        }
+        assert!(Interface::from_index(InterfaceIndex::new(0)).is_err());
+    }
+
+    #[test]
+    fn typed_interface_index() {
+        let interface = interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        let index = InterfaceIndex::from(interface.index.get());
+        assert_eq!(index, interface.index);
+        assert_eq!(u32::from(index), index.get());
+        assert_eq!(index.to_string(), index.get().to_string());
+        assert_eq!(index.interface().unwrap(), interface);
+        assert_eq!(InterfaceHandle::from(index).index(), index);
+        assert!(InterfaceIndex::new(0).interface().is_err());
+    }
+
+    #[test]
+    fn for_socket_addr() {
+        use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6};
+
+        use crate::{interface_and_mtu_for, interface_and_mtu_for_socket_addr};
+
+        let v4 = interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        let v6 = interface_and_mtu(IpAddr::V6(Ipv6Addr::LOCALHOST)).unwrap();
+        assert_eq!(interface_and_mtu_for(Ipv4Addr::LOCALHOST).unwrap(), v4);
+        assert_eq!(interface_and_mtu_for(Ipv6Addr::LOCALHOST).unwrap(), v6);
+        assert_eq!(
+            interface_and_mtu_for_socket_addr(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 443)).unwrap(),
+            v4
+        );
+        assert_eq!(
+            interface_and_mtu_for_socket_addr(SocketAddr::from((Ipv6Addr::LOCALHOST, 443)))
+                .unwrap(),
+            v6
+        );
+        // A scope ID constrains the route to that interface.
+        let scoped = SocketAddrV6::new(Ipv6Addr::LOCALHOST, 443, 0, v6.index.get());
+        assert_eq!(interface_and_mtu_for_socket_addr(scoped).unwrap(), v6);
+    }
+
+    #[test]
+    fn ipv4_mapped() {
+        for ip in [Ipv4Addr::LOCALHOST, Ipv4Addr::new(192, 0, 2, 1)] {
+            let mapped = IpAddr::V6(ip.to_ipv6_mapped());
+            match (interface_and_mtu(IpAddr::V4(ip)), interface_and_mtu(mapped)) {
+                (Ok(native), Ok(mapped)) => assert_eq!(native, mapped),
+                (Err(native), Err(mapped)) => assert_eq!(native.kind(), mapped.kind()),
+                (native, mapped) => panic!("{native:?} != {mapped:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_query() {
+        let query = RouteQuery {
+            prefix_len: 104,
+            ..IpAddr::V6(Ipv4Addr::new(10, 0, 0, 0).to_ipv6_mapped()).into()
+        }
+        .canonical();
+        assert_eq!(query.remote, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)));
+        assert_eq!(query.prefix_len, 8);
+
+        let query = RouteQuery::from(IpAddr::V6(Ipv6Addr::LOCALHOST)).canonical();
+        assert_eq!(query.remote, IpAddr::V6(Ipv6Addr::LOCALHOST));
+        assert_eq!(query.prefix_len, 128);
+    }
+
+    #[test]
+    fn link_local_with_scope_id() {
+        use std::net::SocketAddrV6;
+
+        use crate::{interface_and_mtu_for_socket_addr, routes};
+
+        // Link-local addresses only route with a scope ID, so pick an interface with a link-local
+        // route. Some platforms embed the scope ID into the second segment of the destination.
+        let Some(oif) = routes().ok().and_then(|routes| {
+            routes
+                .into_iter()
+                .find(|route| {
+                    matches!(route.destination, IpAddr::V6(ip) if ip.segments()[0] == 0xfe80)
+                        && route.prefix_len == 64
+                        && route.oif_index.get() != 0
+                })
+                .map(|route| route.oif_index)
+        }) else {
+            return;
+        };
+        let dest = SocketAddrV6::new(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1), 0, 0, oif.get());
+        assert_eq!(interface_and_mtu_for_socket_addr(dest).unwrap().index, oif);
+    }
+
+    #[test]
+    fn resolver() {
+        use crate::MtuResolver;
+
+        let mut resolver = MtuResolver::new().unwrap();
+        for _ in 0..4 {
+            for remote in [
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ] {
+                assert_eq!(
+                    resolver.resolve(remote).unwrap(),
+                    interface_and_mtu(remote).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn batch() {
+        use crate::interface_and_mtu_batch;
+
+        let dests = [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+        ];
+        let results = interface_and_mtu_batch(&dests);
+        assert_eq!(results.len(), dests.len());
+        for (dest, res) in dests.iter().zip(results) {
+            // A failed lookup does not affect the other destinations.
+            match interface_and_mtu(*dest) {
+                Ok(interface) => assert_eq!(res.unwrap(), interface),
+                Err(e) => assert_eq!(res.unwrap_err().kind(), e.kind()),
+            }
+        }
+        assert!(interface_and_mtu_batch(&[]).is_empty());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn interface_and_mtu_async() {
+        use crate::interface_and_mtu_async;
+
+        let v4 = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        // Concurrent lookups each get the replies to their own queries.
+        let (res_v4, res_v6, res_unspecified) = tokio::join!(
+            interface_and_mtu_async(v4),
+            interface_and_mtu_async(v6),
+            interface_and_mtu_async(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+        );
+        assert_eq!(res_v4.unwrap(), interface_and_mtu(v4).unwrap());
+        assert_eq!(res_v6.unwrap(), interface_and_mtu(v6).unwrap());
+        match interface_and_mtu(IpAddr::V4(Ipv4Addr::UNSPECIFIED)) {
+            Ok(interface) => assert_eq!(res_unspecified.unwrap(), interface),
+            Err(e) => assert_eq!(res_unspecified.unwrap_err().kind(), e.kind()),
+        }
+    }
+
+    #[test]
+    fn path_mtu() {
+        use std::net::{TcpListener, TcpStream};
+
+        use crate::PathMtuExt as _;
+
+        for local in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ] {
+            let mtu = interface_and_mtu(local).unwrap().mtu;
+            let socket = UdpSocket::bind((local, 0)).unwrap();
+            // An unconnected socket has no path MTU.
+            assert!(socket.path_mtu().is_err());
+            socket.connect(socket.local_addr().unwrap()).unwrap();
+            // On Linux, the IPv4 path MTU is capped at the maximum IP packet size.
+            assert!((1280..=mtu).contains(&socket.path_mtu().unwrap()));
+
+            let listener = TcpListener::bind((local, 0)).unwrap();
+            let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+            assert_eq!(stream.path_mtu().unwrap(), socket.path_mtu().unwrap());
+        }
+    }
+
+    #[test]
+    fn loopback_path_mtu() {
+        use crate::path_mtu;
+
+        for remote in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ] {
+            let mtu = path_mtu(remote).unwrap();
+            assert!(mtu <= interface_and_mtu(remote).unwrap().mtu);
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            assert!(mtu >= 1280);
+        }
+        // IPv4-mapped destinations have the path MTU of the IPv4 address.
+        assert_eq!(
+            path_mtu(IpAddr::V6(Ipv4Addr::LOCALHOST.to_ipv6_mapped())).unwrap(),
+            path_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap()
+        );
+    }
+
+    #[test]
+    fn listed_interfaces() {
+        use crate::list_interfaces;
+
+        let interfaces = list_interfaces().unwrap();
+        assert!(interfaces.contains(&interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap()));
+        assert!(interfaces
+            .windows(2)
+            .all(|pair| pair[0].index < pair[1].index));
+    }
+
+    #[test]
+    fn interface_index_mtu() {
+        use crate::mtu_for_interface_index;
+
+        for remote in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ] {
+            let interface = interface_and_mtu(remote).unwrap();
+            assert_eq!(
+                mtu_for_interface_index(interface.index).unwrap(),
+                <(String, usize)>::from(interface)
+            );
+        }
+        assert!(mtu_for_interface_index(InterfaceIndex::new(0)).is_err());
+    }
+
+    #[test]
+    fn interface_name_mtu() {
+        use std::io::ErrorKind;
+
+        use crate::mtu_for_interface_name;
+
+        let Interface { name, mtu, .. } =
+            interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        assert_eq!(mtu_for_interface_name(&name).unwrap(), mtu);
+        for name in ["", "mtu-no-such-if", "mtu-name-that-is-too-long"] {
+            assert_eq!(
+                mtu_for_interface_name(name).unwrap_err().kind(),
+                ErrorKind::NotFound
+            );
+        }
+        assert_eq!(
+            mtu_for_interface_name("lo\0").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn from_loopback() {
+        use std::io::ErrorKind;
+
+        use crate::{default_err, interface_and_mtu_from};
+
+        for remote in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ] {
+            assert_eq!(
+                interface_and_mtu_from(remote, remote).unwrap(),
+                interface_and_mtu(remote).unwrap()
+            );
+        }
+        // TEST-NET-1 is not assigned to any interface.
+        let err = interface_and_mtu_from(
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 123)),
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), default_err().to_string());
+        assert_eq!(
+            interface_and_mtu_from(
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(Ipv6Addr::LOCALHOST)
+            )
+            .unwrap_err()
+            .kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn loopback_v6() {
+        assert_eq!(
+            interface_and_mtu(IpAddr::V6(Ipv6Addr::LOCALHOST)).unwrap(),
+            LOOPBACK[1]
+        );
+    }
+
+    #[test]
+    fn loopback_normalized() {
+        for (remote, expected) in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ]
+        .into_iter()
+        .zip(LOOPBACK)
+        {
+            let (name, mtu) = interface_and_mtu_normalized(remote).unwrap().into();
+            assert_eq!((name, mtu), NameMtu(expected.0, expected.1.min(65_535)));
+            // Linux and Windows report loopback MTUs above the maximum IP packet size.
+            #[cfg(any(target_os = "linux", target_os = "android", target_os = "windows"))]
+            assert_eq!(mtu, 65_535);
+        }
+    }
+
+    #[test]
+    fn loopback_v6_flowinfo() {
+        assert_eq!(
+            interface_and_mtu_with_flowinfo(Ipv6Addr::LOCALHOST, 0x0b80_1234).unwrap(),
+            LOOPBACK[1]
+        );
+    }
+
+    #[test]
+    fn inet_v4() {
+        assert_eq!(
+            interface_and_mtu(IpAddr::V4(Ipv4Addr::new(
+                104, 16, 132, 229 // cloudflare.com
+            )))
+            .unwrap(),
+            INET
+        );
+    }
+
+    #[test]
+    fn inet_v6() {
+        match interface_and_mtu(IpAddr::V6(Ipv6Addr::new(
+            0x2606, 0x4700, 0, 0, 0, 0, 0x6810, 0x84e5, // cloudflare.com
+        ))) {
             Ok(res) => assert_eq!(res, INET),
             // The GitHub CI environment does not have IPv6 connectivity.
             Err(_) => assert!(env::var("GITHUB_ACTIONS").is_ok()),
         }
     }
+
+    #[test]
+    fn max_datagram_size_df() {
+        assert_eq!(
+            max_datagram_size(IpAddr::V4(Ipv4Addr::new(104, 16, 132, 229)), false).unwrap(),
+            INET.1 - 28
+        );
+    }
+
+    #[test]
+    fn max_datagram_size_fragmentation() {
+        assert_eq!(
+            max_datagram_size(IpAddr::V4(Ipv4Addr::new(104, 16, 132, 229)), true).unwrap(),
+            65_507
+        );
+        assert_eq!(
+            max_datagram_size(IpAddr::V6(Ipv6Addr::LOCALHOST), true).unwrap(),
+            65_527
+        );
+    }
+
+    #[test]
+    fn max_datagram_size_loopback() {
+        let mtu = interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST))
+            .unwrap()
+            .mtu;
+        assert_eq!(
+            max_datagram_size(IpAddr::V4(Ipv4Addr::LOCALHOST), false).unwrap(),
+            (mtu - 28).min(65_507)
+        );
+    }
+
+    #[test]
+    fn quic_initial_loopback() {
+        // Loopback MTUs are large, so the result is capped.
+        assert_eq!(quic_initial_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)), 1_472);
+        assert_eq!(quic_initial_mtu(IpAddr::V6(Ipv6Addr::LOCALHOST)), 1_452);
+    }
+
+    #[test]
+    fn quic_initial_clamped() {
+        let v4 = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        // The usable payload of a 1,500-byte interface.
+        assert_eq!(quic_initial_size(v4, Ok(1_472)), 1_472);
+        assert_eq!(quic_initial_size(v4, Ok(1_400)), 1_400);
+        assert_eq!(quic_initial_size(v4, Ok(1_000)), 1_200);
+        assert_eq!(
+            quic_initial_size(v4, Err(std::io::Error::other("no route"))),
+            1_200
+        );
+    }
+
+    #[test]
+    fn gso_segment_counts() {
+        let v4 = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        // Exact multiples of the usable payload of a 1,500-byte interface.
+        assert_eq!(gso_segment_count(0, v4, 1_500).unwrap(), 0);
+        assert_eq!(gso_segment_count(1_472, v4, 1_500).unwrap(), 1);
+        assert_eq!(gso_segment_count(10 * 1_472, v4, 1_500).unwrap(), 10);
+        assert_eq!(gso_segment_count(10 * 1_452, v6, 1_500).unwrap(), 10);
+        // Remainders need another segment.
+        assert_eq!(gso_segment_count(1, v4, 1_500).unwrap(), 1);
+        assert_eq!(gso_segment_count(1_473, v4, 1_500).unwrap(), 2);
+        assert_eq!(gso_segment_count(10 * 1_472, v6, 1_500).unwrap(), 11);
+        // Unbounded MTUs are limited by the maximum UDP payload.
+        assert_eq!(gso_segment_count(65_507, v4, usize::MAX).unwrap(), 1);
+        assert_eq!(gso_segment_count(65_508, v4, usize::MAX).unwrap(), 2);
+        assert_eq!(gso_segment_count(65_527, v6, usize::MAX).unwrap(), 1);
+        assert_eq!(
+            gso_segment_count(1, v4, 28).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn gso_segments_loopback() {
+        let remote = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let size = max_datagram_size(remote, false).unwrap();
+        assert_eq!(gso_segments(size, remote).unwrap(), 1);
+        assert_eq!(gso_segments(size + 1, remote).unwrap(), 2);
+    }
+
+    #[test]
+    fn mtu_or_fallback() {
+        use std::io::{Error, ErrorKind};
+
+        use crate::{default_err, interface_and_mtu_or, not_reachable_via, or_fallback};
+
+        for remote in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ] {
+            assert_eq!(
+                interface_and_mtu_or(remote, 1280).unwrap(),
+                interface_and_mtu(remote).unwrap()
+            );
+        }
+        // As when a sandbox denies opening the routing socket.
+        let denied = [
+            #[cfg(unix)]
+            Error::from_raw_os_error(libc::EPERM),
+            #[cfg(unix)]
+            Error::from_raw_os_error(libc::EACCES),
+            #[cfg(unix)]
+            Error::from_raw_os_error(libc::ENOSYS),
+            Error::from(ErrorKind::PermissionDenied),
+        ];
+        for err in denied {
+            assert_eq!(
+                or_fallback(Err(err), 1280).unwrap(),
+                Interface::from(("unknown".to_string(), 1280))
+            );
+        }
+        // Other errors are not from a sandbox, and are returned.
+        for err in [not_reachable_via(1), default_err()] {
+            let kind = err.kind();
+            assert_eq!(or_fallback(Err(err), 1280).unwrap_err().kind(), kind);
+        }
+    }
+
+    #[test]
+    fn mtu_errors() {
+        use std::{
+            error::Error as _,
+            io::{Error, ErrorKind},
+        };
+
+        use crate::{
+            default_err, error_code, interface_and_mtu2, not_reachable_via, MtuError,
+            NO_ROUTE_OS_ERRORS,
+        };
+
+        assert!(matches!(
+            MtuError::from(not_reachable_via(1)),
+            MtuError::NoRoute
+        ));
+        for code in NO_ROUTE_OS_ERRORS {
+            assert!(matches!(
+                MtuError::from(Error::from_raw_os_error(code)),
+                MtuError::NoRoute
+            ));
+        }
+        assert!(matches!(MtuError::from(default_err()), MtuError::NotFound));
+        assert!(matches!(
+            MtuError::from(Error::from(ErrorKind::Unsupported)),
+            MtuError::Unsupported
+        ));
+        assert!(matches!(
+            MtuError::from(Error::new(ErrorKind::InvalidData, "bad")),
+            MtuError::Parse(msg) if msg == "bad"
+        ));
+        let denied = MtuError::from(Error::from(ErrorKind::PermissionDenied));
+        assert!(denied.source().is_some());
+        assert!(matches!(
+            &denied,
+            MtuError::PermissionDenied(err) if err.kind() == ErrorKind::PermissionDenied
+        ));
+        // As when a sandbox denies opening the routing socket, keeping the OS error code.
+        #[cfg(unix)]
+        for code in [libc::EPERM, libc::EACCES] {
+            assert!(matches!(
+                MtuError::from(Error::from_raw_os_error(code)),
+                MtuError::PermissionDenied(err) if err.raw_os_error() == Some(code)
+            ));
+        }
+
+        // Converting back keeps the category.
+        for err in [
+            not_reachable_via(1),
+            default_err(),
+            Error::from(ErrorKind::Unsupported),
+            Error::from(ErrorKind::PermissionDenied),
+        ] {
+            let code = error_code(&err);
+            assert_eq!(error_code(&Error::from(MtuError::from(err))), code);
+        }
+
+        assert_eq!(
+            interface_and_mtu2(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap(),
+            interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap()
+        );
+    }
+
+    #[test]
+    fn cache_expires() {
+        use std::{
+            cell::Cell,
+            time::{Duration, Instant},
+        };
+
+        use crate::MtuCache;
+
+        thread_local! {
+            static START: Instant = Instant::now();
+            static ELAPSED: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+            static LOOKUPS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        let mut cache = MtuCache {
+            now: || START.with(|start| *start + ELAPSED.get()),
+            lookup: |remote| {
+                LOOKUPS.set(LOOKUPS.get() + 1);
+                Ok(Interface::from((remote.to_string(), 1500)))
+            },
+            ..MtuCache::new(Duration::from_secs(10))
+        };
+        let v4 = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        assert_eq!(
+            cache.get(v4).unwrap(),
+            Interface::from(("127.0.0.1".to_string(), 1500))
+        );
+        assert_eq!(LOOKUPS.get(), 1);
+        ELAPSED.set(Duration::from_secs(5));
+        cache.get(v4).unwrap();
+        cache.get(v6).unwrap();
+        assert_eq!(LOOKUPS.get(), 2);
+        // The entry for `v4` expires, the one for `v6` does not.
+        ELAPSED.set(Duration::from_secs(10));
+        cache.get(v6).unwrap();
+        assert_eq!(LOOKUPS.get(), 2);
+        cache.get(v4).unwrap();
+        assert_eq!(LOOKUPS.get(), 3);
+        // Stale entries are evicted when looking up another destination.
+        ELAPSED.set(Duration::from_secs(15));
+        cache.get(IpAddr::V4(Ipv4Addr::UNSPECIFIED)).unwrap();
+        assert_eq!(LOOKUPS.get(), 4);
+        assert!(cache.entries.contains_key(&v4));
+        assert!(!cache.entries.contains_key(&v6));
+    }
+
+    #[test]
+    fn for_host() {
+        use crate::{interface_and_mtu_for_host, MtuError};
+
+        let v4 = interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        let v6 = interface_and_mtu(IpAddr::V6(Ipv6Addr::LOCALHOST)).unwrap();
+        assert_eq!(interface_and_mtu_for_host("127.0.0.1").unwrap(), v4);
+        assert_eq!(interface_and_mtu_for_host("::1").unwrap(), v6);
+        // `localhost` resolves to a loopback address of either family.
+        let localhost = interface_and_mtu_for_host("localhost").unwrap();
+        assert!(localhost == v4 || localhost == v6);
+
+        // The `.invalid` top-level domain never resolves.
+        let err = interface_and_mtu_for_host("mtu.invalid").unwrap_err();
+        assert!(err.to_string().contains("mtu.invalid"));
+        assert!(matches!(MtuError::from(err), MtuError::Resolve(_)));
+    }
+
+    #[test]
+    fn error_code_no_route() {
+        use std::io::Error;
+
+        use crate::{error_code, not_reachable_via, NO_ROUTE_OS_ERRORS};
+
+        assert_eq!(error_code(&not_reachable_via(1)), 1);
+        for code in NO_ROUTE_OS_ERRORS {
+            assert_eq!(error_code(&Error::from_raw_os_error(code)), 1);
+        }
+    }
+
+    #[test]
+    fn error_code_not_found() {
+        use std::io::{Error, ErrorKind};
+
+        use crate::{default_err, error_code};
+
+        assert_eq!(error_code(&default_err()), 2);
+        assert_eq!(error_code(&Error::from(ErrorKind::NotFound)), 2);
+    }
+
+    #[test]
+    fn error_code_timed_out() {
+        use std::io::{Error, ErrorKind};
+
+        use crate::{error_code, TIMED_OUT_OS_ERRORS};
+
+        for code in TIMED_OUT_OS_ERRORS {
+            assert_eq!(error_code(&Error::from_raw_os_error(code)), 3);
+        }
+        assert_eq!(error_code(&Error::from(ErrorKind::TimedOut)), 3);
+        assert_eq!(error_code(&Error::from(ErrorKind::WouldBlock)), 3);
+    }
+
+    #[test]
+    fn error_code_unsupported() {
+        use std::io::{Error, ErrorKind};
+
+        use crate::error_code;
+
+        assert_eq!(error_code(&Error::from(ErrorKind::Unsupported)), 4);
+    }
+
+    #[test]
+    fn error_code_permission_denied() {
+        use std::io::{Error, ErrorKind};
+
+        use crate::{error_code, PERMISSION_DENIED_OS_ERRORS};
+
+        for code in PERMISSION_DENIED_OS_ERRORS {
+            assert_eq!(error_code(&Error::from_raw_os_error(code)), 5);
+        }
+        assert_eq!(error_code(&Error::from(ErrorKind::PermissionDenied)), 5);
+    }
+
+    #[test]
+    fn error_code_other() {
+        use std::io::Error;
+
+        use crate::error_code;
+
+        assert_eq!(error_code(&Error::other("other")), 0);
+        // An OS error without a category, such as `EIO`/`ERROR_GEN_FAILURE`.
+        #[cfg(not(target_os = "windows"))]
+        let code = libc::EIO;
+        #[cfg(target_os = "windows")]
+        let code = 31;
+        assert_eq!(error_code(&Error::from_raw_os_error(code)), 0);
+    }
+
+    #[test]
+    fn bond_members_loopback() {
+        let remote = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        assert_eq!(
+            bond_member_mtus(remote).unwrap(),
+            [interface_and_mtu(remote).unwrap()]
+        );
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN and bonding support"]
+    fn bond_members() {
+        use std::process::Command;
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        ip(&["link", "add", "mtu-bond0", "type", "bond"]);
+        for (member, peer) in [("mtu-bm0", "mtu-bp0"), ("mtu-bm1", "mtu-bp1")] {
+            ip(&["link", "add", member, "type", "veth", "peer", "name", peer]);
+            ip(&["link", "set", member, "master", "mtu-bond0"]);
+        }
+        ip(&["link", "set", "mtu-bond0", "mtu", "1400", "up"]);
+        // Enslaving sets the MTU of a member to that of the bond, so change it afterwards.
+        ip(&["link", "set", "mtu-bm1", "mtu", "1500"]);
+        ip(&["route", "add", "203.0.113.0/24", "dev", "mtu-bond0"]);
+        let members = bond_member_mtus(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)));
+        // Deleting the links also deletes the route through the bond.
+        for link in ["mtu-bm0", "mtu-bm1", "mtu-bond0"] {
+            ip(&["link", "del", link]);
+        }
+        assert_eq!(
+            members.unwrap(),
+            [
+                NameMtu(Some("mtu-bm0"), 1400),
+                NameMtu(Some("mtu-bm1"), 1500)
+            ]
+        );
+    }
+
+    #[test]
+    fn change_generation_monotonic() {
+        let first = change_generation().unwrap();
+        interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        assert!(change_generation().unwrap() >= first);
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn change_generation_bumps() {
+        use std::{process::Command, thread, time::Duration};
+
+        let set_lo_mtu = |mtu: usize| {
+            assert!(Command::new("ip")
+                .args(["link", "set", "dev", "lo", "mtu", &mtu.to_string()])
+                .status()
+                .unwrap()
+                .success());
+        };
+        let first = change_generation().unwrap();
+        set_lo_mtu(LOOPBACK[0].1 - 1);
+        set_lo_mtu(LOOPBACK[0].1);
+        thread::sleep(Duration::from_millis(100));
+        assert!(change_generation().unwrap() > first);
+    }
+
+    #[test]
+    fn for_prefix_out_of_range() {
+        assert_eq!(
+            interface_and_mtu_for_prefix(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 33)
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            interface_and_mtu_for_prefix(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 129)
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn for_prefix_covering_route() {
+        use std::process::Command;
+
+        let ip_route = |cmd: &str| {
+            assert!(Command::new("ip")
+                .args(["route", cmd, "10.0.0.0/8", "dev", "lo"])
+                .status()
+                .unwrap()
+                .success());
+        };
+        ip_route("add");
+        let res = interface_and_mtu_for_prefix(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3)), 8);
+        ip_route("del");
+        assert_eq!(res.unwrap(), LOOPBACK[0]);
+    }
+
+    #[test]
+    fn loopback_not_ambiguous() {
+        assert!(!is_route_ambiguous(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap());
+        assert!(!is_route_ambiguous(IpAddr::V6(Ipv6Addr::LOCALHOST)).unwrap());
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn multipath_is_ambiguous() {
+        use std::process::Command;
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        ip(&[
+            "link",
+            "add",
+            "mtu-ecmp0",
+            "type",
+            "veth",
+            "peer",
+            "name",
+            "mtu-ecmp1",
+        ]);
+        ip(&["link", "set", "mtu-ecmp0", "up"]);
+        ip(&["link", "set", "mtu-ecmp1", "up"]);
+        ip(&["route", "add", "203.0.113.0/24", "dev", "mtu-ecmp0"]);
+        let single = is_route_ambiguous(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)));
+        ip(&[
+            "route",
+            "replace",
+            "203.0.113.0/24",
+            "nexthop",
+            "dev",
+            "mtu-ecmp0",
+            "nexthop",
+            "dev",
+            "mtu-ecmp1",
+        ]);
+        let multi = is_route_ambiguous(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)));
+        // Deleting the link also deletes the routes through it.
+        ip(&["link", "del", "mtu-ecmp0"]);
+        assert!(!single.unwrap());
+        assert!(multi.unwrap());
+    }
+
+    #[test]
+    fn mtu_preference_select() {
+        assert_eq!(MtuPreference::default(), MtuPreference::Smaller);
+        assert_eq!(MtuPreference::Smaller.select(1500, Some(1300)), 1300);
+        assert_eq!(MtuPreference::Smaller.select(1300, Some(1500)), 1300);
+        assert_eq!(MtuPreference::RouteMetric.select(1500, Some(1300)), 1300);
+        assert_eq!(MtuPreference::RouteMetric.select(1300, Some(1500)), 1500);
+        assert_eq!(MtuPreference::Interface.select(1500, Some(1300)), 1500);
+        for preference in [
+            MtuPreference::Smaller,
+            MtuPreference::RouteMetric,
+            MtuPreference::Interface,
+        ] {
+            assert_eq!(preference.select(1500, None), 1500);
+        }
+    }
+
+    #[test]
+    fn mtu_preference_loopback() {
+        let remote = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        assert_eq!(
+            interface_and_mtu_with_preference(remote, MtuPreference::Interface).unwrap(),
+            interface_and_mtu(remote).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn mtu_preference_locked_route() {
+        use std::process::Command;
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        ip(&[
+            "link",
+            "add",
+            "mtu-pref0",
+            "mtu",
+            "1500",
+            "type",
+            "veth",
+            "peer",
+            "name",
+            "mtu-pref1",
+        ]);
+        ip(&["link", "set", "mtu-pref0", "up"]);
+        ip(&["link", "set", "mtu-pref1", "up"]);
+        ip(&[
+            "route",
+            "add",
+            "203.0.113.0/24",
+            "dev",
+            "mtu-pref0",
+            "mtu",
+            "lock",
+            "1300",
+        ]);
+        let remote = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1));
+        let results = [
+            MtuPreference::Smaller,
+            MtuPreference::RouteMetric,
+            MtuPreference::Interface,
+        ]
+        .map(|preference| interface_and_mtu_with_preference(remote, preference));
+        // Deleting the link also deletes the route through it.
+        ip(&["link", "del", "mtu-pref0"]);
+        let [smaller, route_metric, interface] = results.map(Result::unwrap);
+        assert_eq!(smaller, NameMtu(Some("mtu-pref0"), 1300));
+        assert_eq!(route_metric, NameMtu(Some("mtu-pref0"), 1300));
+        assert_eq!(interface, NameMtu(Some("mtu-pref0"), 1500));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "macos", target_os = "freebsd"))]
+    #[ignore = "requires root"]
+    fn blackhole_is_unreachable() {
+        use std::process::Command;
+
+        let route = |args: &[&str]| {
+            assert!(Command::new("route")
+                .arg("-n")
+                .args(args)
+                .status()
+                .unwrap()
+                .success());
+        };
+        route(&["add", "-host", "203.0.113.1", "127.0.0.1", "-blackhole"]);
+        let res = interface_and_mtu(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)));
+        route(&["delete", "-host", "203.0.113.1"]);
+        assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::ENETUNREACH));
+    }
+
+    #[test]
+    #[cfg(target_os = "freebsd")]
+    #[ignore = "requires root and net.fibs >= 2"]
+    fn fib_route() {
+        use std::process::Command;
+
+        use crate::interface_and_mtu_in_fib;
+
+        let route = |args: &[&str]| {
+            assert!(Command::new("route")
+                .arg("-n")
+                .args(args)
+                .status()
+                .unwrap()
+                .success());
+        };
+        let remote = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1));
+        route(&[
+            "add",
+            "-net",
+            "203.0.113.0/24",
+            "-interface",
+            "lo0",
+            "-fib",
+            "1",
+        ]);
+        let in_fib = interface_and_mtu_in_fib(remote, 1);
+        let unreachable = interface_and_mtu_in_fib(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1)), 1);
+        route(&["delete", "-net", "203.0.113.0/24", "-fib", "1"]);
+        assert_eq!(
+            in_fib.unwrap(),
+            interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap()
+        );
+        assert_eq!(
+            unreachable.unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "openbsd")]
+    fn rdomain_route() {
+        use crate::interface_and_mtu_in_rdomain;
+
+        let loopback: (String, usize) = interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST))
+            .unwrap()
+            .into();
+        assert_eq!(
+            interface_and_mtu_in_rdomain(IpAddr::V4(Ipv4Addr::LOCALHOST), 0).unwrap(),
+            loopback
+        );
+        // Only routing table 0 exists by default.
+        assert!(interface_and_mtu_in_rdomain(IpAddr::V4(Ipv4Addr::LOCALHOST), 255).is_err());
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn table_route() {
+        use std::process::Command;
+
+        use crate::interface_and_mtu_in_table;
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        let remote = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1));
+        ip(&[
+            "route",
+            "add",
+            "203.0.113.0/24",
+            "dev",
+            "lo",
+            "table",
+            "1000",
+        ]);
+        let in_table = interface_and_mtu_in_table(remote, 1000);
+        let unreachable =
+            interface_and_mtu_in_table(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1)), 1000);
+        ip(&[
+            "route",
+            "del",
+            "203.0.113.0/24",
+            "dev",
+            "lo",
+            "table",
+            "1000",
+        ]);
+        let loopback = interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        assert_eq!(in_table.unwrap(), loopback);
+        assert_eq!(
+            unreachable.unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn snapshot_has_loopback() {
+        for remote in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ] {
+            let index = interface_index(remote).unwrap();
+            let mtu = interface_and_mtu(remote).unwrap().mtu;
+            assert_eq!(mtu_snapshot().unwrap().get(&index), Some(&mtu));
+        }
+    }
+
+    #[test]
+    fn proxy_path() {
+        use std::net::SocketAddr;
+
+        use crate::{proxy_hint, route_info};
+
+        let proxy = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 1080);
+        let destination = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)), 443);
+        let info = proxy_hint(proxy, destination).unwrap();
+        assert_eq!(info.interface, interface_and_mtu(proxy.ip()).unwrap());
+        assert_eq!(info.remote, proxy.ip());
+        assert_eq!(info.destination, Some(destination));
+        assert_eq!(route_info(proxy.ip()).unwrap().destination, None);
+        // Loopback is not a point-to-point link, and it is on-link.
+        assert_eq!(info.peer, None);
+        assert_eq!(info.gateway, None);
+        assert_eq!(info.source, Some(proxy.ip()));
+        assert!(!info.mtu_is_default);
+    }
+
+    #[test]
+    fn fallback_mtu_for_zero() {
+        use crate::{fallback_mtu, InterfaceType};
+
+        let zero = Interface::from(("ppp0".to_string(), 0));
+        let mut interface = zero.clone();
+        assert!(fallback_mtu(&mut interface, Some(InterfaceType::Pppoe)));
+        assert_eq!(interface, Interface::from(("ppp0".to_string(), 1492)));
+
+        // Reported MTUs and unknown types are left alone.
+        let mut interface = zero.clone();
+        assert!(!fallback_mtu(&mut interface, None));
+        assert_eq!(interface, zero);
+        let mut interface = Interface::from(("eth0".to_string(), 9000));
+        assert!(!fallback_mtu(&mut interface, Some(InterfaceType::Ethernet)));
+        assert_eq!(interface.mtu, 9000);
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn p2p_peer() {
+        use std::process::Command;
+
+        use crate::route_info;
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        ip(&["tuntap", "add", "mode", "tun", "mtu-tun0"]);
+        ip(&[
+            "addr",
+            "add",
+            "198.51.100.1",
+            "peer",
+            "198.51.100.2",
+            "dev",
+            "mtu-tun0",
+        ]);
+        ip(&["link", "set", "mtu-tun0", "up"]);
+        let peer = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 2));
+        let info = route_info(peer);
+        ip(&["link", "del", "mtu-tun0"]);
+        let info = info.unwrap();
+        assert_eq!(info.interface.name, "mtu-tun0");
+        assert_eq!(info.peer, Some(peer));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn route_gateway() {
+        use std::process::Command;
+
+        use crate::route_info;
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        ip(&[
+            "link", "add", "mtu-gw0", "type", "veth", "peer", "name", "mtu-gw1",
+        ]);
+        ip(&["addr", "add", "192.0.2.1/24", "dev", "mtu-gw0"]);
+        ip(&["link", "set", "mtu-gw0", "up"]);
+        ip(&["link", "set", "mtu-gw1", "up"]);
+        ip(&[
+            "route",
+            "add",
+            "198.18.0.0/15",
+            "via",
+            "192.0.2.2",
+            "dev",
+            "mtu-gw0",
+        ]);
+        let via = route_info(IpAddr::V4(Ipv4Addr::new(198, 18, 0, 1)));
+        let direct = route_info(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 3)));
+        ip(&["link", "del", "mtu-gw0"]);
+        let via = via.unwrap();
+        assert_eq!(via.interface.name, "mtu-gw0");
+        assert_eq!(via.gateway, Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2))));
+        assert_eq!(via.source, Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))));
+        assert_eq!(direct.unwrap().gateway, None);
+    }
+
+    #[test]
+    fn loopback_family_mtus() {
+        use crate::interface_mtus;
+
+        let v4 = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let (name, inet, _) = interface_mtus(v4).unwrap();
+        assert_eq!(name, interface_and_mtu(v4).unwrap().name);
+        assert_eq!(inet, Some(interface_and_mtu(v4).unwrap().mtu));
+        let v6 = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let (_, _, inet6) = interface_mtus(v6).unwrap();
+        assert_eq!(inet6, Some(interface_and_mtu(v6).unwrap().mtu));
+    }
+
+    #[test]
+    #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+    fn loopback_stats() {
+        use crate::{interface_stats, InterfaceHandle};
+
+        let index = interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST))
+            .unwrap()
+            .index;
+        let before = interface_stats(InterfaceHandle::from_index(index)).unwrap();
+        let after = interface_stats(InterfaceHandle::from_index(index)).unwrap();
+        assert!(after.rx_bytes >= before.rx_bytes);
+        assert!(after.tx_packets >= before.tx_packets);
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn family_mtus() {
+        use std::process::Command;
+
+        use crate::family_mtus_impl;
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        let sysctl = |key: &str, value: &str| {
+            std::fs::write(format!("/proc/sys/net/ipv6/conf/mtu-fam0/{key}"), value).unwrap();
+        };
+        ip(&[
+            "link", "add", "mtu-fam0", "mtu", "1500", "type", "veth", "peer", "name", "mtu-fam1",
+        ]);
+        let index = InterfaceHandle::from_name("mtu-fam0")
+            .unwrap()
+            .index()
+            .get();
+        sysctl("mtu", "1280");
+        let distinct = family_mtus_impl(index);
+        sysctl("disable_ipv6", "1");
+        let disabled = family_mtus_impl(index);
+        ip(&["link", "del", "mtu-fam0"]);
+        assert_eq!(distinct.unwrap(), (Some(1500), Some(1280)));
+        assert_eq!(disabled.unwrap(), (Some(1500), None));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn watcher() {
+        use std::process::Command;
+
+        use crate::{ChangeEvent, MtuWatcher};
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        let mut watcher = MtuWatcher::new().unwrap();
+        ip(&[
+            "link",
+            "add",
+            "mtu-watch0",
+            "type",
+            "veth",
+            "peer",
+            "name",
+            "mtu-watch1",
+        ]);
+        let index = InterfaceHandle::from_name("mtu-watch0").unwrap().index();
+        ip(&["link", "set", "mtu-watch0", "mtu", "1400"]);
+        ip(&["link", "del", "mtu-watch0"]);
+        let expected = ChangeEvent {
+            index: Some(index),
+            mtu: Some(1400),
+        };
+        while watcher.recv().unwrap() != expected {}
+    }
+
+    #[test]
+    fn watcher_timeout() {
+        use std::time::Duration;
+
+        use crate::MtuWatcher;
+
+        let mut watcher = MtuWatcher::new().unwrap();
+        // Unrelated changes may arrive, but eventually the timeout expires.
+        while watcher
+            .recv_timeout(Duration::from_millis(10))
+            .unwrap()
+            .is_some()
+        {}
+    }
+
+    #[test]
+    #[cfg(feature = "tokio")]
+    fn interface_changes() {
+        use std::collections::HashMap;
+
+        use crate::{interface_changes, InterfaceChange, InterfaceIndex};
+
+        let index = InterfaceIndex::new;
+        let mtus = |mtus: [(u32, usize); 3]| -> HashMap<InterfaceIndex, usize> {
+            mtus.into_iter().map(|(i, mtu)| (index(i), mtu)).collect()
+        };
+        let mut snapshot = mtus([(1, 65_536), (2, 1500), (3, 1500)]);
+        let current = mtus([(1, 65_536), (2, 1400), (4, 1280)]);
+        // An event for an interface only compares that interface.
+        assert_eq!(
+            interface_changes(&mut snapshot, &current, Some(index(1))),
+            [InterfaceChange::StateChanged { index: index(1) }]
+        );
+        assert_eq!(
+            interface_changes(&mut snapshot, &current, Some(index(2))),
+            [InterfaceChange::MtuChanged {
+                index: index(2),
+                old: 1500,
+                new: 1400
+            }]
+        );
+        assert_eq!(snapshot, mtus([(1, 65_536), (2, 1400), (3, 1500)]));
+        // An event that is not attributed to an interface compares all of them.
+        assert_eq!(
+            interface_changes(&mut snapshot, &current, None),
+            [
+                InterfaceChange::Removed { index: index(3) },
+                InterfaceChange::Added { index: index(4) }
+            ]
+        );
+        assert_eq!(snapshot, current);
+        assert!(interface_changes(&mut snapshot, &current, Some(index(5))).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "tokio")]
+    fn event_mtus() {
+        use std::collections::HashMap;
+
+        use crate::{event_mtus, ChangeEvent, InterfaceIndex};
+
+        let missing = InterfaceIndex::new(u32::MAX);
+        // The MTU of an event is used as is.
+        let event = ChangeEvent {
+            index: Some(missing),
+            mtu: Some(1400),
+        };
+        assert_eq!(event_mtus(event).unwrap(), HashMap::from([(missing, 1400)]));
+        // Otherwise, it is looked up, and an interface that does not exist is gone.
+        let event = ChangeEvent {
+            index: Some(missing),
+            mtu: None,
+        };
+        assert!(event_mtus(event).unwrap().is_empty());
+        let loopback = interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        let event = ChangeEvent {
+            index: Some(loopback.index),
+            mtu: None,
+        };
+        assert_eq!(
+            event_mtus(event).unwrap(),
+            HashMap::from([(loopback.index, loopback.mtu)])
+        );
+    }
+
+    #[tokio::test]
+    #[cfg(all(feature = "tokio", any(target_os = "linux", target_os = "android")))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    async fn watch_stream_loopback_mtu() {
+        use std::{future::poll_fn, pin::pin, process::Command};
+
+        use futures_core::Stream as _;
+
+        use crate::{watch_stream, InterfaceChange};
+
+        let set_lo_mtu = |mtu: usize| {
+            assert!(Command::new("ip")
+                .args(["link", "set", "dev", "lo", "mtu", &mtu.to_string()])
+                .status()
+                .unwrap()
+                .success());
+        };
+        let Interface { index, mtu, .. } =
+            interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        let mut stream = pin!(watch_stream());
+        set_lo_mtu(mtu - 1);
+        let expected = InterfaceChange::MtuChanged {
+            index,
+            old: mtu,
+            new: mtu - 1,
+        };
+        let mut found = false;
+        while let Some(change) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+            if change.unwrap() == expected {
+                found = true;
+                break;
+            }
+        }
+        set_lo_mtu(mtu);
+        assert!(found);
+    }
+
+    #[test]
+    fn clamped_loopback() {
+        use crate::interface_and_mtu_clamped;
+
+        let remote = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let raw = interface_and_mtu(remote).unwrap();
+        assert_eq!(interface_and_mtu_clamped(remote, usize::MAX).unwrap(), raw);
+        let clamped = interface_and_mtu_clamped(remote, 1280).unwrap();
+        assert_eq!(clamped.mtu, 1280);
+        assert_eq!(clamped.name, raw.name);
+    }
+
+    #[test]
+    fn ipv6_min_mtu() {
+        use crate::{interface_and_mtu_ipv6_min, with_ipv6_min_mtu};
+
+        let tunnel = || Interface::new("tun0".to_string(), 1000, 7);
+        let v6 = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+        let raised = with_ipv6_min_mtu(v6, tunnel(), true).unwrap();
+        assert_eq!((raised.name.as_str(), raised.mtu), ("tun0", 1280));
+        let err = with_ipv6_min_mtu(v6, tunnel(), false).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("tun0"), "{err}");
+
+        // IPv4 destinations, also when mapped into IPv6, keep their MTU.
+        for v4 in [
+            IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+            IpAddr::V6(Ipv4Addr::new(192, 0, 2, 1).to_ipv6_mapped()),
+        ] {
+            assert_eq!(with_ipv6_min_mtu(v4, tunnel(), false).unwrap().mtu, 1000);
+        }
+
+        let remote = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        assert_eq!(
+            interface_and_mtu_ipv6_min(remote, false).unwrap(),
+            interface_and_mtu(remote).unwrap()
+        );
+    }
+
+    #[test]
+    fn loopback_source() {
+        use crate::{interface_and_mtu_from, route_info};
+
+        for remote in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ] {
+            let info = route_info(remote).unwrap();
+            assert_eq!(info.source, Some(remote));
+            assert_eq!(
+                interface_and_mtu_from(info.source.unwrap(), remote).unwrap(),
+                info.interface
+            );
+        }
+    }
+
+    #[test]
+    fn loopback_on_link() {
+        assert!(is_on_link(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap());
+        assert!(is_on_link(IpAddr::V6(Ipv6Addr::LOCALHOST)).unwrap());
+    }
+
+    #[test]
+    fn inet_not_on_link() {
+        assert!(!is_on_link(IpAddr::V4(Ipv4Addr::new(
+            104, 16, 132, 229 // cloudflare.com
+        )))
+        .unwrap());
+    }
+
+    #[test]
+    fn handle_loopback() {
+        let Interface { name, mtu, .. } =
+            interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        #[cfg(not(target_os = "windows"))]
+        let handle = InterfaceHandle::from_name(&name).unwrap();
+        #[cfg(target_os = "windows")]
+        let handle = InterfaceHandle::from_index(InterfaceIndex::new(1));
+        assert_eq!(handle.name().unwrap(), name);
+        assert_eq!(handle.mtu().unwrap(), mtu);
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn mac_address() {
+        use std::process::Command;
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        ip(&[
+            "link",
+            "add",
+            "mtu-mac0",
+            "address",
+            "02:00:5e:00:53:01",
+            "type",
+            "veth",
+            "peer",
+            "name",
+            "mtu-mac1",
+        ]);
+        let interface = InterfaceHandle::from_name("mtu-mac0")
+            .and_then(|handle| Interface::from_index(handle.index()));
+        let listed = crate::list_interfaces().map(|interfaces| {
+            interfaces
+                .into_iter()
+                .find(|interface| interface.name == "mtu-mac0")
+        });
+        ip(&["link", "del", "mtu-mac0"]);
+        let expected = Some([0x02, 0x00, 0x5e, 0x00, 0x53, 0x01]);
+        let interface = interface.unwrap();
+        assert_eq!(interface.mac, expected);
+        assert_eq!(listed.unwrap().unwrap().mac, expected);
+        assert_eq!(interface.kind, Some(InterfaceKind::Ethernet));
+        // The link is created down.
+        assert!(interface.flags.contains(InterfaceFlags::MULTICAST));
+        assert!(!interface.flags.contains(InterfaceFlags::UP));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn handle_survives_rename() {
+        use std::process::Command;
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        ip(&[
+            "link", "add", "mtu-hdl0", "mtu", "1280", "type", "veth", "peer", "name", "mtu-hdl1",
+        ]);
+        let handle = InterfaceHandle::from_name("mtu-hdl0");
+        ip(&["link", "set", "mtu-hdl0", "name", "mtu-hdl2"]);
+        let (name, mtu) = handle.as_ref().map_or_else(
+            |_| (None, None),
+            |handle| (handle.name().ok(), handle.mtu().ok()),
+        );
+        ip(&["link", "del", "mtu-hdl2"]);
+        let removed = handle.as_ref().map(InterfaceHandle::mtu);
+        assert_eq!(name.as_deref(), Some("mtu-hdl2"));
+        assert_eq!(mtu, Some(1280));
+        assert!(removed.unwrap().is_err());
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn source_preference() {
+        use std::{process::Command, thread::sleep, time::Duration};
+
+        let ip = |args: &[&str]| {
+            let out = Command::new("ip").args(args).output().unwrap();
+            assert!(out.status.success());
+            String::from_utf8(out.stdout).unwrap()
+        };
+        let sysctl = |arg: &str| {
+            assert!(Command::new("sysctl")
+                .args(["-qw", arg])
+                .status()
+                .unwrap()
+                .success());
+        };
+        ip(&[
+            "link", "add", "mtu-src0", "type", "veth", "peer", "name", "mtu-src1",
+        ]);
+        ip(&["link", "set", "mtu-src1", "mtu", "1280"]);
+        ip(&["link", "set", "mtu-src0", "up"]);
+        ip(&["link", "set", "mtu-src1", "up"]);
+        sysctl("net.ipv6.conf.mtu-src0.accept_dad=0");
+        sysctl("net.ipv6.conf.mtu-src0.use_tempaddr=2");
+        // With `mngtmpaddr`, the kernel derives a temporary address from the stable one.
+        ip(&[
+            "-6",
+            "addr",
+            "add",
+            "2001:db8:1::1/64",
+            "dev",
+            "mtu-src0",
+            "mngtmpaddr",
+            "nodad",
+        ]);
+        for _ in 0..50 {
+            if !ip(&["-6", "addr", "show", "dev", "mtu-src0", "temporary"]).is_empty() {
+                break;
+            }
+            sleep(Duration::from_millis(100));
+        }
+        // Only traffic from the stable address is routed via `mtu-src1`. The other route needs a
+        // shorter prefix, since the kernel does not fall back to it within the same prefix when
+        // looking up a route without a source address.
+        ip(&["-6", "route", "add", "2001:db8:2::/48", "dev", "mtu-src0"]);
+        ip(&[
+            "-6",
+            "route",
+            "add",
+            "2001:db8:2::/64",
+            "from",
+            "2001:db8:1::1",
+            "dev",
+            "mtu-src1",
+        ]);
+        let remote = Ipv6Addr::new(0x2001, 0xdb8, 2, 0, 0, 0, 0, 1);
+        let temporary =
+            interface_and_mtu_with_source_preference(remote, SourcePreference::Temporary);
+        let stable = interface_and_mtu_with_source_preference(remote, SourcePreference::Stable);
+        // Deleting the link also deletes the addresses and routes on it.
+        ip(&["link", "del", "mtu-src0"]);
+        assert_eq!(temporary.unwrap(), NameMtu(Some("mtu-src0"), 1500));
+        assert_eq!(stable.unwrap(), NameMtu(Some("mtu-src1"), 1280));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn link_local_v4() {
+        use std::process::Command;
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        ip(&[
+            "link", "add", "mtu-ll0", "mtu", "1300", "type", "veth", "peer", "name", "mtu-ll1",
+        ]);
+        ip(&["link", "set", "mtu-ll0", "up"]);
+        // Without the prefix route, the route query would return the interface of the default
+        // route.
+        ip(&[
+            "addr",
+            "add",
+            "169.254.10.1/16",
+            "dev",
+            "mtu-ll0",
+            "noprefixroute",
+        ]);
+        let res = interface_and_mtu(IpAddr::V4(Ipv4Addr::new(169, 254, 20, 2)));
+        ip(&["link", "del", "mtu-ll0"]);
+        assert_eq!(res.unwrap(), NameMtu(Some("mtu-ll0"), 1300));
+    }
+
+    #[test]
+    fn routes_include_loopback() {
+        let name = interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST))
+            .unwrap()
+            .name;
+        let routes = routes().unwrap();
+        assert!(routes.iter().any(|route| {
+            route.destination
+                == crate::network_address(IpAddr::V4(Ipv4Addr::LOCALHOST), route.prefix_len)
+                && InterfaceHandle::from_index(route.oif_index)
+                    .name()
+                    .is_ok_and(|oif| oif == name)
+        }));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn routes_report_mtu() {
+        use std::process::Command;
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        ip(&[
+            "route",
+            "add",
+            "198.51.100.0/24",
+            "via",
+            "127.0.0.2",
+            "dev",
+            "lo",
+            "mtu",
+            "1234",
+        ]);
+        let routes = routes();
+        ip(&["route", "del", "198.51.100.0/24"]);
+        let route = routes
+            .unwrap()
+            .into_iter()
+            .find(|route| route.destination == IpAddr::V4(Ipv4Addr::new(198, 51, 100, 0)))
+            .unwrap();
+        assert_eq!(route.prefix_len, 24);
+        assert_eq!(route.gateway, Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))));
+        assert_eq!(route.mtu, Some(1234));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn auto_src_loopback() {
+        let remote = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let Interface { name, mtu, .. } = interface_and_mtu(remote).unwrap();
+        assert_eq!(
+            interface_and_mtu_via_name_auto_src(remote, &name).unwrap(),
+            (remote, mtu)
+        );
+        assert_eq!(
+            interface_and_mtu_via_name_auto_src(remote, "nonexistent0")
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[ignore = "requires CAP_NET_ADMIN"]
+    fn auto_src_pinned() {
+        use std::process::Command;
+
+        let ip = |args: &[&str]| {
+            assert!(Command::new("ip").args(args).status().unwrap().success());
+        };
+        ip(&[
+            "link", "add", "mtu-as0", "mtu", "1300", "type", "veth", "peer", "name", "mtu-as1",
+        ]);
+        ip(&["link", "set", "mtu-as0", "up"]);
+        ip(&["addr", "add", "192.0.2.1/24", "dev", "mtu-as0"]);
+        // Pinning the interface overrides the routing table, which has no route via it.
+        let res = interface_and_mtu_via_name_auto_src(
+            IpAddr::V4(Ipv4Addr::new(198, 51, 100, 7)),
+            "mtu-as0",
+        );
+        ip(&["link", "del", "mtu-as0"]);
+        assert_eq!(
+            res.unwrap(),
+            (IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)), 1300)
+        );
+    }
+
+    #[test]
+    fn changed_loopback() {
+        let remote = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let prev = interface_and_mtu(remote).unwrap();
+        assert_eq!(changed(&prev, remote).unwrap(), None);
+
+        let stale = Interface {
+            mtu: prev.mtu - 1,
+            ..prev.clone()
+        };
+        assert_eq!(changed(&stale, remote).unwrap(), Some(prev));
+    }
+
+    #[test]
+    fn explain_loopback() {
+        let remote = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let Interface {
+            name, mtu, index, ..
+        } = interface_and_mtu(remote).unwrap();
+        let explanation = explain(remote).unwrap();
+        assert!(explanation.starts_with(&format!(
+            "Destination 127.0.0.1 routes via interface {name} (index {index}, loopback, up) on a \
+             /32 route.\nDestination is on-link, source address 127.0.0.1.\nLink MTU {mtu}"
+        )));
+        assert!(explanation.ends_with(&format!(", usable UDP payload {}.", (mtu - 28).min(65_507))));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn explain_loopback_exact() {
+        assert_eq!(
+            explain(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap(),
+            "Destination 127.0.0.1 routes via interface lo (index 1, loopback, up) on a /32 \
+             route.\nDestination is on-link, source address 127.0.0.1.\nLink MTU 65536, usable UDP \
+             payload 65507."
+        );
+    }
+
+    #[test]
+    fn explain_route() {
+        use crate::RouteExplanation;
+
+        let route = RouteExplanation {
+            remote: IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+            interface: Interface {
+                flags: InterfaceFlags::UP | InterfaceFlags::RUNNING,
+                kind: Some(InterfaceKind::Ethernet),
+                ..Interface::new("eth0".to_string(), 1500, 2)
+            },
+            prefix_len: Some(0),
+            on_link: false,
+            gateway: Some(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1))),
+            source: Some(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 2))),
+            route_mtu: Some(1400),
+        };
+        assert_eq!(
+            route.to_string(),
+            "Destination 192.0.2.1 routes via interface eth0 (index 2, ethernet, up) on a /0 \
+             default route.\nNext hop is gateway 198.51.100.1, source address 198.51.100.2.\nLink \
+             MTU 1500, route MTU 1400, usable UDP payload 1372."
+        );
+
+        let route = RouteExplanation {
+            interface: Interface::new("eth0".to_string(), 1500, 2),
+            prefix_len: Some(24),
+            on_link: true,
+            gateway: None,
+            source: None,
+            route_mtu: None,
+            ..route
+        };
+        assert_eq!(
+            route.to_string(),
+            "Destination 192.0.2.1 routes via interface eth0 (index 2, down) on a /24 route.\n\
+             Destination is on-link, source address not known.\nLink MTU 1500, usable UDP payload \
+             1472."
+        );
+
+        let route = RouteExplanation {
+            interface: Interface {
+                kind: Some(InterfaceKind::Other(512)),
+                ..route.interface
+            },
+            prefix_len: None,
+            ..route
+        };
+        assert!(route.to_string().starts_with(
+            "Destination 192.0.2.1 routes via interface eth0 (index 2, type 512, down).\n"
+        ));
+    }
+
+    #[test]
+    fn frame_payload_loopback() {
+        let interface = interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap();
+        assert_eq!(max_frame_payload(interface.index).unwrap(), interface.mtu);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn via_index_loopback() {
+        let name = std::ffi::CString::new(LOOPBACK[0].0.unwrap()).unwrap();
+        // SAFETY: `name` is a NUL-terminated string that outlives the call.
+        let index = InterfaceIndex::new(unsafe { libc::if_nametoindex(name.as_ptr()) });
+        assert_ne!(index.get(), 0);
+        assert_eq!(
+            interface_and_mtu_via_index(IpAddr::V4(Ipv4Addr::LOCALHOST), index).unwrap(),
+            LOOPBACK[0]
+        );
+        // The variants canonicalize IPv4-mapped destinations, as `interface_and_mtu` does.
+        let mapped = IpAddr::V6(Ipv4Addr::LOCALHOST.to_ipv6_mapped());
+        assert_eq!(
+            interface_and_mtu_via_index(mapped, index).unwrap(),
+            LOOPBACK[0]
+        );
+    }
+
+    #[test]
+    fn bound_device_loopback() {
+        let remote = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        assert_eq!(
+            crate::mtu_for_bound_device(LOOPBACK[0].0.unwrap()).unwrap(),
+            interface_and_mtu(remote).unwrap().mtu
+        );
+    }
+
+    #[test]
+    fn bound_device_unknown() {
+        assert_eq!(
+            crate::mtu_for_bound_device("nonexistent0")
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn from_raw_fd_loopback() {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        socket.connect(socket.local_addr().unwrap()).unwrap();
+        #[cfg(not(target_os = "windows"))]
+        let res = crate::interface_and_mtu_from_raw_fd(socket.as_fd());
+        #[cfg(target_os = "windows")]
+        let res = crate::interface_and_mtu_from_raw_socket(socket.as_socket());
+        assert_eq!(res.unwrap(), LOOPBACK[0]);
+    }
+
+    #[test]
+    fn from_raw_fd_bound() {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        #[cfg(not(target_os = "windows"))]
+        let res = crate::interface_and_mtu_from_raw_fd(socket.as_fd());
+        #[cfg(target_os = "windows")]
+        let res = crate::interface_and_mtu_from_raw_socket(socket.as_socket());
+        assert_eq!(res.unwrap(), LOOPBACK[0]);
+    }
+
+    #[test]
+    fn from_raw_fd_unconnected() {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        #[cfg(not(target_os = "windows"))]
+        let res = crate::interface_and_mtu_from_raw_fd(socket.as_fd());
+        #[cfg(target_os = "windows")]
+        let res = crate::interface_and_mtu_from_raw_socket(socket.as_socket());
+        assert!(res.is_err());
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn from_raw_fd_not_a_socket() {
+        let file = std::fs::File::open(env::current_exe().unwrap()).unwrap();
+        assert_eq!(
+            crate::interface_and_mtu_from_raw_fd(file.as_fd())
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+    }
 }
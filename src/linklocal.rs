@@ -0,0 +1,97 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::{
+    ffi::CStr,
+    io::{Error, Result},
+    net::Ipv4Addr,
+    ptr,
+};
+
+use libc::{freeifaddrs, getifaddrs, if_nametoindex, ifaddrs, sockaddr_in, AF_INET};
+
+use crate::{interface_and_mtu_impl, Interface, InterfaceIndex, RouteQuery};
+
+/// The linked list of interface addresses returned by `getifaddrs`.
+struct IfAddrs(*mut ifaddrs);
+
+impl IfAddrs {
+    fn new() -> Result<Self> {
+        let mut ifap = Self(ptr::null_mut());
+        // getifaddrs allocates memory for the linked list of interfaces that is freed by
+        // `IfAddrs::drop`.
+        if unsafe { getifaddrs(ptr::from_mut(&mut ifap.0)) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(ifap)
+    }
+}
+
+impl Drop for IfAddrs {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            // Free the memory allocated by `getifaddrs`.
+            unsafe {
+                freeifaddrs(self.0);
+            }
+        }
+    }
+}
+
+/// Return the IPv4 address in `sa`, if it is an `AF_INET` socket address.
+fn ipv4(sa: *const libc::sockaddr) -> Option<Ipv4Addr> {
+    if sa.is_null() || i32::from(unsafe { (*sa).sa_family }) != AF_INET {
+        return None;
+    }
+    let sin: sockaddr_in = unsafe { ptr::read_unaligned(sa.cast()) };
+    Some(Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr)))
+}
+
+/// Return the indices and names of the interfaces that have an IPv4 link-local address on a subnet
+/// containing `remote`, ordered by index.
+fn link_local_owners(remote: Ipv4Addr) -> Result<Vec<(u32, String)>> {
+    let ifap = IfAddrs::new()?;
+    let mut owners = Vec::new();
+    let mut ifa = ifap.0;
+    while !ifa.is_null() {
+        let cur = unsafe { &*ifa };
+        ifa = cur.ifa_next;
+        let (Some(addr), Some(mask)) = (ipv4(cur.ifa_addr), ipv4(cur.ifa_netmask)) else {
+            continue;
+        };
+        let mask = u32::from(mask);
+        if !addr.is_link_local() || u32::from(addr) & mask != u32::from(remote) & mask {
+            continue;
+        }
+        let index = unsafe { if_nametoindex(cur.ifa_name) };
+        let name = unsafe { CStr::from_ptr(cur.ifa_name) }
+            .to_string_lossy()
+            .to_string();
+        owners.push((index, name));
+    }
+    owners.sort_unstable();
+    owners.dedup();
+    Ok(owners)
+}
+
+/// Return the name and MTU of the interface towards the IPv4 link-local destination `remote`, for
+/// which `query` is the route query.
+///
+/// Link-local destinations are always on-link, so the interface needs to have a link-local address
+/// on a subnet containing `remote`. The interface selected by the route query is used if it
+/// qualifies, and otherwise the qualifying interface with the lowest index. If no interface
+/// qualifies, the result of the route query is returned.
+pub fn interface_and_mtu(remote: Ipv4Addr, query: RouteQuery) -> Result<Interface> {
+    let owners = link_local_owners(remote)?;
+    let routed = interface_and_mtu_impl(query);
+    match (&routed, owners.first()) {
+        (Ok(Interface { name, .. }), Some(_)) if owners.iter().any(|(_, owner)| owner == name) => {
+            routed
+        }
+        (_, Some(&(index, _))) => Interface::from_index(InterfaceIndex(index)),
+        (_, None) => routed,
+    }
+}
@@ -5,21 +5,42 @@
 // except according to those terms.
 
 use std::{
+    collections::{HashMap, VecDeque},
     ffi::CStr,
+    fs,
     io::{Error, ErrorKind, Read as _, Result, Write as _},
-    net::IpAddr,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket},
     num::TryFromIntError,
+    os::fd::{AsRawFd as _, BorrowedFd},
     ptr, slice,
+    time::Duration,
 };
 
 use libc::{
-    c_int, AF_NETLINK, ARPHRD_NONE, IFLA_IFNAME, IFLA_MTU, NETLINK_ROUTE, RTA_DST, RTA_OIF,
-    RTM_GETLINK, RTM_GETROUTE, RTM_NEWLINK, RTM_NEWROUTE, RTN_UNICAST, RT_SCOPE_UNIVERSE,
-    RT_TABLE_MAIN,
+    bind, c_char, c_int, getsockopt, if_nametoindex, setsockopt, sockaddr, sockaddr_nl, socklen_t,
+    AF_NETLINK, ARPHRD_ETHER, ARPHRD_IEEE80211, ARPHRD_IEEE80211_PRISM, ARPHRD_IEEE80211_RADIOTAP,
+    ARPHRD_IPGRE, ARPHRD_LOOPBACK, ARPHRD_NONE, ARPHRD_PPP, ARPHRD_SIT, ARPHRD_TUNNEL,
+    ARPHRD_TUNNEL6, IFA_ADDRESS, IFA_LOCAL, IFLA_ADDRESS, IFLA_IFNAME, IFLA_INFO_KIND,
+    IFLA_LINKINFO, IFLA_MASTER, IFLA_MTU, IFLA_STATS64, IPPROTO_IP, IPPROTO_IPV6, IPV6_MTU, IP_MTU,
+    NETLINK_ROUTE, RTA_DST, RTA_GATEWAY, RTA_METRICS, RTA_MULTIPATH, RTA_OIF, RTA_PREFSRC,
+    RTA_PRIORITY, RTA_SRC, RTA_TABLE, RTM_DELLINK, RTM_DELROUTE, RTM_GETADDR, RTM_GETLINK,
+    RTM_GETROUTE, RTM_NEWADDR, RTM_NEWLINK, RTM_NEWROUTE, RTN_BLACKHOLE, RTN_LOCAL, RTN_PROHIBIT,
+    RTN_THROW, RTN_UNICAST, RTN_UNREACHABLE, RT_SCOPE_UNIVERSE, RT_TABLE_MAIN, SOL_SOCKET,
+    SO_BINDTODEVICE,
 };
 use static_assertions::{const_assert, const_assert_eq};
 
-use crate::{aligned_by, default_err, routesocket::RouteSocket, unlikely_err};
+#[cfg(feature = "tokio")]
+use crate::routesocket::AsyncRouteSocket;
+use crate::{
+    default_err, inconsistent_reply_err, max_prefix_len, network_address, no_route_err,
+    not_reachable_via,
+    parse::{aligned_by, parse_c_int, NeFields, RtAttrs, RTA_HDR_LEN},
+    route_if_name_err,
+    routesocket::{self, RouteSocket, QUERY_TIMEOUT},
+    unlikely_err, ChangeEvent, Interface, InterfaceFlags, InterfaceIndex, InterfaceKind,
+    InterfaceStats, InterfaceType, RouteDetails, RouteEntry, RouteQuery, SourcePreference, STRICT,
+};
 
 #[allow(
     clippy::struct_field_names,
@@ -30,80 +51,200 @@ mod bindings {
     include!(env!("BINDINGS"));
 }
 
-use bindings::{ifinfomsg, nlmsghdr, rtattr, rtmsg};
+// libc only has the `RTMGRP_*` constants for Linux, not for Android.
+use bindings::{
+    ifaddrmsg, ifinfomsg, nlmsghdr, rtattr, rtmsg, rtnexthop, RTMGRP_IPV4_ROUTE, RTMGRP_IPV6_ROUTE,
+    RTMGRP_LINK, RTM_F_FIB_MATCH,
+};
+asserted_const_with_type!(RTAX_MTU, u16, bindings::RTAX_MTU, u32);
 
 asserted_const_with_type!(AF_INET, u8, libc::AF_INET, i32);
 asserted_const_with_type!(AF_INET6, u8, libc::AF_INET6, i32);
 asserted_const_with_type!(AF_UNSPEC, u8, libc::AF_UNSPEC, i32);
 asserted_const_with_type!(NLM_F_REQUEST, u16, libc::NLM_F_REQUEST, c_int);
-asserted_const_with_type!(NLM_F_ACK, u16, libc::NLM_F_ACK, c_int);
+asserted_const_with_type!(NLM_F_DUMP, u16, libc::NLM_F_DUMP, c_int);
+asserted_const_with_type!(NLM_F_MULTI, u16, libc::NLM_F_MULTI, c_int);
+asserted_const_with_type!(NLMSG_DONE, u16, libc::NLMSG_DONE, c_int);
+asserted_const_with_type!(NLMSG_NOOP, u16, libc::NLMSG_NOOP, c_int);
 asserted_const_with_type!(NLMSG_ERROR, u16, libc::NLMSG_ERROR, c_int);
+asserted_const_with_type!(NLMSG_OVERRUN, u16, libc::NLMSG_OVERRUN, c_int);
+asserted_const_with_type!(AF_NETLINK_FAMILY, u16, AF_NETLINK, c_int);
+/// The link types of raw IP devices, such as cellular modems, and of IPv6 GRE tunnels, which
+/// `libc` does not define.
+const ARPHRD_RAWIP: u16 = 519;
+const ARPHRD_IP6GRE: u16 = 823;
+/// The per-family link attributes and the indices of the IPv6 MTU and of the IPv6 disable switch
+/// in the IPv6 configuration, which `libc` does not define.
+const IFLA_AF_SPEC: u16 = 26;
+const IFLA_INET6_CONF: u16 = 2;
+const DEVCONF_MTU6: usize = 2;
+const DEVCONF_DISABLE_IPV6: usize = 26;
+#[allow(clippy::cast_sign_loss)] // The interface flag bits are positive.
+const IFF_POINTOPOINT: u32 = libc::IFF_POINTOPOINT as u32;
+const RTMGRP_CHANGES: u32 = RTMGRP_LINK | RTMGRP_IPV4_ROUTE | RTMGRP_IPV6_ROUTE;
+/// The length of a Netlink message header, i.e., `NLMSG_HDRLEN`.
+const NLMSG_HDRLEN: usize = std::mem::size_of::<nlmsghdr>();
 
 const_assert!(std::mem::size_of::<nlmsghdr>() <= u8::MAX as usize);
 const_assert!(std::mem::size_of::<rtmsg>() <= u8::MAX as usize);
 const_assert!(std::mem::size_of::<rtattr>() <= u8::MAX as usize);
 const_assert!(std::mem::size_of::<ifinfomsg>() <= u8::MAX as usize);
+const_assert!(std::mem::size_of::<ifaddrmsg>() <= u8::MAX as usize);
+const_assert_eq!(std::mem::size_of::<rtattr>(), RTA_HDR_LEN);
 
-const NETLINK_BUFFER_SIZE: usize = 8192; // See netlink(7) man page.
+/// A fixed-size netlink struct that is parsed field by field from the start of a buffer, instead of
+/// being copied out of it with a pointer cast.
+trait FromNeFields: Sized {
+    /// Read all fields of `Self`, in order, from `fields`.
+    fn read(fields: &mut NeFields<'_>) -> Option<Self>;
 
-#[repr(C)]
-enum AddrBytes {
-    V4([u8; 4]),
-    V6([u8; 16]),
+    /// Parse `Self` from the start of `buf`, or return `None` if `buf` is too short.
+    fn parse(buf: &[u8]) -> Option<Self> {
+        Self::read(&mut NeFields(buf))
+    }
 }
 
-impl AddrBytes {
-    const fn new(ip: IpAddr) -> Self {
-        match ip {
-            IpAddr::V4(ip) => Self::V4(ip.octets()),
-            IpAddr::V6(ip) => Self::V6(ip.octets()),
-        }
+// The structs have no implicit padding, so reading their fields in order covers them exactly.
+const_assert_eq!(std::mem::size_of::<nlmsghdr>(), 16);
+const_assert_eq!(std::mem::size_of::<rtmsg>(), 12);
+const_assert_eq!(std::mem::size_of::<rtnexthop>(), 8);
+const_assert_eq!(std::mem::size_of::<ifinfomsg>(), 16);
+const_assert_eq!(std::mem::size_of::<ifaddrmsg>(), 8);
+
+impl FromNeFields for nlmsghdr {
+    fn read(fields: &mut NeFields<'_>) -> Option<Self> {
+        Some(Self {
+            nlmsg_len: fields.u32()?,
+            nlmsg_type: fields.u16()?,
+            nlmsg_flags: fields.u16()?,
+            nlmsg_seq: fields.u32()?,
+            nlmsg_pid: fields.u32()?,
+        })
     }
+}
 
-    const fn len(&self) -> usize {
-        match self {
-            Self::V4(_) => 4,
-            Self::V6(_) => 16,
-        }
+impl FromNeFields for rtmsg {
+    fn read(fields: &mut NeFields<'_>) -> Option<Self> {
+        Some(Self {
+            rtm_family: fields.u8()?,
+            rtm_dst_len: fields.u8()?,
+            rtm_src_len: fields.u8()?,
+            rtm_tos: fields.u8()?,
+            rtm_table: fields.u8()?,
+            rtm_protocol: fields.u8()?,
+            rtm_scope: fields.u8()?,
+            rtm_type: fields.u8()?,
+            rtm_flags: fields.u32()?,
+        })
     }
 }
 
-impl From<AddrBytes> for [u8; 16] {
-    fn from(addr: AddrBytes) -> Self {
-        match addr {
-            AddrBytes::V4(bytes) => {
-                let mut v6 = [0; 16];
-                v6[..4].copy_from_slice(&bytes);
-                v6
-            }
-            AddrBytes::V6(bytes) => bytes,
-        }
+impl FromNeFields for rtnexthop {
+    fn read(fields: &mut NeFields<'_>) -> Option<Self> {
+        Some(Self {
+            rtnh_len: fields.u16()?,
+            rtnh_flags: fields.u8()?,
+            rtnh_hops: fields.u8()?,
+            rtnh_ifindex: fields.i32()?,
+        })
+    }
+}
+
+impl FromNeFields for ifinfomsg {
+    fn read(fields: &mut NeFields<'_>) -> Option<Self> {
+        Some(Self {
+            ifi_family: fields.u8()?,
+            __ifi_pad: fields.u8()?,
+            ifi_type: fields.u16()?,
+            ifi_index: fields.i32()?,
+            ifi_flags: fields.u32()?,
+            ifi_change: fields.u32()?,
+        })
+    }
+}
+
+impl FromNeFields for ifaddrmsg {
+    fn read(fields: &mut NeFields<'_>) -> Option<Self> {
+        Some(Self {
+            ifa_family: fields.u8()?,
+            ifa_prefixlen: fields.u8()?,
+            ifa_flags: fields.u8()?,
+            ifa_scope: fields.u8()?,
+            ifa_index: fields.u32()?,
+        })
     }
 }
+// Interface indices are `c_int` in netlink messages. They are never negative, so they fit the `u32`
+// of the public API.
+const_assert!(std::mem::size_of::<libc::c_int>() <= std::mem::size_of::<u32>());
+
+/// The initial size of the buffer for replies, which grows to fit larger ones. See netlink(7).
+const NETLINK_BUFFER_SIZE: usize = 8192;
+/// The kernel sizes the messages of a dump by the largest read buffer it has seen, up to 32 KiB.
+const NETLINK_DUMP_BUFFER_SIZE: usize = 32768;
+
+/// Open a netlink socket for queries, which gives up on replies after `QUERY_TIMEOUT`.
+fn query_socket() -> Result<RouteSocket> {
+    let fd = RouteSocket::new(AF_NETLINK, NETLINK_ROUTE)?;
+    fd.set_read_timeout(Some(QUERY_TIMEOUT))?;
+    Ok(fd)
+}
+
+/// Write a Netlink route attribute of type `rta_type` holding `ip` to `buf` at `offset`, and return
+/// the offset following it.
+fn put_ip_attr(buf: &mut [u8], offset: usize, rta_type: u16, ip: IpAddr) -> usize {
+    let (bytes, len) = match ip {
+        IpAddr::V4(ip) => {
+            let mut bytes = [0; 16];
+            bytes[..4].copy_from_slice(&ip.octets());
+            (bytes, 4)
+        }
+        IpAddr::V6(ip) => (ip.octets(), 16),
+    };
+    let rta_len = RTA_HDR_LEN + len;
+    #[allow(clippy::cast_possible_truncation)] // At most 20.
+    buf[offset..offset + 2].copy_from_slice(&(rta_len as u16).to_ne_bytes());
+    buf[offset + 2..offset + RTA_HDR_LEN].copy_from_slice(&rta_type.to_ne_bytes());
+    buf[offset + RTA_HDR_LEN..offset + rta_len].copy_from_slice(&bytes[..len]);
+    // Addresses are four or 16 bytes long, so the attribute needs no padding.
+    offset + rta_len
+}
 
 #[repr(C)]
-#[derive(Default)]
 struct IfIndexMsg {
     nlmsg: nlmsghdr,
     rtm: rtmsg,
-    rt: rtattr,
-    addr: [u8; 16],
+    oif_rt: rtattr,
+    oif: u32,
+    /// The `RTA_DST` attribute, followed by the `RTA_SRC` attribute for queries with a source
+    /// address. Only the used part is included in `nlmsg_len`.
+    addrs: [u8; 2 * (RTA_HDR_LEN + 16)],
 }
 
 impl IfIndexMsg {
-    fn new(remote: IpAddr, nlmsg_seq: u32) -> Self {
-        let addr = AddrBytes::new(remote);
+    fn new(query: RouteQuery, nlmsg_seq: u32) -> Self {
+        let remote = query.remote;
+        debug_assert!(query
+            .src
+            .map_or(true, |src| src.is_ipv4() == remote.is_ipv4()));
+        let src = query.src.filter(|src| src.is_ipv4() == remote.is_ipv4());
+        let mut addrs = [0; 2 * (RTA_HDR_LEN + 16)];
+        let mut addrs_len = put_ip_attr(&mut addrs, 0, RTA_DST, remote);
+        if let Some(src) = src {
+            addrs_len = put_ip_attr(&mut addrs, addrs_len, RTA_SRC, src);
+        }
         #[allow(clippy::cast_possible_truncation)]
-        // Structs lens are <= u8::MAX per `const_assert!`s above; `addr_bytes` is max. 16 for IPv6.
+        // Structs lens are <= u8::MAX per `const_assert!`s above; `addrs_len` is max. 40.
         let nlmsg_len = (std::mem::size_of::<nlmsghdr>()
             + std::mem::size_of::<rtmsg>()
             + std::mem::size_of::<rtattr>()
-            + addr.len()) as u32;
+            + std::mem::size_of::<u32>()
+            + addrs_len) as u32;
         Self {
             nlmsg: nlmsghdr {
                 nlmsg_len,
                 nlmsg_type: RTM_GETROUTE,
-                nlmsg_flags: NLM_F_REQUEST | NLM_F_ACK,
+                nlmsg_flags: NLM_F_REQUEST,
                 nlmsg_seq,
                 ..Default::default()
             },
@@ -112,22 +253,28 @@ impl IfIndexMsg {
                     IpAddr::V4(_) => AF_INET,
                     IpAddr::V6(_) => AF_INET6,
                 },
-                rtm_dst_len: match remote {
-                    IpAddr::V4(_) => 32,
-                    IpAddr::V6(_) => 128,
+                rtm_dst_len: query.prefix_len,
+                rtm_src_len: src.map_or(0, max_prefix_len),
+                #[allow(clippy::cast_possible_truncation)] // Masked to eight bits.
+                rtm_tos: match remote {
+                    IpAddr::V4(_) => 0,
+                    // The IPv6 traffic class is in bits 20 to 27 of the flowinfo.
+                    IpAddr::V6(_) => ((query.flowinfo >> 20) & 0xff) as u8,
                 },
                 rtm_table: RT_TABLE_MAIN,
                 rtm_scope: RT_SCOPE_UNIVERSE,
                 rtm_type: RTN_UNICAST,
                 ..Default::default()
             },
-            rt: rtattr {
+            oif_rt: rtattr {
                 #[allow(clippy::cast_possible_truncation)]
-                // Structs len is <= u8::MAX per `const_assert!` above; `addr_bytes` is max. 16 for IPv6.
-                rta_len: (std::mem::size_of::<rtattr>() + addr.len()) as u16,
-                rta_type: RTA_DST,
+                // Structs len is <= u8::MAX per `const_assert!` above.
+                rta_len: (std::mem::size_of::<rtattr>() + std::mem::size_of::<u32>()) as u16,
+                rta_type: RTA_OIF,
             },
-            addr: addr.into(),
+            // The kernel treats an output interface index of zero as "any interface".
+            oif: query.oif.unwrap_or(0),
+            addrs,
         }
     }
 
@@ -144,121 +291,456 @@ impl From<&IfIndexMsg> for &[u8] {
     }
 }
 
-impl TryFrom<&[u8]> for nlmsghdr {
-    type Error = Error;
+/// Return a buffer for replies, for [`read_msg_with_seq`].
+fn reply_buf() -> Vec<u8> {
+    vec![0u8; NETLINK_BUFFER_SIZE]
+}
 
-    fn try_from(value: &[u8]) -> Result<Self> {
-        if value.len() < std::mem::size_of::<Self>() {
-            return Err(default_err());
+/// Read replies from `fd` into `buf` until one of type `kind` with sequence number `seq` arrives,
+/// and return it. `buf` grows to fit larger replies and can be reused for further reads.
+fn read_msg_with_seq(
+    fd: &mut RouteSocket,
+    buf: &mut Vec<u8>,
+    seq: u32,
+    kind: u16,
+) -> Result<(nlmsghdr, Vec<u8>)> {
+    loop {
+        let len = fd.read_growing(buf)?;
+        if let Some((hdr, msg)) = find_msg_with_seq(&buf[..len], seq, kind, STRICT)? {
+            return Ok((hdr, msg.to_vec()));
         }
-        Ok(unsafe { ptr::read_unaligned(value.as_ptr().cast()) })
     }
 }
 
-fn parse_c_int(buf: &[u8]) -> Result<c_int> {
-    let bytes = <&[u8] as TryInto<[u8; std::mem::size_of::<c_int>()]>>::try_into(
-        &buf[..std::mem::size_of::<c_int>()],
-    )
-    .map_err(|_| default_err())?;
-    Ok(c_int::from_ne_bytes(bytes))
-}
-
-fn read_msg_with_seq(fd: &mut RouteSocket, seq: u32, kind: u16) -> Result<(nlmsghdr, Vec<u8>)> {
+/// Like [`read_msg_with_seq`], but waiting for the reply on the Tokio reactor.
+#[cfg(feature = "tokio")]
+async fn read_msg_with_seq_async(
+    fd: &mut AsyncRouteSocket,
+    buf: &mut Vec<u8>,
+    seq: u32,
+    kind: u16,
+) -> Result<(nlmsghdr, Vec<u8>)> {
     loop {
-        let buf = &mut [0u8; NETLINK_BUFFER_SIZE];
-        let len = fd.read(buf.as_mut_slice())?;
-        let mut next = &buf[..len];
-        while std::mem::size_of::<nlmsghdr>() <= next.len() {
-            let (hdr, mut msg) = next.split_at(std::mem::size_of::<nlmsghdr>());
-            let hdr: nlmsghdr = hdr.try_into()?;
-            // `msg` has the remainder of this message plus any following messages.
-            // Strip those it off and assign them to `next`.
-            debug_assert!(std::mem::size_of::<nlmsghdr>() <= hdr.nlmsg_len as usize);
-            (msg, next) = msg.split_at(hdr.nlmsg_len as usize - std::mem::size_of::<nlmsghdr>());
-
-            if hdr.nlmsg_seq != seq {
-                continue;
-            }
-
-            if hdr.nlmsg_type == NLMSG_ERROR {
-                // Extract the error code and return it.
-                let err = parse_c_int(msg)?;
-                if err != 0 {
-                    return Err(Error::from_raw_os_error(-err));
-                }
-            } else if hdr.nlmsg_type == kind {
-                // Return the header and the message.
-                return Ok((hdr, msg.to_vec()));
-            }
+        let len = fd.read(buf).await?;
+        if let Some((hdr, msg)) = find_msg_with_seq(&buf[..len], seq, kind, STRICT)? {
+            return Ok((hdr, msg.to_vec()));
         }
     }
 }
 
-impl TryFrom<&[u8]> for rtattr {
-    type Error = Error;
+/// Look for a message of type `kind` with sequence number `seq` among the messages in `buf`.
+/// Returns `None` if more data needs to be read. If `strict`, first check that the messages exactly
+/// fill `buf`.
+fn find_msg_with_seq(
+    buf: &[u8],
+    seq: u32,
+    kind: u16,
+    strict: bool,
+) -> Result<Option<(nlmsghdr, &[u8])>> {
+    if strict {
+        check_nlmsg_lens(buf)?;
+    }
+    let mut next = buf;
+    while NLMSG_HDRLEN <= next.len() {
+        let (hdr, msg, rest) = split_nlmsg(next)?;
+        next = rest;
+
+        if hdr.nlmsg_type == NLMSG_NOOP {
+            // Padding, regardless of its sequence number.
+            continue;
+        }
+
+        if hdr.nlmsg_type == NLMSG_OVERRUN {
+            // The kernel dropped data, possibly including our response. The caller needs to
+            // query again.
+            return Err(Error::other("Netlink overrun, data was lost"));
+        }
 
-    fn try_from(value: &[u8]) -> Result<Self> {
-        if value.len() < std::mem::size_of::<Self>() {
+        if hdr.nlmsg_seq != seq {
+            continue;
+        }
+
+        if hdr.nlmsg_type == NLMSG_DONE {
+            // The reply was a multipart message that ended without one of type `kind`. Waiting for
+            // more would only run into the read timeout.
             return Err(default_err());
         }
-        Ok(unsafe { ptr::read_unaligned(value.as_ptr().cast()) })
+
+        if hdr.nlmsg_type == NLMSG_ERROR {
+            // Extract the error code and return it.
+            let err = parse_c_int(msg).ok_or_else(default_err)?;
+            if err != 0 {
+                return Err(Error::from_raw_os_error(-err));
+            }
+        } else if hdr.nlmsg_type == kind {
+            // Return the header and the message.
+            return Ok(Some((hdr, msg)));
+        }
     }
+    Ok(None)
 }
 
-struct RtAttr<'a> {
-    hdr: rtattr,
-    msg: &'a [u8],
+/// Split the Netlink message at the start of `buf` into its header, its payload and the messages
+/// that follow it. Returns an [`ErrorKind::InvalidData`] error if `buf` is shorter than a header,
+/// or if the length in the header is shorter than the header or longer than `buf`.
+fn split_nlmsg(buf: &[u8]) -> Result<(nlmsghdr, &[u8], &[u8])> {
+    let Some(hdr) = nlmsghdr::parse(buf) else {
+        return Err(inconsistent_reply_err(
+            "Truncated Netlink message header".to_string(),
+        ));
+    };
+    let len = hdr.nlmsg_len as usize;
+    let Some(msg) = buf.get(NLMSG_HDRLEN..len) else {
+        return Err(inconsistent_reply_err(format!(
+            "Netlink message length {len} does not fit the {} bytes left",
+            buf.len()
+        )));
+    };
+    // Messages are padded to four bytes, except possibly the last one.
+    Ok((hdr, msg, buf.get(aligned_by(len, 4)..).unwrap_or_default()))
 }
 
-impl<'a> RtAttr<'a> {
-    fn new(bytes: &'a [u8]) -> Result<Self> {
-        debug_assert!(bytes.len() >= std::mem::size_of::<rtattr>());
-        let (hdr, mut msg) = bytes.split_at(std::mem::size_of::<rtattr>());
-        let hdr: rtattr = hdr.try_into()?;
-        let aligned_len = aligned_by(hdr.rta_len.into(), 4);
-        debug_assert!(std::mem::size_of::<rtattr>() <= aligned_len);
-        (msg, _) = msg.split_at(aligned_len - std::mem::size_of::<rtattr>());
-        Ok(Self { hdr, msg })
+/// Return an error if the lengths of the messages in `buf` do not add up to the length of `buf`.
+fn check_nlmsg_lens(buf: &[u8]) -> Result<()> {
+    let mut next = buf;
+    while !next.is_empty() {
+        (_, _, next) = split_nlmsg(next)?;
     }
+    Ok(())
 }
 
-struct RtAttrs<'a>(&'a [u8]);
+/// If `strict`, return an error if the `rtmsg` at the start of `buf` is not for `family`.
+fn check_rtm_family(buf: &[u8], family: u8, strict: bool) -> Result<()> {
+    if !strict {
+        return Ok(());
+    }
+    let Some(rtm) = rtmsg::parse(buf) else {
+        return Err(inconsistent_reply_err("Truncated route reply".to_string()));
+    };
+    if rtm.rtm_family != family {
+        return Err(inconsistent_reply_err(format!(
+            "Route reply family {} does not match request family {family}",
+            rtm.rtm_family
+        )));
+    }
+    Ok(())
+}
 
-impl<'a> Iterator for RtAttrs<'a> {
-    type Item = RtAttr<'a>;
+/// The parts of an `RTM_NEWROUTE` reply to a route query that we use.
+struct RouteReply {
+    /// The index of the outgoing interface.
+    if_index: i32,
+    /// The preferred source address of the route, if any.
+    src: Option<IpAddr>,
+    /// The gateway of the route, unless the destination is on-link.
+    gateway: Option<IpAddr>,
+    /// The MTU metric of the route, if any.
+    mtu: Option<usize>,
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if std::mem::size_of::<rtattr>() <= self.0.len() {
-            let attr = RtAttr::new(self.0).ok()?;
-            let aligned_len = aligned_by(attr.hdr.rta_len.into(), 4);
-            debug_assert!(self.0.len() >= aligned_len);
-            self.0 = self.0.split_at(aligned_len).1;
-            Some(attr)
-        } else {
-            None
-        }
-    }
+fn if_index(query: RouteQuery, fd: &mut RouteSocket, buf: &mut Vec<u8>) -> Result<i32> {
+    route_reply(query, fd, buf).map(|reply| reply.if_index)
 }
 
-fn if_index(remote: IpAddr, fd: &mut RouteSocket) -> Result<i32> {
+/// Query the route towards `query.remote` and return the relevant parts of the reply.
+fn route_reply(query: RouteQuery, fd: &mut RouteSocket, buf: &mut Vec<u8>) -> Result<RouteReply> {
     // Send RTM_GETROUTE message to get the interface index associated with the destination.
-    let msg_seq = RouteSocket::new_seq();
-    let msg = IfIndexMsg::new(remote, msg_seq);
+    let msg_seq = fd.new_seq();
+    let msg = IfIndexMsg::new(query, msg_seq);
     fd.write_all((&msg).into())?;
 
     // Receive RTM_GETROUTE response.
-    let (_hdr, mut buf) = read_msg_with_seq(fd, msg_seq, RTM_NEWROUTE)?;
+    parse_route_reply(
+        query,
+        msg.rtm.rtm_family,
+        read_msg_with_seq(fd, buf, msg_seq, RTM_NEWROUTE),
+    )
+}
+
+/// Return the relevant parts of `reply`, the result of reading the reply to a route query for
+/// `family`.
+fn parse_route_reply(
+    query: RouteQuery,
+    family: u8,
+    reply: Result<(nlmsghdr, Vec<u8>)>,
+) -> Result<RouteReply> {
+    let (_hdr, mut buf) = match (reply, query.oif) {
+        (Err(e), Some(oif))
+            if matches!(
+                e.raw_os_error(),
+                Some(libc::ENETUNREACH | libc::EHOSTUNREACH | libc::ENODEV)
+            ) =>
+        {
+            return Err(not_reachable_via(oif));
+        }
+        (res, _) => res?,
+    };
+    check_rtm_family(&buf, family, STRICT)?;
     debug_assert!(std::mem::size_of::<rtmsg>() <= buf.len());
     let buf = buf.split_off(std::mem::size_of::<rtmsg>());
 
-    // Parse through the attributes to find the interface index.
+    // Parse through the attributes to find the interface index, preferred source address, gateway
+    // and MTU.
+    let mut if_index = None;
+    let mut src = None;
+    let mut gateway = None;
+    let mut mtu = None;
     for attr in RtAttrs(buf.as_slice()).by_ref() {
-        if attr.hdr.rta_type == RTA_OIF {
-            // We have our interface index.
-            return parse_c_int(attr.msg);
+        match attr.rta_type {
+            RTA_OIF => if_index = Some(parse_c_int(attr.payload).ok_or_else(default_err)?),
+            RTA_PREFSRC => src = ip_from_bytes(family, attr.payload),
+            RTA_GATEWAY => gateway = ip_from_bytes(family, attr.payload),
+            RTA_METRICS => mtu = metrics_mtu(attr.payload),
+            _ => (),
+        }
+    }
+    let if_index = if_index.ok_or_else(default_err)?;
+    match query.oif {
+        Some(oif) if u32::try_from(if_index).ok() != Some(oif) => Err(not_reachable_via(oif)),
+        _ => Ok(RouteReply {
+            if_index,
+            src,
+            gateway,
+            mtu,
+        }),
+    }
+}
+
+/// Return the address of the given family in `bytes`, if it has the right length.
+fn ip_from_bytes(family: u8, bytes: &[u8]) -> Option<IpAddr> {
+    match family {
+        AF_INET => <[u8; 4]>::try_from(bytes).ok().map(IpAddr::from),
+        AF_INET6 => <[u8; 16]>::try_from(bytes).ok().map(IpAddr::from),
+        _ => None,
+    }
+}
+
+/// Return the number of `rtnexthop` entries in the payload of an `RTA_MULTIPATH` attribute.
+fn nexthop_count(mut buf: &[u8]) -> usize {
+    let mut count = 0;
+    while let Some(rtnh) = rtnexthop::parse(buf) {
+        let aligned_len = aligned_by(rtnh.rtnh_len.into(), 4);
+        if aligned_len < std::mem::size_of::<rtnexthop>() || buf.len() < aligned_len {
+            break;
         }
+        count += 1;
+        buf = &buf[aligned_len..];
     }
-    Err(default_err())
+    count
+}
+
+/// Return the interface index of the first `rtnexthop` entry in the payload of an `RTA_MULTIPATH`
+/// attribute.
+fn first_nexthop_index(buf: &[u8]) -> Option<u32> {
+    let rtnh = rtnexthop::parse(buf)?;
+    u32::try_from(rtnh.rtnh_ifindex).ok()
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct RouteDumpMsg {
+    nlmsg: nlmsghdr,
+    rtm: rtmsg,
+}
+
+impl RouteDumpMsg {
+    fn new(nlmsg_seq: u32) -> Self {
+        Self {
+            nlmsg: nlmsghdr {
+                #[allow(clippy::cast_possible_truncation)]
+                // Structs lens are <= u8::MAX per `const_assert!`s above.
+                nlmsg_len: std::mem::size_of::<Self>() as u32,
+                nlmsg_type: RTM_GETROUTE,
+                nlmsg_flags: NLM_F_REQUEST | NLM_F_DUMP,
+                nlmsg_seq,
+                ..Default::default()
+            },
+            // An `rtm_family` of `AF_UNSPEC` dumps the routes of all families.
+            rtm: rtmsg {
+                rtm_family: AF_UNSPEC,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl From<&RouteDumpMsg> for &[u8] {
+    fn from(value: &RouteDumpMsg) -> Self {
+        unsafe {
+            slice::from_raw_parts(
+                ptr::from_ref(value).cast(),
+                std::mem::size_of::<RouteDumpMsg>(),
+            )
+        }
+    }
+}
+
+/// The number of times a dump is restarted after the receive buffer of its socket overflowed.
+const DUMP_RETRIES: usize = 3;
+/// The receive buffer size to request for the first restart of a dump; each further restart
+/// doubles it.
+const DUMP_RETRY_RCVBUF: c_int = 1 << 20;
+
+/// Send the dump request built by `request` for a fresh sequence number, and return the payloads of
+/// the replies of type `kind`.
+///
+/// If the receive buffer of the socket overflows during the dump, the kernel signals `ENOBUFS` and
+/// the dump is incomplete. The partial results are then discarded, and the dump is restarted with
+/// a larger receive buffer, up to `DUMP_RETRIES` times.
+fn dump<T>(request: impl Fn(u32) -> T, kind: u16) -> Result<Vec<Vec<u8>>>
+where
+    for<'a> &'a T: Into<&'a [u8]>,
+{
+    retry_dump(|rcvbuf| {
+        // Use a new socket for every attempt, since the kernel does not start a dump while another
+        // one is still in progress on the same socket.
+        let mut fd = query_socket()?;
+        if let Some(rcvbuf) = rcvbuf {
+            set_rcvbuf(&fd, rcvbuf)?;
+        }
+        let msg_seq = fd.new_seq();
+        fd.write_all((&request(msg_seq)).into())?;
+        read_dump_with_seq(&mut fd, msg_seq, kind)
+    })
+}
+
+/// Call `attempt` until it does not fail with `ENOBUFS`, at most `DUMP_RETRIES + 1` times. It is
+/// passed the receive buffer size to use, if any.
+fn retry_dump(
+    mut attempt: impl FnMut(Option<c_int>) -> Result<Vec<Vec<u8>>>,
+) -> Result<Vec<Vec<u8>>> {
+    let mut rcvbuf = None;
+    for _ in 0..DUMP_RETRIES {
+        match attempt(rcvbuf) {
+            Err(e) if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                rcvbuf =
+                    Some(rcvbuf.map_or(DUMP_RETRY_RCVBUF, |size: c_int| size.saturating_mul(2)));
+            }
+            res => return res,
+        }
+    }
+    attempt(rcvbuf)
+}
+
+/// Set the receive buffer size of `fd` to `size`.
+fn set_rcvbuf(fd: &RouteSocket, size: c_int) -> Result<()> {
+    if unsafe {
+        setsockopt(
+            fd.as_raw_fd(),
+            SOL_SOCKET,
+            libc::SO_RCVBUF,
+            ptr::from_ref(&size).cast(),
+            #[allow(clippy::cast_possible_truncation)] // The size of a `c_int` fits.
+            {
+                std::mem::size_of::<c_int>() as socklen_t
+            },
+        )
+    } == -1
+    {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Read the replies to the dump request with sequence number `seq`, and return the payloads of
+/// those of type `kind`.
+fn read_dump_with_seq(fd: &mut RouteSocket, seq: u32, kind: u16) -> Result<Vec<Vec<u8>>> {
+    let mut msgs = Vec::new();
+    let mut buf = vec![0u8; NETLINK_DUMP_BUFFER_SIZE];
+    loop {
+        let len = fd.read_growing(&mut buf)?;
+        if collect_dump_msgs(&buf[..len], seq, kind, STRICT, &mut msgs)? {
+            return Ok(msgs);
+        }
+    }
+}
+
+/// Append the payloads of the messages of type `kind` with sequence number `seq` in `buf` to
+/// `msgs`. Returns whether the end of the dump was reached, which is signalled by `NLMSG_DONE` for
+/// a multipart reply, or by a reply without `NLM_F_MULTI` that is complete by itself. If `strict`,
+/// first check that the messages exactly fill `buf`.
+fn collect_dump_msgs(
+    buf: &[u8],
+    seq: u32,
+    kind: u16,
+    strict: bool,
+    msgs: &mut Vec<Vec<u8>>,
+) -> Result<bool> {
+    if strict {
+        check_nlmsg_lens(buf)?;
+    }
+    let mut next = buf;
+    while NLMSG_HDRLEN <= next.len() {
+        let (hdr, msg, rest) = split_nlmsg(next)?;
+        next = rest;
+
+        if hdr.nlmsg_type == NLMSG_OVERRUN {
+            return Err(Error::other("Netlink overrun, data was lost"));
+        }
+        if hdr.nlmsg_seq != seq || hdr.nlmsg_type == NLMSG_NOOP {
+            continue;
+        }
+        match hdr.nlmsg_type {
+            NLMSG_DONE => return Ok(true),
+            NLMSG_ERROR => {
+                let err = parse_c_int(msg).ok_or_else(default_err)?;
+                if err != 0 {
+                    return Err(Error::from_raw_os_error(-err));
+                }
+            }
+            t if t == kind => {
+                msgs.push(msg.to_vec());
+                if hdr.nlmsg_flags & NLM_F_MULTI == 0 {
+                    return Ok(true);
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(false)
+}
+
+/// Return the MTU in the payload of an `RTA_METRICS` attribute, if any.
+fn metrics_mtu(buf: &[u8]) -> Option<usize> {
+    RtAttrs(buf)
+        .find(|metric| metric.rta_type == RTAX_MTU)
+        .and_then(|metric| parse_c_int(metric.payload))
+        .and_then(|mtu| usize::try_from(mtu).ok())
+        .filter(|&mtu| mtu != 0)
+}
+
+/// Parse the payload of an `RTM_NEWROUTE` message into a route entry. Returns `None` for routes of
+/// families other than IPv4 and IPv6, and for malformed messages.
+fn parse_route(buf: &[u8]) -> Option<RouteEntry> {
+    let rtm = rtmsg::parse(buf)?;
+    let ip = |bytes: &[u8]| ip_from_bytes(rtm.rtm_family, bytes);
+    let mut route = RouteEntry {
+        destination: match rtm.rtm_family {
+            AF_INET => Ipv4Addr::UNSPECIFIED.into(),
+            AF_INET6 => Ipv6Addr::UNSPECIFIED.into(),
+            _ => return None,
+        },
+        prefix_len: rtm.rtm_dst_len,
+        gateway: None,
+        oif_index: InterfaceIndex(0),
+        mtu: None,
+    };
+    for attr in RtAttrs(&buf[std::mem::size_of::<rtmsg>()..]) {
+        match attr.rta_type {
+            RTA_DST => route.destination = ip(attr.payload)?,
+            RTA_GATEWAY => route.gateway = ip(attr.payload),
+            RTA_OIF => {
+                route.oif_index = InterfaceIndex(u32::try_from(parse_c_int(attr.payload)?).ok()?);
+            }
+            // Multipath routes carry their output interfaces in their next hops.
+            RTA_MULTIPATH if route.oif_index.0 == 0 => {
+                route.oif_index = InterfaceIndex(first_nexthop_index(attr.payload).unwrap_or(0));
+            }
+            RTA_METRICS => route.mtu = metrics_mtu(attr.payload),
+            _ => (),
+        }
+    }
+    Some(route)
 }
 
 #[repr(C)]
@@ -276,7 +758,7 @@ impl IfInfoMsg {
             nlmsg: nlmsghdr {
                 nlmsg_len,
                 nlmsg_type: RTM_GETLINK,
-                nlmsg_flags: NLM_F_REQUEST | NLM_F_ACK,
+                nlmsg_flags: NLM_F_REQUEST,
                 nlmsg_seq,
                 ..Default::default()
             },
@@ -289,6 +771,13 @@ impl IfInfoMsg {
         }
     }
 
+    /// Create a request to dump all links.
+    fn dump(nlmsg_seq: u32) -> Self {
+        let mut msg = Self::new(0, nlmsg_seq);
+        msg.nlmsg.nlmsg_flags |= NLM_F_DUMP;
+        msg
+    }
+
     const fn len(&self) -> usize {
         self.nlmsg.nlmsg_len as usize
     }
@@ -301,51 +790,1563 @@ impl From<&IfInfoMsg> for &[u8] {
     }
 }
 
-fn if_name_mtu(if_index: i32, fd: &mut RouteSocket) -> Result<(String, usize)> {
+/// Return the link-layer address in the payload of an `IFLA_ADDRESS` attribute, if it is a
+/// non-zero Ethernet address. Loopback links have an all-zero one, and tunnels none or a shorter
+/// one.
+fn parse_mac(payload: &[u8]) -> Option<[u8; 6]> {
+    let mac: [u8; 6] = payload.try_into().ok()?;
+    (mac != [0; 6]).then_some(mac)
+}
+
+/// Return the kind of a link with the `ARPHRD_*` type `hw_type`. Links without a link layer, such
+/// as `tun` and `wireguard` devices, are tunnels.
+fn link_kind(hw_type: u16) -> InterfaceKind {
+    match hw_type {
+        ARPHRD_LOOPBACK => InterfaceKind::Loopback,
+        ARPHRD_ETHER => InterfaceKind::Ethernet,
+        ARPHRD_IEEE80211 | ARPHRD_IEEE80211_PRISM | ARPHRD_IEEE80211_RADIOTAP => {
+            InterfaceKind::WiFi
+        }
+        ARPHRD_RAWIP => InterfaceKind::Cellular,
+        ARPHRD_NONE | ARPHRD_TUNNEL | ARPHRD_TUNNEL6 | ARPHRD_SIT | ARPHRD_IPGRE
+        | ARPHRD_IP6GRE => InterfaceKind::Tunnel,
+        _ => InterfaceKind::Other(u32::from(hw_type)),
+    }
+}
+
+/// Fill in the link speed of `interface` from sysfs. Only Ethernet drivers report one, so this
+/// skips other kinds of interfaces, which saves reading sysfs for loopback lookups.
+fn add_link_speed(interface: &mut Interface) {
+    if interface.kind != Some(InterfaceKind::Ethernet) {
+        return;
+    }
+    interface.speed_bps = fs::read_to_string(format!("/sys/class/net/{}/speed", interface.name))
+        .ok()
+        .and_then(|speed| parse_sysfs_speed(&speed));
+}
+
+/// Parse the contents of the sysfs `speed` file of a link, in Mbit/s, into bits per second. Drivers
+/// that do not know the speed report -1 or zero.
+fn parse_sysfs_speed(speed: &str) -> Option<u64> {
+    let mbps: u64 = speed.trim().parse().ok()?;
+    mbps.checked_mul(1_000_000).filter(|&bps| bps != 0)
+}
+
+fn if_name_mtu(if_index: i32, fd: &mut RouteSocket, buf: &mut Vec<u8>) -> Result<(String, usize)> {
+    if_interface(if_index, fd, buf).map(Into::into)
+}
+
+fn if_interface(if_index: i32, fd: &mut RouteSocket, buf: &mut Vec<u8>) -> Result<Interface> {
     // Send RTM_GETLINK message to get interface information for the given interface index.
-    let msg_seq = RouteSocket::new_seq();
+    let msg_seq = fd.new_seq();
     let msg = IfInfoMsg::new(if_index, msg_seq);
     fd.write_all((&msg).into())?;
 
     // Receive RTM_GETLINK response.
-    let (_hdr, mut buf) = read_msg_with_seq(fd, msg_seq, RTM_NEWLINK)?;
-    debug_assert!(std::mem::size_of::<ifinfomsg>() <= buf.len());
+    let (_hdr, msg) = read_msg_with_seq(fd, buf, msg_seq, RTM_NEWLINK)?;
+    let mut interface = parse_link_reply(if_index, msg)?;
+    add_link_speed(&mut interface);
+    Ok(interface)
+}
+
+/// Return the interface with index `if_index` described by `buf`, the body of the reply to a link
+/// query.
+fn parse_link_reply(if_index: i32, mut buf: Vec<u8>) -> Result<Interface> {
+    let ifim = ifinfomsg::parse(&buf).ok_or_else(default_err)?;
     let buf = buf.split_off(std::mem::size_of::<ifinfomsg>());
 
-    // Parse through the attributes to find the interface name and MTU.
+    // Parse through the attributes to find the interface name, MTU and link-layer address.
     let mut ifname = None;
     let mut mtu = None;
-    for attr in RtAttrs(buf.as_slice()).by_ref() {
-        match attr.hdr.rta_type {
+    let mut mac = None;
+    for attr in RtAttrs(buf.as_slice()) {
+        match attr.rta_type {
             IFLA_IFNAME => {
-                let name = CStr::from_bytes_until_nul(attr.msg)
-                    .map_err(|err| Error::new(ErrorKind::Other, err))?;
                 ifname = Some(
-                    name.to_str()
-                        .map_err(|err| Error::new(ErrorKind::Other, err))?
-                        .to_string(),
+                    parse_str(attr.payload)
+                        .ok_or_else(|| Error::other("Interface name is not NUL-terminated"))?,
                 );
             }
             IFLA_MTU => {
                 mtu = Some(
-                    parse_c_int(attr.msg)?
+                    parse_c_int(attr.payload)
+                        .ok_or_else(default_err)?
                         .try_into()
                         .map_err(|e: TryFromIntError| unlikely_err(e.to_string()))?,
                 );
             }
+            IFLA_ADDRESS => mac = parse_mac(attr.payload),
             _ => (),
         }
-        if let (Some(ifname), Some(mtu)) = (ifname.as_ref(), mtu.as_ref()) {
-            return Ok((ifname.clone(), *mtu));
+    }
+
+    let (Some(name), Some(mtu)) = (ifname, mtu) else {
+        return Err(default_err());
+    };
+    Ok(Interface {
+        name,
+        mtu,
+        index: InterfaceIndex(u32::try_from(if_index).map_err(|e| unlikely_err(e.to_string()))?),
+        mac,
+        flags: InterfaceFlags::from_iff(u64::from(ifim.ifi_flags)),
+        kind: Some(link_kind(ifim.ifi_type)),
+        speed_bps: None,
+    })
+}
+
+/// The attributes of a link in an `RTM_NEWLINK` message.
+#[derive(Debug)]
+struct Link {
+    index: i32,
+    name: String,
+    mtu: usize,
+    /// The `ARPHRD_*` type of the link.
+    hw_type: u16,
+    /// The link-layer address of the link, if it is an Ethernet address.
+    mac: Option<[u8; 6]>,
+    /// The `IFF_*` flags of the link.
+    flags: u32,
+    /// The index of the link this one is a member of, such as a bond, if any.
+    master: Option<i32>,
+    /// The kind of virtual link, such as `bond`, if any.
+    kind: Option<String>,
+}
+
+/// Return the NUL-terminated string at the start of `buf`, if any. Interface names are arbitrary
+/// bytes, so invalid UTF-8 is replaced rather than rejected.
+fn parse_str(buf: &[u8]) -> Option<String> {
+    Some(
+        CStr::from_bytes_until_nul(buf)
+            .ok()?
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+/// Parse the payload of an `RTM_NEWLINK` message. Returns `None` for malformed messages.
+fn parse_link(buf: &[u8]) -> Option<Link> {
+    let ifim = ifinfomsg::parse(buf)?;
+    let mut name = None;
+    let mut mtu = None;
+    let mut master = None;
+    let mut kind = None;
+    let mut mac = None;
+    for attr in RtAttrs(&buf[std::mem::size_of::<ifinfomsg>()..]) {
+        match attr.rta_type {
+            IFLA_ADDRESS => mac = parse_mac(attr.payload),
+            IFLA_IFNAME => name = parse_str(attr.payload),
+            IFLA_MTU => mtu = usize::try_from(parse_c_int(attr.payload)?).ok(),
+            IFLA_MASTER => master = parse_c_int(attr.payload),
+            IFLA_LINKINFO => {
+                kind = RtAttrs(attr.payload)
+                    .find(|info| info.rta_type == IFLA_INFO_KIND)
+                    .and_then(|info| parse_str(info.payload));
+            }
+            _ => (),
         }
     }
+    Some(Link {
+        index: ifim.ifi_index,
+        name: name?,
+        mtu: mtu?,
+        hw_type: ifim.ifi_type,
+        mac,
+        flags: ifim.ifi_flags,
+        master,
+        kind,
+    })
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct AddrDumpMsg {
+    nlmsg: nlmsghdr,
+    ifa: ifaddrmsg,
+}
 
-    Err(default_err())
+impl AddrDumpMsg {
+    fn new(nlmsg_seq: u32) -> Self {
+        Self {
+            nlmsg: nlmsghdr {
+                #[allow(clippy::cast_possible_truncation)]
+                // Structs lens are <= u8::MAX per `const_assert!`s above.
+                nlmsg_len: std::mem::size_of::<Self>() as u32,
+                nlmsg_type: RTM_GETADDR,
+                nlmsg_flags: NLM_F_REQUEST | NLM_F_DUMP,
+                nlmsg_seq,
+                ..Default::default()
+            },
+            // An `ifa_family` of `AF_UNSPEC` dumps the addresses of all families.
+            ifa: ifaddrmsg {
+                ifa_family: AF_UNSPEC,
+                ..Default::default()
+            },
+        }
+    }
 }
 
-pub fn interface_and_mtu_impl(remote: IpAddr) -> Result<(String, usize)> {
-    // Create a netlink socket.
-    let mut fd = RouteSocket::new(AF_NETLINK, NETLINK_ROUTE)?;
-    let if_index = if_index(remote, &mut fd)?;
-    if_name_mtu(if_index, &mut fd)
+impl From<&AddrDumpMsg> for &[u8] {
+    fn from(value: &AddrDumpMsg) -> Self {
+        unsafe {
+            slice::from_raw_parts(
+                ptr::from_ref(value).cast(),
+                std::mem::size_of::<AddrDumpMsg>(),
+            )
+        }
+    }
+}
+
+/// An interface address, as described by an `RTM_NEWADDR` message.
+///
+/// For point-to-point interfaces, `IFA_LOCAL` is the local address and `IFA_ADDRESS` the address
+/// of the peer. For all other interfaces, both are the local address, or `IFA_LOCAL` is missing.
+struct IfAddr {
+    if_index: u32,
+    family: u8,
+    /// The `IFA_ADDRESS` attribute, if any.
+    address: Option<IpAddr>,
+    /// The `IFA_LOCAL` attribute, if any.
+    local: Option<IpAddr>,
+}
+
+impl IfAddr {
+    /// Return the local address.
+    fn local(&self) -> Option<IpAddr> {
+        self.local.or(self.address)
+    }
+
+    /// Return the address of the peer, if this is the address of a point-to-point interface.
+    fn peer(&self) -> Option<IpAddr> {
+        self.address
+            .filter(|address| self.local.is_some_and(|local| local != *address))
+    }
+}
+
+/// Parse the payload of an `RTM_NEWADDR` message.
+fn parse_addr(buf: &[u8]) -> Option<IfAddr> {
+    let ifa = ifaddrmsg::parse(buf)?;
+    let mut addr = IfAddr {
+        if_index: ifa.ifa_index,
+        family: ifa.ifa_family,
+        address: None,
+        local: None,
+    };
+    for attr in RtAttrs(&buf[std::mem::size_of::<ifaddrmsg>()..]) {
+        match attr.rta_type {
+            IFA_ADDRESS => addr.address = ip_from_bytes(addr.family, attr.payload),
+            IFA_LOCAL => addr.local = ip_from_bytes(addr.family, attr.payload),
+            _ => (),
+        }
+    }
+    Some(addr)
+}
+
+/// Parse the payload of an `RTM_NEWADDR` message, and return the peer address if the message is
+/// for the interface with index `if_index` and address family `family` and has one.
+fn parse_peer(buf: &[u8], if_index: u32, family: u8) -> Option<IpAddr> {
+    parse_addr(buf)
+        .filter(|addr| addr.if_index == if_index && addr.family == family)?
+        .peer()
+}
+
+pub fn local_addrs_impl() -> Result<Vec<(IpAddr, u32)>> {
+    Ok(dump(AddrDumpMsg::new, RTM_NEWADDR)?
+        .iter()
+        .filter_map(|msg| {
+            let addr = parse_addr(msg)?;
+            Some((addr.local()?, addr.if_index))
+        })
+        .collect())
+}
+
+/// The kinds of links whose members `bond_members` reports.
+const AGGREGATE_KINDS: [&str; 2] = ["bond", "team"];
+
+/// Return the names and MTUs of the members of the link with index `if_index` in `links`, ordered
+/// by index, if it is a bond or team with members, and otherwise the name and MTU of the link
+/// itself.
+fn bond_members(links: &[Link], if_index: i32) -> Option<Vec<&Link>> {
+    let link = links.iter().find(|link| link.index == if_index)?;
+    let mut members: Vec<&Link> = if link
+        .kind
+        .as_deref()
+        .is_some_and(|kind| AGGREGATE_KINDS.contains(&kind))
+    {
+        links
+            .iter()
+            .filter(|member| member.master == Some(if_index))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if members.is_empty() {
+        members.push(link);
+    }
+    members.sort_unstable_by_key(|member| member.index);
+    Some(members)
+}
+
+pub fn bond_member_mtus_impl(query: RouteQuery) -> Result<Vec<Interface>> {
+    let mut fd = query_socket()?;
+    let if_index = if_index(query, &mut fd, &mut reply_buf())?;
+    // Dump all links, since the kernel only filters dumps by master since Linux 4.19.
+    let links: Vec<Link> = dump(IfInfoMsg::dump, RTM_NEWLINK)?
+        .iter()
+        .filter_map(|msg| parse_link(msg))
+        .collect();
+    bond_members(&links, if_index)
+        .and_then(|members| members.into_iter().map(link_interface).collect())
+        .ok_or_else(|| route_if_name_err(if_index, &default_err()))
+}
+
+pub fn interface_index_impl(query: RouteQuery) -> Result<u32> {
+    let mut fd = query_socket()?;
+    u32::try_from(if_index(query, &mut fd, &mut reply_buf())?)
+        .map_err(|e: TryFromIntError| unlikely_err(e.to_string()))
+}
+
+/// Return the attributes of the link with index `if_index`.
+fn if_link(if_index: i32, fd: &mut RouteSocket, buf: &mut Vec<u8>) -> Result<Link> {
+    let msg_seq = fd.new_seq();
+    fd.write_all((&IfInfoMsg::new(if_index, msg_seq)).into())?;
+    let (_hdr, msg) = read_msg_with_seq(fd, buf, msg_seq, RTM_NEWLINK)?;
+    parse_link(&msg).ok_or_else(default_err)
+}
+
+/// Return the interface type of a link with `ARPHRD_*` type `hw_type`, if it has a conventional
+/// MTU.
+const fn link_type(hw_type: u16) -> Option<InterfaceType> {
+    match hw_type {
+        ARPHRD_ETHER => Some(InterfaceType::Ethernet),
+        ARPHRD_PPP => Some(InterfaceType::Pppoe),
+        ARPHRD_SIT => Some(InterfaceType::SixInFour),
+        ARPHRD_LOOPBACK => Some(InterfaceType::Loopback),
+        _ => None,
+    }
+}
+
+/// Return the peer address for `family` of `link`, if it is a point-to-point link and has one.
+fn link_peer(link: &Link, family: u8) -> Result<Option<IpAddr>> {
+    if link.flags & IFF_POINTOPOINT == 0 {
+        return Ok(None);
+    }
+    // The kernel only filters address dumps by interface for sockets with strict checking enabled.
+    let if_index = u32::try_from(link.index).map_err(|e| unlikely_err(e.to_string()))?;
+    Ok(dump(AddrDumpMsg::new, RTM_NEWADDR)?
+        .iter()
+        .find_map(|msg| parse_peer(msg, if_index, family)))
+}
+
+pub fn route_details_impl(query: RouteQuery) -> Result<RouteDetails> {
+    let mut fd = query_socket()?;
+    let mut buf = reply_buf();
+    let RouteReply {
+        if_index,
+        src,
+        gateway,
+        mtu,
+    } = route_reply(query, &mut fd, &mut buf)?;
+    // The link attributes are not part of the route reply, see `Resolver::interface_and_mtu`.
+    let link = if_link(if_index, &mut fd, &mut buf).map_err(|e| route_if_name_err(if_index, &e))?;
+    let interface =
+        link_interface(&link).ok_or_else(|| route_if_name_err(if_index, &default_err()))?;
+    let family = match query.remote {
+        IpAddr::V4(_) => AF_INET,
+        IpAddr::V6(_) => AF_INET6,
+    };
+    Ok(RouteDetails {
+        interface,
+        interface_type: link_type(link.hw_type),
+        route_mtu: mtu,
+        on_link: gateway.is_none(),
+        gateway,
+        source: src,
+        peer: link_peer(&link, family)?,
+    })
+}
+
+pub fn mtu_snapshot_impl() -> Result<HashMap<u32, usize>> {
+    Ok(dump(IfInfoMsg::dump, RTM_NEWLINK)?
+        .iter()
+        .filter_map(|msg| parse_link(msg))
+        .filter_map(|link| Some((u32::try_from(link.index).ok()?, link.mtu)))
+        .collect())
+}
+
+/// Return the interface described by `link`, with its speed, or `None` if its index is invalid.
+fn link_interface(link: &Link) -> Option<Interface> {
+    let mut interface = Interface {
+        index: InterfaceIndex(u32::try_from(link.index).ok()?),
+        name: link.name.clone(),
+        mtu: link.mtu,
+        mac: link.mac,
+        flags: InterfaceFlags::from_iff(u64::from(link.flags)),
+        kind: Some(link_kind(link.hw_type)),
+        speed_bps: None,
+    };
+    add_link_speed(&mut interface);
+    Some(interface)
+}
+
+pub fn interfaces_impl() -> Result<Vec<Interface>> {
+    let mut interfaces: Vec<_> = dump(IfInfoMsg::dump, RTM_NEWLINK)?
+        .iter()
+        .filter_map(|msg| parse_link(msg))
+        .filter_map(|link| link_interface(&link))
+        .collect();
+    interfaces.sort_unstable_by_key(|interface| interface.index);
+    Ok(interfaces)
+}
+
+/// A netlink socket for route queries, which is kept open between queries.
+#[derive(Debug)]
+pub struct Resolver {
+    fd: RouteSocket,
+    /// The buffer for replies, which is reused for all queries.
+    buf: Vec<u8>,
+}
+
+impl Resolver {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            fd: query_socket()?,
+            buf: reply_buf(),
+        })
+    }
+
+    pub fn interface_and_mtu(&mut self, query: RouteQuery) -> Result<Interface> {
+        // This takes two request/reply exchanges, since `RTM_GETLINK` needs the interface index
+        // from the `RTM_GETROUTE` reply. No kernel folds link attributes into an `RTM_GETROUTE`
+        // reply; the `RTEXT_FILTER_*` flags only apply to `RTM_GETLINK`. The requests don't set
+        // `NLM_F_ACK`, since the kernel reports errors regardless, and each acknowledgement costs
+        // an extra `read`.
+        let if_index = if_index(query, &mut self.fd, &mut self.buf)?;
+        if_interface(if_index, &mut self.fd, &mut self.buf)
+            .map_err(|e| route_if_name_err(if_index, &e))
+    }
+}
+
+pub fn interface_and_mtu_impl(query: RouteQuery) -> Result<Interface> {
+    Resolver::new()?.interface_and_mtu(query)
+}
+
+/// Like [`interface_and_mtu_impl`], but waiting for the replies on the Tokio reactor.
+#[cfg(feature = "tokio")]
+pub async fn interface_and_mtu_async_impl(query: RouteQuery) -> Result<Interface> {
+    let mut fd = AsyncRouteSocket::new(query_socket()?)?;
+    let mut buf = reply_buf();
+
+    let msg_seq = fd.new_seq();
+    let msg = IfIndexMsg::new(query, msg_seq);
+    fd.write_all((&msg).into())?;
+    let reply = read_msg_with_seq_async(&mut fd, &mut buf, msg_seq, RTM_NEWROUTE).await;
+    let if_index = parse_route_reply(query, msg.rtm.rtm_family, reply)?.if_index;
+
+    let msg_seq = fd.new_seq();
+    let msg = IfInfoMsg::new(if_index, msg_seq);
+    fd.write_all((&msg).into())?;
+    read_msg_with_seq_async(&mut fd, &mut buf, msg_seq, RTM_NEWLINK)
+        .await
+        .and_then(|(_hdr, buf)| parse_link_reply(if_index, buf))
+        .map(|mut interface| {
+            add_link_speed(&mut interface);
+            interface
+        })
+        .map_err(|e| route_if_name_err(if_index, &e))
+}
+
+pub fn interface_and_route_mtu_impl(query: RouteQuery) -> Result<(Interface, Option<usize>)> {
+    let mut fd = query_socket()?;
+    let mut buf = reply_buf();
+    let RouteReply { if_index, mtu, .. } = route_reply(query, &mut fd, &mut buf)?;
+    let interface =
+        if_interface(if_index, &mut fd, &mut buf).map_err(|e| route_if_name_err(if_index, &e))?;
+    Ok((interface, mtu))
+}
+
+pub fn source_and_mtu_via_impl(query: RouteQuery) -> Result<(IpAddr, usize)> {
+    let mut fd = query_socket()?;
+    let mut buf = reply_buf();
+    let RouteReply { if_index, src, .. } = route_reply(query, &mut fd, &mut buf)?;
+    let src = src.ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "No source address towards {} on interface {if_index}",
+                query.remote
+            ),
+        )
+    })?;
+    let (_, mtu) =
+        if_name_mtu(if_index, &mut fd, &mut buf).map_err(|e| route_if_name_err(if_index, &e))?;
+    Ok((src, mtu))
+}
+
+pub fn is_on_link_impl(query: RouteQuery) -> Result<bool> {
+    let mut fd = query_socket()?;
+    Ok(route_reply(query, &mut fd, &mut reply_buf())?
+        .gateway
+        .is_none())
+}
+
+pub fn interface_and_mtu_by_index_impl(index: u32) -> Result<(String, usize)> {
+    interface_by_index_impl(index).map(Into::into)
+}
+
+pub fn interface_by_index_impl(index: u32) -> Result<Interface> {
+    let index = i32::try_from(index).map_err(|_| default_err())?;
+    let mut fd = query_socket()?;
+    if_interface(index, &mut fd, &mut reply_buf())
+}
+
+pub fn family_mtus_impl(index: u32) -> Result<(Option<usize>, Option<usize>)> {
+    let index = i32::try_from(index).map_err(|_| default_err())?;
+    let mut fd = query_socket()?;
+    let msg_seq = fd.new_seq();
+    fd.write_all((&IfInfoMsg::new(index, msg_seq)).into())?;
+    let (_hdr, buf) = read_msg_with_seq(&mut fd, &mut reply_buf(), msg_seq, RTM_NEWLINK)?;
+    parse_family_mtus(&buf).ok_or_else(default_err)
+}
+
+/// Parse the IPv4 and IPv6 MTUs from the payload of an `RTM_NEWLINK` message. IPv4 uses the link
+/// MTU, while IPv6 has its own, which router advertisements can lower. A family is `None` if it is
+/// not enabled on the link. Returns `None` for malformed messages.
+fn parse_family_mtus(buf: &[u8]) -> Option<(Option<usize>, Option<usize>)> {
+    let mut mtu = None;
+    let mut has_inet = false;
+    let mut inet6_mtu = None;
+    for attr in RtAttrs(buf.get(std::mem::size_of::<ifinfomsg>()..)?) {
+        match attr.rta_type {
+            IFLA_MTU => mtu = usize::try_from(parse_c_int(attr.payload)?).ok(),
+            IFLA_AF_SPEC => {
+                for af in RtAttrs(attr.payload) {
+                    match u8::try_from(af.rta_type) {
+                        Ok(AF_INET) => has_inet = true,
+                        Ok(AF_INET6) => inet6_mtu = parse_inet6_mtu(af.payload),
+                        _ => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    Some((has_inet.then_some(mtu?), inet6_mtu))
+}
+
+/// Parse the IPv6 MTU from the `AF_INET6` attributes of a link, which hold the IPv6 configuration
+/// as an array of 32-bit values. Returns `None` if IPv6 is disabled on the link.
+fn parse_inet6_mtu(buf: &[u8]) -> Option<usize> {
+    let conf = RtAttrs(buf).find(|attr| attr.rta_type == IFLA_INET6_CONF)?;
+    let devconf = |i: usize| parse_c_int(conf.payload.get(i * std::mem::size_of::<c_int>()..)?);
+    if devconf(DEVCONF_DISABLE_IPV6)? != 0 {
+        return None;
+    }
+    usize::try_from(devconf(DEVCONF_MTU6)?).ok()
+}
+
+pub fn interface_stats_impl(index: u32) -> Result<InterfaceStats> {
+    let index = i32::try_from(index).map_err(|_| default_err())?;
+    let mut fd = query_socket()?;
+    let msg_seq = fd.new_seq();
+    fd.write_all((&IfInfoMsg::new(index, msg_seq)).into())?;
+    let (_hdr, buf) = read_msg_with_seq(&mut fd, &mut reply_buf(), msg_seq, RTM_NEWLINK)?;
+    parse_link_stats(&buf).ok_or_else(default_err)
+}
+
+/// Parse the byte and packet counters from the `IFLA_STATS64` attribute in the payload of an
+/// `RTM_NEWLINK` message. Its `struct rtnl_link_stats64` starts with the received and sent packet
+/// counts, followed by the received and sent byte counts. Returns `None` if the attribute is
+/// missing or malformed.
+fn parse_link_stats(buf: &[u8]) -> Option<InterfaceStats> {
+    let attr = RtAttrs(buf.get(std::mem::size_of::<ifinfomsg>()..)?)
+        .find(|attr| attr.rta_type == IFLA_STATS64)?;
+    let mut fields = NeFields(attr.payload);
+    let rx_packets = fields.u64()?;
+    let tx_packets = fields.u64()?;
+    Some(InterfaceStats {
+        rx_bytes: fields.u64()?,
+        tx_bytes: fields.u64()?,
+        rx_packets,
+        tx_packets,
+    })
+}
+
+pub fn routes_impl() -> Result<Vec<RouteEntry>> {
+    Ok(dump(RouteDumpMsg::new, RTM_NEWROUTE)?
+        .iter()
+        .filter_map(|msg| parse_route(msg))
+        .collect())
+}
+
+/// A route of a routing table that covers the destination of a table lookup.
+#[derive(Debug, PartialEq, Eq)]
+struct TableRoute {
+    prefix_len: u8,
+    /// The metric of the route. Lower metrics are preferred.
+    priority: u32,
+    /// The index of the outgoing interface, or `None` for routes that do not forward traffic, such
+    /// as unreachable and blackhole routes.
+    oif: Option<u32>,
+}
+
+/// Parse `buf`, a message of a route dump, as a route of the table with ID `table` that covers
+/// `remote`, or return `None` if it is not one.
+fn parse_table_route(buf: &[u8], table: u32, remote: IpAddr) -> Option<TableRoute> {
+    let rtm = rtmsg::parse(buf)?;
+    let route = parse_route(buf)?;
+    // Table IDs above 255 are only carried by `RTA_TABLE`.
+    let mut route_table = u32::from(rtm.rtm_table);
+    let mut priority = 0;
+    for attr in RtAttrs(&buf[std::mem::size_of::<rtmsg>()..]) {
+        #[allow(clippy::cast_sign_loss)] // Both are `u32` in the kernel.
+        match attr.rta_type {
+            RTA_TABLE => route_table = parse_c_int(attr.payload)? as u32,
+            RTA_PRIORITY => priority = parse_c_int(attr.payload)? as u32,
+            _ => (),
+        }
+    }
+    if route_table != table
+        || route.destination.is_ipv4() != remote.is_ipv4()
+        || network_address(remote, route.prefix_len) != route.destination
+    {
+        return None;
+    }
+    let oif = match rtm.rtm_type {
+        RTN_UNICAST | RTN_LOCAL => Some(route.oif_index.0).filter(|&oif| oif != 0),
+        RTN_UNREACHABLE | RTN_PROHIBIT | RTN_BLACKHOLE | RTN_THROW => None,
+        // Broadcast, multicast and other routes don't apply to unicast destinations.
+        _ => return None,
+    };
+    Some(TableRoute {
+        prefix_len: route.prefix_len,
+        priority,
+        oif,
+    })
+}
+
+/// Return the route that the kernel would select towards `remote` among `routes`: the most specific
+/// one, and among those the one with the lowest metric.
+fn best_table_route(routes: impl Iterator<Item = TableRoute>) -> Option<TableRoute> {
+    routes.min_by_key(|route| (std::cmp::Reverse(route.prefix_len), route.priority))
+}
+
+pub fn interface_and_mtu_in_table_impl(remote: IpAddr, table: u32) -> Result<Interface> {
+    // `RTM_GETROUTE` requests always follow the routing policy rules and ignore `rtm_table` and
+    // `RTA_TABLE`, so look for the route in a dump instead.
+    let routes = dump(RouteDumpMsg::new, RTM_NEWROUTE)?;
+    let if_index = best_table_route(
+        routes
+            .iter()
+            .filter_map(|msg| parse_table_route(msg, table, remote)),
+    )
+    .and_then(|route| route.oif)
+    .and_then(|oif| i32::try_from(oif).ok())
+    .ok_or_else(|| {
+        no_route_err(format!(
+            "Destination {remote} not reachable in table {table}"
+        ))
+    })?;
+    let mut fd = query_socket()?;
+    if_interface(if_index, &mut fd, &mut reply_buf()).map_err(|e| route_if_name_err(if_index, &e))
+}
+
+/// Return the payload of the reply to a route query for `query` that asks for the matching routing
+/// table entry, which lists all next hops and has the prefix of the route, instead of the route the
+/// kernel selected for this particular destination.
+fn fib_match_reply(query: RouteQuery) -> Result<Vec<u8>> {
+    let mut fd = query_socket()?;
+    let msg_seq = fd.new_seq();
+    let mut msg = IfIndexMsg::new(query, msg_seq);
+    msg.rtm.rtm_flags = RTM_F_FIB_MATCH;
+    fd.write_all((&msg).into())?;
+
+    let (_hdr, buf) = read_msg_with_seq(&mut fd, &mut reply_buf(), msg_seq, RTM_NEWROUTE)?;
+    check_rtm_family(&buf, msg.rtm.rtm_family, STRICT)?;
+    Ok(buf)
+}
+
+pub fn is_route_ambiguous_impl(query: RouteQuery) -> Result<bool> {
+    let buf = fib_match_reply(query)?;
+    let attrs = buf
+        .get(std::mem::size_of::<rtmsg>()..)
+        .ok_or_else(|| inconsistent_reply_err("Truncated route reply".to_string()))?;
+    Ok(
+        RtAttrs(attrs)
+            .any(|attr| attr.rta_type == RTA_MULTIPATH && nexthop_count(attr.payload) > 1),
+    )
+}
+
+pub fn route_prefix_len_impl(query: RouteQuery) -> Result<Option<u8>> {
+    Ok(rtmsg::parse(&fib_match_reply(query)?).map(|rtm| rtm.rtm_dst_len))
+}
+
+/// Return the source address that the kernel selects towards `remote` for a socket with the
+/// `IPV6_ADDR_PREFERENCES` option corresponding to `preference`.
+pub fn preferred_source(remote: Ipv6Addr, preference: SourcePreference) -> Result<Ipv6Addr> {
+    let preference: c_int = match preference {
+        SourcePreference::Temporary => libc::IPV6_PREFER_SRC_TMP,
+        SourcePreference::Stable => libc::IPV6_PREFER_SRC_PUBLIC,
+        SourcePreference::Cga => libc::IPV6_PREFER_SRC_CGA,
+    };
+    let socket = UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))?;
+    if unsafe {
+        setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IPV6,
+            libc::IPV6_ADDR_PREFERENCES,
+            ptr::from_ref(&preference).cast(),
+            #[allow(clippy::cast_possible_truncation)] // The size of a `c_int` fits.
+            {
+                std::mem::size_of::<c_int>() as socklen_t
+            },
+        )
+    } == -1
+    {
+        return Err(Error::last_os_error());
+    }
+    // Connecting a UDP socket does not send anything, but makes the kernel select a source address.
+    // The port does not matter.
+    socket.connect((remote, 9))?;
+    match socket.local_addr()?.ip() {
+        IpAddr::V6(src) => Ok(src),
+        IpAddr::V4(_) => Err(unlikely_err("IPv6 socket has an IPv4 address".to_string())),
+    }
+}
+
+/// Return the index of the device that the socket `fd` is bound to via `SO_BINDTODEVICE`, if any.
+pub fn bound_device_index(fd: BorrowedFd<'_>) -> Result<Option<u32>> {
+    let mut name: [c_char; libc::IF_NAMESIZE] = [0; libc::IF_NAMESIZE];
+    #[allow(clippy::cast_possible_truncation)] // `IF_NAMESIZE` is 16.
+    let mut len = name.len() as socklen_t;
+    if unsafe {
+        getsockopt(
+            fd.as_raw_fd(),
+            SOL_SOCKET,
+            SO_BINDTODEVICE,
+            name.as_mut_ptr().cast(),
+            ptr::from_mut(&mut len),
+        )
+    } == -1
+    {
+        return Err(Error::last_os_error());
+    }
+    if len == 0 || name[0] == 0 {
+        return Ok(None);
+    }
+    match unsafe { if_nametoindex(name.as_ptr()) } {
+        0 => Err(Error::last_os_error()),
+        index => Ok(Some(index)),
+    }
+}
+
+/// Return the path MTU that the kernel has cached for the connected socket `fd`, via `IP_MTU` or,
+/// for an IPv6 socket, `IPV6_MTU`.
+pub fn path_mtu(fd: BorrowedFd<'_>, ipv6: bool) -> Result<usize> {
+    let (level, name) = if ipv6 {
+        (IPPROTO_IPV6, IPV6_MTU)
+    } else {
+        (IPPROTO_IP, IP_MTU)
+    };
+    let mut mtu: c_int = 0;
+    #[allow(clippy::cast_possible_truncation)] // The size of a `c_int` fits.
+    let mut len = std::mem::size_of::<c_int>() as socklen_t;
+    if unsafe {
+        getsockopt(
+            fd.as_raw_fd(),
+            level,
+            name,
+            ptr::from_mut(&mut mtu).cast(),
+            ptr::from_mut(&mut len),
+        )
+    } == -1
+    {
+        return Err(Error::last_os_error());
+    }
+    usize::try_from(mtu).map_err(|_| default_err())
+}
+
+/// Open a netlink socket subscribed to link and route change notifications.
+fn change_socket() -> Result<RouteSocket> {
+    let fd = RouteSocket::new(AF_NETLINK, NETLINK_ROUTE)?;
+    let mut addr: sockaddr_nl = unsafe { std::mem::zeroed() };
+    addr.nl_family = AF_NETLINK_FAMILY;
+    addr.nl_groups = RTMGRP_CHANGES;
+    #[allow(clippy::cast_possible_truncation)] // `sockaddr_nl` is 12 bytes.
+    let len = std::mem::size_of::<sockaddr_nl>() as libc::socklen_t;
+    if unsafe { bind(fd.as_raw_fd(), ptr::from_ref(&addr).cast::<sockaddr>(), len) } == -1 {
+        return Err(Error::last_os_error());
+    }
+    Ok(fd)
+}
+
+/// A netlink socket subscribed to link and route change notifications.
+#[derive(Debug)]
+pub struct Watcher {
+    fd: RouteSocket,
+    /// The events of a received datagram that have not been returned yet.
+    pending: VecDeque<ChangeEvent>,
+}
+
+impl Watcher {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            fd: change_socket()?,
+            pending: VecDeque::new(),
+        })
+    }
+
+    pub fn recv(&mut self) -> Result<ChangeEvent> {
+        let mut buf = [0u8; NETLINK_BUFFER_SIZE];
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+            match self.fd.read(&mut buf) {
+                Ok(len) => self.pending.extend(parse_changes(&buf[..len])),
+                // The kernel dropped notifications, so any interface may have changed.
+                Err(e) if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                    return Ok(ChangeEvent::UNKNOWN);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<ChangeEvent>> {
+        self.fd.set_read_timeout(Some(timeout))?;
+        let res = self.recv();
+        self.fd.set_read_timeout(None)?;
+        match res {
+            Ok(event) => Ok(Some(event)),
+            // The read timeout expired.
+            Err(e) if e.kind() == ErrorKind::TimedOut => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Parse the link and route notifications in `buf` into change events, skipping malformed and
+/// other messages.
+fn parse_changes(buf: &[u8]) -> Vec<ChangeEvent> {
+    let mut events = Vec::new();
+    let mut next = buf;
+    while let Ok((hdr, msg, rest)) = split_nlmsg(next) {
+        next = rest;
+        let event = match hdr.nlmsg_type {
+            RTM_NEWLINK => parse_link(msg).map(|link| ChangeEvent {
+                index: u32::try_from(link.index).ok().map(InterfaceIndex),
+                mtu: Some(link.mtu),
+            }),
+            RTM_DELLINK => ifinfomsg::parse(msg).map(|ifim| ChangeEvent {
+                index: u32::try_from(ifim.ifi_index).ok().map(InterfaceIndex),
+                mtu: None,
+            }),
+            RTM_NEWROUTE | RTM_DELROUTE => parse_route(msg).map(|route| ChangeEvent {
+                index: (route.oif_index.0 != 0).then_some(route.oif_index),
+                mtu: None,
+            }),
+            _ => None,
+        };
+        events.extend(event);
+    }
+    events
+}
+
+pub fn change_generation_impl() -> Result<u64> {
+    // Only multicast notifications are delivered to this socket, so every message is a change.
+    routesocket::change_generation(change_socket, |_| true)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{Error, ErrorKind, Write as _},
+        net::{IpAddr, Ipv4Addr, Ipv6Addr},
+        ptr, slice,
+        time::Duration,
+    };
+
+    use libc::{
+        AF_NETLINK, ARPHRD_ETHER, ARPHRD_IEEE80211, ARPHRD_LOOPBACK, ARPHRD_NONE, ARPHRD_PPP,
+        IFA_ADDRESS, IFA_LOCAL, IFLA_IFNAME, IFLA_MTU, IFLA_STATS64, NETLINK_ROUTE, RTA_DST,
+        RTA_OIF, RTA_SRC,
+    };
+
+    use super::{
+        best_table_route, bond_members, check_rtm_family, collect_dump_msgs, find_msg_with_seq,
+        if_index, if_name_mtu, ifaddrmsg, ifinfomsg, link_kind, nlmsghdr, parse_addr,
+        parse_changes, parse_family_mtus, parse_link, parse_link_stats, parse_peer,
+        parse_sysfs_speed, parse_table_route, read_dump_with_seq, read_msg_with_seq, reply_buf,
+        retry_dump, rtmsg, rtnexthop, set_rcvbuf, FromNeFields, IfInfoMsg, Link, TableRoute,
+        AF_INET, AF_INET6, DEVCONF_DISABLE_IPV6, DEVCONF_MTU6, DUMP_RETRIES, DUMP_RETRY_RCVBUF,
+        IFLA_AF_SPEC, IFLA_INET6_CONF, NLMSG_DONE, NLMSG_ERROR, NLMSG_NOOP, NLMSG_OVERRUN,
+        NLM_F_MULTI, RTA_PRIORITY, RTA_TABLE, RTM_DELLINK, RTM_NEWLINK, RTM_NEWROUTE,
+        RTN_BLACKHOLE, RTN_UNICAST,
+    };
+    use crate::{
+        linux::IfIndexMsg,
+        parse::{RtAttrs, RTA_HDR_LEN},
+        route_if_name_err,
+        routesocket::RouteSocket,
+        ChangeEvent, InterfaceIndex, InterfaceKind, RouteQuery,
+    };
+
+    /// Check that parsing `T` from distinct bytes yields a struct with exactly those bytes, i.e.,
+    /// that its fields are read in layout order, and that a truncated buffer does not parse.
+    fn assert_parses_layout<T: FromNeFields>() {
+        let len = std::mem::size_of::<T>();
+        let buf: Vec<u8> = (1..=u8::try_from(len).unwrap()).collect();
+        let parsed = T::parse(&buf).unwrap();
+        let bytes = unsafe { slice::from_raw_parts(ptr::from_ref(&parsed).cast::<u8>(), len) };
+        assert_eq!(bytes, buf);
+        assert!(T::parse(&buf[..len - 1]).is_none());
+    }
+
+    #[test]
+    fn headers_are_parsed_by_field() {
+        assert_parses_layout::<nlmsghdr>();
+        assert_parses_layout::<rtmsg>();
+        assert_parses_layout::<rtnexthop>();
+        assert_parses_layout::<ifinfomsg>();
+        assert_parses_layout::<ifaddrmsg>();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "Miri does not support Netlink sockets")]
+    fn separate_sockets() {
+        let mut fd = RouteSocket::new(AF_NETLINK, NETLINK_ROUTE).unwrap();
+        let mut other = RouteSocket::new(AF_NETLINK, NETLINK_ROUTE).unwrap();
+        let mut buf = reply_buf();
+        let v4 = if_index(IpAddr::V4(Ipv4Addr::LOCALHOST).into(), &mut fd, &mut buf).unwrap();
+        let v6 = if_index(IpAddr::V6(Ipv6Addr::LOCALHOST).into(), &mut other, &mut buf).unwrap();
+        assert_eq!(v4, v6);
+        let (name, _) = if_name_mtu(v4, &mut other, &mut buf).unwrap();
+        assert_eq!(if_name_mtu(v6, &mut fd, &mut buf).unwrap().0, name);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "Miri does not support Netlink sockets")]
+    fn read_times_out() {
+        let mut fd = RouteSocket::new(AF_NETLINK, NETLINK_ROUTE).unwrap();
+        assert_eq!(
+            fd.set_read_timeout(Some(Duration::ZERO))
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidInput
+        );
+        fd.set_read_timeout(Some(Duration::from_millis(1))).unwrap();
+        // Nothing was sent, so there is no reply.
+        let seq = fd.new_seq();
+        let mut buf = reply_buf();
+        assert_eq!(
+            read_msg_with_seq(&mut fd, &mut buf, seq, RTM_NEWROUTE)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::TimedOut
+        );
+        // The socket remains usable.
+        if_index(IpAddr::V4(Ipv4Addr::LOCALHOST).into(), &mut fd, &mut buf).unwrap();
+    }
+
+    /// Serialize a Netlink message of type `kind` with sequence number `seq` and `payload`.
+    fn nlmsg(kind: u16, seq: u32, payload: &[u8]) -> Vec<u8> {
+        nlmsg_with_flags(kind, 0, seq, payload)
+    }
+
+    fn nlmsg_with_flags(kind: u16, flags: u16, seq: u32, payload: &[u8]) -> Vec<u8> {
+        let hdr = nlmsghdr {
+            nlmsg_len: u32::try_from(std::mem::size_of::<nlmsghdr>() + payload.len()).unwrap(),
+            nlmsg_type: kind,
+            nlmsg_flags: flags,
+            nlmsg_seq: seq,
+            nlmsg_pid: 0,
+        };
+        // SAFETY: `nlmsghdr` is a plain C struct without padding.
+        let hdr = unsafe {
+            std::slice::from_raw_parts(
+                std::ptr::addr_of!(hdr).cast::<u8>(),
+                std::mem::size_of::<nlmsghdr>(),
+            )
+        };
+        [hdr, payload].concat()
+    }
+
+    /// Serialize a route attribute of type `kind` with `payload`, without padding.
+    fn rtattr(kind: u16, payload: &[u8]) -> Vec<u8> {
+        let len = u16::try_from(RTA_HDR_LEN + payload.len()).unwrap();
+        [&len.to_ne_bytes()[..], &kind.to_ne_bytes(), payload].concat()
+    }
+
+    #[test]
+    fn noop_is_skipped() {
+        let buf = [
+            nlmsg(NLMSG_NOOP, 1, &[]),
+            nlmsg(RTM_NEWROUTE, 2, &[0xaa; 4]),
+            nlmsg(NLMSG_NOOP, 1, &[0; 4]),
+            nlmsg(RTM_NEWROUTE, 1, &[0xbb; 4]),
+        ]
+        .concat();
+        let (hdr, msg) = find_msg_with_seq(&buf, 1, RTM_NEWROUTE, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(hdr.nlmsg_seq, 1);
+        assert_eq!(msg, [0xbb; 4]);
+    }
+
+    #[test]
+    fn noop_only_needs_more_data() {
+        let buf = [nlmsg(NLMSG_NOOP, 1, &[]), nlmsg(NLMSG_NOOP, 1, &[])].concat();
+        assert!(find_msg_with_seq(&buf, 1, RTM_NEWROUTE, false)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn overrun_is_error() {
+        let buf = [
+            nlmsg(NLMSG_NOOP, 1, &[]),
+            nlmsg(NLMSG_OVERRUN, 0, &[]),
+            nlmsg(RTM_NEWROUTE, 1, &[0xbb; 4]),
+        ]
+        .concat();
+        assert!(find_msg_with_seq(&buf, 1, RTM_NEWROUTE, false).is_err());
+    }
+
+    #[test]
+    fn dump_is_collected_until_done() {
+        let mut msgs = Vec::new();
+        let buf = [
+            nlmsg_with_flags(RTM_NEWROUTE, NLM_F_MULTI, 1, &[0xaa; 4]),
+            nlmsg_with_flags(RTM_NEWROUTE, NLM_F_MULTI, 2, &[0xcc; 4]),
+            nlmsg_with_flags(RTM_NEWROUTE, NLM_F_MULTI, 1, &[0xbb; 4]),
+        ]
+        .concat();
+        assert!(!collect_dump_msgs(&buf, 1, RTM_NEWROUTE, true, &mut msgs).unwrap());
+        let buf = nlmsg_with_flags(NLMSG_DONE, NLM_F_MULTI, 1, &0i32.to_ne_bytes());
+        assert!(collect_dump_msgs(&buf, 1, RTM_NEWROUTE, true, &mut msgs).unwrap());
+        assert_eq!(msgs, [[0xaa; 4], [0xbb; 4]]);
+    }
+
+    #[test]
+    fn single_part_reply_ends_dump() {
+        let mut msgs = Vec::new();
+        let buf = [
+            nlmsg(RTM_NEWROUTE, 1, &[0xaa; 4]),
+            nlmsg(RTM_NEWROUTE, 1, &[0xbb; 4]),
+        ]
+        .concat();
+        assert!(collect_dump_msgs(&buf, 1, RTM_NEWROUTE, true, &mut msgs).unwrap());
+        assert_eq!(msgs, [[0xaa; 4]]);
+    }
+
+    #[test]
+    fn multipart_reply_is_parsed() {
+        // The parts of a multipart reply may be split across datagrams.
+        let buf = [
+            nlmsg_with_flags(RTM_NEWLINK, NLM_F_MULTI, 1, &[0xaa; 4]),
+            nlmsg_with_flags(RTM_NEWROUTE, NLM_F_MULTI, 2, &[0xbb; 4]),
+        ]
+        .concat();
+        assert!(find_msg_with_seq(&buf, 1, RTM_NEWROUTE, true)
+            .unwrap()
+            .is_none());
+        let buf = [
+            nlmsg_with_flags(RTM_NEWROUTE, NLM_F_MULTI, 1, &[0xcc; 4]),
+            nlmsg_with_flags(NLMSG_DONE, NLM_F_MULTI, 1, &0i32.to_ne_bytes()),
+        ]
+        .concat();
+        let (hdr, msg) = find_msg_with_seq(&buf, 1, RTM_NEWROUTE, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(hdr.nlmsg_flags, NLM_F_MULTI);
+        assert_eq!(msg, [0xcc; 4]);
+
+        // A reply that ends without a matching message is an error, rather than a reason to wait.
+        let buf = [
+            nlmsg_with_flags(RTM_NEWLINK, NLM_F_MULTI, 1, &[0xaa; 4]),
+            nlmsg_with_flags(NLMSG_DONE, NLM_F_MULTI, 1, &0i32.to_ne_bytes()),
+        ]
+        .concat();
+        assert!(find_msg_with_seq(&buf, 1, RTM_NEWROUTE, true).is_err());
+    }
+
+    #[test]
+    fn link_kinds() {
+        assert_eq!(link_kind(ARPHRD_LOOPBACK), InterfaceKind::Loopback);
+        assert_eq!(link_kind(ARPHRD_ETHER), InterfaceKind::Ethernet);
+        assert_eq!(link_kind(ARPHRD_IEEE80211), InterfaceKind::WiFi);
+        assert_eq!(link_kind(super::ARPHRD_RAWIP), InterfaceKind::Cellular);
+        assert_eq!(link_kind(ARPHRD_NONE), InterfaceKind::Tunnel);
+        assert_eq!(
+            link_kind(ARPHRD_PPP),
+            InterfaceKind::Other(ARPHRD_PPP.into())
+        );
+    }
+
+    #[test]
+    fn sysfs_speeds() {
+        assert_eq!(parse_sysfs_speed("1000\n"), Some(1_000_000_000));
+        assert_eq!(parse_sysfs_speed("-1\n"), None);
+        assert_eq!(parse_sysfs_speed("0\n"), None);
+        assert_eq!(parse_sysfs_speed(""), None);
+    }
+
+    #[test]
+    fn bond_members_are_listed() {
+        let link = |index: i32, master: Option<i32>, kind: Option<&str>| Link {
+            index,
+            name: format!("link{index}"),
+            mtu: 1500 - usize::try_from(index).unwrap(),
+            hw_type: ARPHRD_ETHER,
+            mac: None,
+            flags: 0,
+            master,
+            kind: kind.map(ToString::to_string),
+        };
+        let links = [
+            link(1, None, None),
+            link(4, Some(2), None),
+            link(2, None, Some("bond")),
+            link(3, Some(2), None),
+            link(5, None, Some("team")),
+            link(6, Some(7), None),
+            link(7, None, Some("bridge")),
+        ];
+        let members = |if_index| {
+            bond_members(&links, if_index).map(|members| {
+                members
+                    .iter()
+                    .map(|member| (member.name.as_str(), member.mtu))
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(members(2).unwrap(), [("link3", 1497), ("link4", 1496)]);
+        // A team without members, a bridge and a plain link are reported themselves.
+        assert_eq!(members(5).unwrap(), [("link5", 1495)]);
+        assert_eq!(members(7).unwrap(), [("link7", 1493)]);
+        assert_eq!(members(1).unwrap(), [("link1", 1499)]);
+        assert!(members(8).is_none());
+    }
+
+    #[test]
+    fn non_utf8_name_is_lossy() {
+        let msg = [
+            &[0; std::mem::size_of::<ifinfomsg>()][..],
+            &rtattr(IFLA_IFNAME, b"eth\xff0\0\0\0"),
+            &rtattr(IFLA_MTU, &1500i32.to_ne_bytes()),
+        ]
+        .concat();
+        let link = parse_link(&msg).unwrap();
+        assert_eq!(link.name, "eth\u{fffd}0");
+        assert_eq!(link.mtu, 1500);
+    }
+
+    #[test]
+    fn family_mtus_are_parsed() {
+        let inet6 = |mtu: i32, disabled: i32| {
+            let mut conf = [0i32; DEVCONF_DISABLE_IPV6 + 1];
+            conf[DEVCONF_MTU6] = mtu;
+            conf[DEVCONF_DISABLE_IPV6] = disabled;
+            let conf: Vec<u8> = conf.iter().flat_map(|v| v.to_ne_bytes()).collect();
+            rtattr(AF_INET6.into(), &rtattr(IFLA_INET6_CONF, &conf))
+        };
+        let msg = |af_spec: &[&[u8]]| {
+            [
+                &[0; std::mem::size_of::<ifinfomsg>()][..],
+                &rtattr(IFLA_MTU, &1500i32.to_ne_bytes()),
+                &rtattr(IFLA_AF_SPEC, &af_spec.concat()),
+            ]
+            .concat()
+        };
+        let inet = rtattr(AF_INET.into(), &[]);
+        assert_eq!(
+            parse_family_mtus(&msg(&[&inet, &inet6(1280, 0)])),
+            Some((Some(1500), Some(1280)))
+        );
+        assert_eq!(
+            parse_family_mtus(&msg(&[&inet, &inet6(1280, 1)])),
+            Some((Some(1500), None))
+        );
+        assert_eq!(
+            parse_family_mtus(&msg(&[&inet6(1500, 0)])),
+            Some((None, Some(1500)))
+        );
+        assert_eq!(parse_family_mtus(&msg(&[])), Some((None, None)));
+        assert_eq!(parse_family_mtus(&[0; 4]), None);
+    }
+
+    #[test]
+    fn link_stats_are_parsed() {
+        let msg = |attrs: &[&[u8]]| {
+            [&[0; std::mem::size_of::<ifinfomsg>()][..], &attrs.concat()].concat()
+        };
+        // `struct rtnl_link_stats64` has more counters after the first four.
+        let stats: Vec<u8> = [1u64, 2, 3, 4, 5, 6]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect();
+        let parsed = parse_link_stats(&msg(&[
+            &rtattr(IFLA_MTU, &1500i32.to_ne_bytes()),
+            &rtattr(IFLA_STATS64, &stats),
+        ]))
+        .unwrap();
+        assert_eq!(
+            (
+                parsed.rx_packets,
+                parsed.tx_packets,
+                parsed.rx_bytes,
+                parsed.tx_bytes
+            ),
+            (1, 2, 3, 4)
+        );
+        assert_eq!(
+            parse_link_stats(&msg(&[&rtattr(IFLA_STATS64, &stats[..24])])),
+            None
+        );
+        assert_eq!(parse_link_stats(&msg(&[])), None);
+    }
+
+    #[test]
+    fn changes_are_parsed() {
+        let ifim = |index: i32| {
+            let mut ifim = [0; std::mem::size_of::<ifinfomsg>()];
+            ifim[4..8].copy_from_slice(&index.to_ne_bytes());
+            ifim
+        };
+        let link = [
+            &ifim(2)[..],
+            &rtattr(IFLA_IFNAME, b"eth0\0\0\0\0"),
+            &rtattr(IFLA_MTU, &1400i32.to_ne_bytes()),
+        ]
+        .concat();
+        let route = [
+            &[AF_INET, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0][..],
+            &rtattr(RTA_OIF, &3i32.to_ne_bytes()),
+        ]
+        .concat();
+        let buf = [
+            nlmsg(RTM_NEWLINK, 0, &link),
+            nlmsg(NLMSG_NOOP, 0, &[]),
+            nlmsg(RTM_DELLINK, 0, &ifim(4)),
+            nlmsg(RTM_NEWROUTE, 0, &route),
+            // Truncated messages end the parsing.
+            nlmsg(RTM_DELLINK, 0, &ifim(5))[..20].to_vec(),
+        ]
+        .concat();
+        assert_eq!(
+            parse_changes(&buf),
+            [
+                ChangeEvent {
+                    index: Some(InterfaceIndex(2)),
+                    mtu: Some(1400)
+                },
+                ChangeEvent {
+                    index: Some(InterfaceIndex(4)),
+                    mtu: None
+                },
+                ChangeEvent {
+                    index: Some(InterfaceIndex(3)),
+                    mtu: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn peer_is_parsed() {
+        let msg = |index: u32, local: Option<[u8; 4]>, address: [u8; 4]| {
+            let ifa = [AF_INET, 32, 0, 0];
+            let local = local.map(|local| rtattr(IFA_LOCAL, &local));
+            [
+                &ifa[..],
+                &index.to_ne_bytes(),
+                &local.unwrap_or_default(),
+                &rtattr(IFA_ADDRESS, &address),
+            ]
+            .concat()
+        };
+        let peer = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 2));
+        let p2p = msg(2, Some([198, 51, 100, 1]), [198, 51, 100, 2]);
+        assert_eq!(parse_peer(&p2p, 2, AF_INET), Some(peer));
+        // Other interfaces and families are skipped.
+        assert_eq!(parse_peer(&p2p, 3, AF_INET), None);
+        assert_eq!(parse_peer(&p2p, 2, AF_INET6), None);
+        // Without a distinct local address, there is no peer.
+        let local = msg(2, Some([192, 0, 2, 2]), [192, 0, 2, 2]);
+        assert_eq!(parse_peer(&local, 2, AF_INET), None);
+        assert_eq!(parse_peer(&msg(2, None, [192, 0, 2, 2]), 2, AF_INET), None);
+        assert_eq!(parse_peer(&p2p[..4], 2, AF_INET), None);
+        // The local address is `IFA_LOCAL` if present, and `IFA_ADDRESS` otherwise.
+        let local_addr = |msg: &[u8]| parse_addr(msg).unwrap().local();
+        assert_eq!(
+            local_addr(&p2p),
+            Some(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1)))
+        );
+        assert_eq!(
+            local_addr(&msg(2, None, [192, 0, 2, 2])),
+            Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)))
+        );
+    }
+
+    #[test]
+    fn dump_is_retried_on_enobufs() {
+        let mut rcvbufs = Vec::new();
+        let res = retry_dump(|rcvbuf| {
+            rcvbufs.push(rcvbuf);
+            if rcvbufs.len() < 3 {
+                Err(Error::from_raw_os_error(libc::ENOBUFS))
+            } else {
+                Ok(vec![vec![0xaa; 4]])
+            }
+        });
+        assert_eq!(res.unwrap(), [[0xaa; 4]]);
+        assert_eq!(
+            rcvbufs,
+            [None, Some(DUMP_RETRY_RCVBUF), Some(2 * DUMP_RETRY_RCVBUF)]
+        );
+    }
+
+    #[test]
+    fn dump_retries_are_bounded() {
+        let mut attempts = 0;
+        let res = retry_dump(|_| {
+            attempts += 1;
+            Err(Error::from_raw_os_error(libc::ENOBUFS))
+        });
+        assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::ENOBUFS));
+        assert_eq!(attempts, DUMP_RETRIES + 1);
+    }
+
+    #[test]
+    fn other_dump_errors_are_not_retried() {
+        let mut attempts = 0;
+        let res = retry_dump(|_| {
+            attempts += 1;
+            Err(Error::from_raw_os_error(libc::EPERM))
+        });
+        assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::EPERM));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "Miri does not support Netlink sockets")]
+    fn dump_with_rcvbuf() {
+        let mut fd = RouteSocket::new(AF_NETLINK, NETLINK_ROUTE).unwrap();
+        set_rcvbuf(&fd, DUMP_RETRY_RCVBUF).unwrap();
+        let msg_seq = fd.new_seq();
+        fd.write_all((&IfInfoMsg::dump(msg_seq)).into()).unwrap();
+        let links = read_dump_with_seq(&mut fd, msg_seq, RTM_NEWLINK).unwrap();
+        assert!(links
+            .iter()
+            .filter_map(|msg| parse_link(msg))
+            .any(|link| link.name == "lo"));
+    }
+
+    #[test]
+    fn error_is_returned() {
+        let buf = nlmsg(NLMSG_ERROR, 1, &(-libc::ENETUNREACH).to_ne_bytes());
+        let err = find_msg_with_seq(&buf, 1, RTM_NEWROUTE, false).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENETUNREACH));
+    }
+
+    #[test]
+    fn table_routes_are_selected() {
+        let route =
+            |dst: [u8; 4], dst_len: u8, rtm_type: u8, table: u32, priority: u32, oif: i32| {
+                [
+                    &[AF_INET, dst_len, 0, 0, 0, 0, 0, rtm_type, 0, 0, 0, 0][..],
+                    &rtattr(RTA_TABLE, &table.to_ne_bytes()),
+                    &rtattr(RTA_DST, &dst),
+                    &rtattr(RTA_PRIORITY, &priority.to_ne_bytes()),
+                    &rtattr(RTA_OIF, &oif.to_ne_bytes()),
+                ]
+                .concat()
+            };
+        let routes = [
+            route([0, 0, 0, 0], 0, RTN_UNICAST, 1000, 0, 2),
+            route([203, 0, 113, 0], 24, RTN_UNICAST, 1000, 200, 3),
+            route([203, 0, 113, 0], 24, RTN_UNICAST, 1000, 100, 4),
+            // More specific, but in another table.
+            route([203, 0, 113, 0], 25, RTN_UNICAST, 254, 0, 5),
+            route([198, 51, 100, 0], 24, RTN_BLACKHOLE, 1000, 0, 0),
+        ];
+        let best = |remote: Ipv4Addr| {
+            best_table_route(
+                routes
+                    .iter()
+                    .filter_map(|msg| parse_table_route(msg, 1000, IpAddr::V4(remote))),
+            )
+        };
+        assert_eq!(
+            best(Ipv4Addr::new(203, 0, 113, 1)),
+            Some(TableRoute {
+                prefix_len: 24,
+                priority: 100,
+                oif: Some(4)
+            })
+        );
+        assert_eq!(best(Ipv4Addr::new(192, 0, 2, 1)).unwrap().oif, Some(2));
+        assert_eq!(best(Ipv4Addr::new(198, 51, 100, 1)).unwrap().oif, None);
+        assert!(parse_table_route(&routes[0], 1000, IpAddr::V6(Ipv6Addr::LOCALHOST)).is_none());
+    }
+
+    #[test]
+    fn traffic_class_sets_tos() {
+        let query = RouteQuery {
+            flowinfo: 0x0b80_1234,
+            ..IpAddr::V6(Ipv6Addr::LOCALHOST).into()
+        };
+        assert_eq!(IfIndexMsg::new(query, 0).rtm.rtm_tos, 0xb8);
+    }
+
+    #[test]
+    fn traffic_class_ignored_for_v4() {
+        let query = RouteQuery {
+            flowinfo: 0x0b80_1234,
+            ..IpAddr::V4(Ipv4Addr::LOCALHOST).into()
+        };
+        assert_eq!(IfIndexMsg::new(query, 0).rtm.rtm_tos, 0);
+    }
+
+    #[test]
+    fn source_follows_destination() {
+        let query = RouteQuery {
+            src: Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2))),
+            ..IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1)).into()
+        };
+        let msg = IfIndexMsg::new(query, 0);
+        assert_eq!(msg.rtm.rtm_src_len, 32);
+        let attrs: Vec<_> = RtAttrs(
+            &<&[u8]>::from(&msg)[std::mem::size_of::<nlmsghdr>() + std::mem::size_of::<rtmsg>()..],
+        )
+        .collect();
+        assert_eq!(
+            attrs.iter().map(|attr| attr.rta_type).collect::<Vec<_>>(),
+            [RTA_OIF, RTA_DST, RTA_SRC]
+        );
+        assert_eq!(attrs[1].payload, [198, 51, 100, 1]);
+        assert_eq!(attrs[2].payload, [192, 0, 2, 2]);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "Miri does not support Netlink sockets")]
+    fn bad_index_in_error() {
+        let mut fd = RouteSocket::new(AF_NETLINK, NETLINK_ROUTE).unwrap();
+        let err = if_name_mtu(i32::MAX, &mut fd, &mut reply_buf())
+            .map_err(|e| route_if_name_err(i32::MAX, &e))
+            .unwrap_err();
+        assert!(err.to_string().contains(&i32::MAX.to_string()), "{err}");
+    }
+
+    #[test]
+    fn strict_rejects_trailing_bytes() {
+        let buf = [nlmsg(RTM_NEWROUTE, 1, &[0xbb; 4]), vec![0; 4]].concat();
+        assert!(find_msg_with_seq(&buf, 1, RTM_NEWROUTE, false)
+            .unwrap()
+            .is_some());
+        let err = find_msg_with_seq(&buf, 1, RTM_NEWROUTE, true).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn bad_message_lengths_are_rejected() {
+        // A length shorter than the header, and a message truncated by the end of the buffer.
+        let mut short = nlmsg(RTM_NEWROUTE, 1, &[0xbb; 4]);
+        short[..4].copy_from_slice(&8u32.to_ne_bytes());
+        let truncated = nlmsg(RTM_NEWROUTE, 1, &[0xbb; 8])[..20].to_vec();
+        for buf in [short, truncated] {
+            for strict in [false, true] {
+                let err = find_msg_with_seq(&buf, 1, RTM_NEWROUTE, strict).unwrap_err();
+                assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+                let err =
+                    collect_dump_msgs(&buf, 1, RTM_NEWROUTE, strict, &mut Vec::new()).unwrap_err();
+                assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+            }
+        }
+    }
+
+    #[test]
+    fn strict_rejects_overlong_message() {
+        let mut buf = nlmsg(RTM_NEWROUTE, 1, &[0xbb; 4]);
+        buf.extend_from_slice(&nlmsg(NLMSG_NOOP, 1, &[0; 8])[..20]);
+        assert!(find_msg_with_seq(&buf, 1, RTM_NEWROUTE, false)
+            .unwrap()
+            .is_some());
+        let err = find_msg_with_seq(&buf, 1, RTM_NEWROUTE, true).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn strict_rejects_family_mismatch() {
+        let rtm = rtmsg {
+            rtm_family: AF_INET,
+            ..Default::default()
+        };
+        // SAFETY: `rtmsg` is a plain C struct without padding.
+        let buf = unsafe {
+            std::slice::from_raw_parts(
+                std::ptr::addr_of!(rtm).cast::<u8>(),
+                std::mem::size_of::<rtmsg>(),
+            )
+        };
+        assert!(check_rtm_family(buf, AF_INET, true).is_ok());
+        assert!(check_rtm_family(buf, AF_INET6, false).is_ok());
+        let err = check_rtm_family(buf, AF_INET6, true).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    /// The checked-in bindings that the `vendored-bindings` feature uses instead of bindgen.
+    #[cfg(all(feature = "bindgen", not(feature = "vendored-bindings")))]
+    #[allow(dead_code, non_camel_case_types, clippy::struct_field_names)]
+    mod vendored {
+        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/bindings/linux.rs"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "bindgen", not(feature = "vendored-bindings")))]
+    fn vendored_bindings_match() {
+        use super::bindings;
+
+        assert_same_layout!(
+            bindings::nlmsghdr,
+            vendored::nlmsghdr,
+            [nlmsg_len, nlmsg_type, nlmsg_flags, nlmsg_seq, nlmsg_pid]
+        );
+        assert_same_layout!(bindings::rtattr, vendored::rtattr, [rta_len, rta_type]);
+        assert_same_layout!(
+            bindings::rtmsg,
+            vendored::rtmsg,
+            [
+                rtm_family,
+                rtm_dst_len,
+                rtm_src_len,
+                rtm_tos,
+                rtm_table,
+                rtm_protocol,
+                rtm_scope,
+                rtm_type,
+                rtm_flags
+            ]
+        );
+        assert_same_layout!(
+            bindings::rtnexthop,
+            vendored::rtnexthop,
+            [rtnh_len, rtnh_flags, rtnh_hops, rtnh_ifindex]
+        );
+        assert_same_layout!(
+            bindings::ifinfomsg,
+            vendored::ifinfomsg,
+            [ifi_family, ifi_type, ifi_index, ifi_flags, ifi_change]
+        );
+        assert_same_layout!(
+            bindings::ifaddrmsg,
+            vendored::ifaddrmsg,
+            [ifa_family, ifa_prefixlen, ifa_flags, ifa_scope, ifa_index]
+        );
+        assert_eq!(bindings::RTM_F_FIB_MATCH, vendored::RTM_F_FIB_MATCH);
+        assert_eq!(bindings::RTAX_MTU, vendored::RTAX_MTU);
+        assert_eq!(bindings::RTMGRP_LINK, vendored::RTMGRP_LINK);
+        assert_eq!(bindings::RTMGRP_IPV4_ROUTE, vendored::RTMGRP_IPV4_ROUTE);
+        assert_eq!(bindings::RTMGRP_IPV6_ROUTE, vendored::RTMGRP_IPV6_ROUTE);
+    }
 }
@@ -0,0 +1,90 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::{
+    io::Result,
+    net::IpAddr,
+    sync::{PoisonError, RwLock},
+};
+
+/// A function that replaces the operating system lookup in [`crate::interface_and_mtu`].
+type MockFn = dyn Fn(IpAddr) -> Result<(String, usize)> + Send + Sync;
+
+/// The currently installed mock, if any.
+static MOCK: RwLock<Option<Box<MockFn>>> = RwLock::new(None);
+
+/// Make [`crate::interface_and_mtu`] return the result of `f` instead of querying the operating
+/// system, until [`clear_mock`] is called.
+///
+/// The mock is process-wide and replaces any previously installed one. This allows tests to
+/// deterministically exercise code paths that depend on a specific MTU or on a lookup failure.
+/// `f` must not call [`set_mock`] or [`clear_mock`], which would deadlock.
+pub fn set_mock(f: impl Fn(IpAddr) -> Result<(String, usize)> + Send + Sync + 'static) {
+    *MOCK.write().unwrap_or_else(PoisonError::into_inner) = Some(Box::new(f));
+}
+
+/// Remove the mock installed by [`set_mock`], if any, so that [`crate::interface_and_mtu`] queries
+/// the operating system again.
+pub fn clear_mock() {
+    *MOCK.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Return the result of the installed mock for `remote`, or `None` if no mock is installed.
+pub fn mocked(remote: IpAddr) -> Option<Result<(String, usize)>> {
+    MOCK.read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .map(|f| f(remote))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{Error, ErrorKind},
+        net::{IpAddr, Ipv4Addr},
+    };
+
+    use super::{clear_mock, set_mock};
+    use crate::{interface_and_mtu, interface_and_mtu_impl, Interface};
+
+    // TEST-NET-1 is not routable, so a lookup for it is unaffected by other tests running in
+    // parallel. Everything else falls through to the real lookup, for the same reason.
+    const MOCKED: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+
+    // A single test, because the mock is process-wide.
+    #[test]
+    fn set_and_clear() {
+        let real = interface_and_mtu(MOCKED).map_err(|e| e.kind());
+
+        set_mock(|remote| {
+            if remote == MOCKED {
+                Ok(("mock0".to_string(), 1280))
+            } else {
+                interface_and_mtu_impl(remote.into()).map(Into::into)
+            }
+        });
+        assert_eq!(
+            interface_and_mtu(MOCKED).unwrap(),
+            Interface::from(("mock0".to_string(), 1280))
+        );
+
+        // Setting a new mock replaces the old one, and errors are passed through.
+        set_mock(|remote| {
+            if remote == MOCKED {
+                Err(Error::new(ErrorKind::TimedOut, "mocked failure"))
+            } else {
+                interface_and_mtu_impl(remote.into()).map(Into::into)
+            }
+        });
+        assert_eq!(
+            interface_and_mtu(MOCKED).unwrap_err().kind(),
+            ErrorKind::TimedOut
+        );
+
+        clear_mock();
+        assert_eq!(interface_and_mtu(MOCKED).map_err(|e| e.kind()), real);
+    }
+}
@@ -0,0 +1,206 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Message construction and parsing helpers that only depend on `core`.
+//!
+//! These are public with the `core-only` feature, so that they can be reused on targets that supply
+//! their own transport for routing messages. This file must not use anything from `std`; it is also
+//! compiled under `#![no_std]` by `tests/no_std.rs`.
+
+#![warn(
+    clippy::std_instead_of_core,
+    clippy::std_instead_of_alloc,
+    clippy::alloc_instead_of_core
+)]
+
+#[cfg(any(feature = "core-only", target_os = "linux", target_os = "android"))]
+use core::ffi::c_int;
+
+/// Align `size` to the next multiple of `align` (which needs to be a power of two).
+#[must_use]
+pub const fn aligned_by(size: usize, align: usize) -> usize {
+    if size == 0 {
+        align
+    } else {
+        1 + ((size - 1) | (align - 1))
+    }
+}
+
+/// Parse a native-endian `c_int` from the start of `buf`.
+#[cfg(any(feature = "core-only", target_os = "linux", target_os = "android"))]
+#[must_use]
+pub fn parse_c_int(buf: &[u8]) -> Option<c_int> {
+    let bytes = buf.get(..core::mem::size_of::<c_int>())?;
+    Some(c_int::from_ne_bytes(bytes.try_into().ok()?))
+}
+
+/// A cursor that reads native-endian fields one after the other from a buffer, for parsing C
+/// structs without pointer casts. Each read returns `None` if the buffer is too short.
+#[cfg(any(feature = "core-only", target_os = "linux", target_os = "android"))]
+#[derive(Clone, Debug)]
+pub struct NeFields<'a>(pub &'a [u8]);
+
+#[cfg(any(feature = "core-only", target_os = "linux", target_os = "android"))]
+impl NeFields<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.0.get(..N)?.try_into().ok()?;
+        self.0 = &self.0[N..];
+        Some(bytes)
+    }
+
+    /// Read a `u8`.
+    pub fn u8(&mut self) -> Option<u8> {
+        self.take().map(u8::from_ne_bytes)
+    }
+
+    /// Read a native-endian `u16`.
+    pub fn u16(&mut self) -> Option<u16> {
+        self.take().map(u16::from_ne_bytes)
+    }
+
+    /// Read a native-endian `u32`.
+    pub fn u32(&mut self) -> Option<u32> {
+        self.take().map(u32::from_ne_bytes)
+    }
+
+    /// Read a native-endian `i32`.
+    pub fn i32(&mut self) -> Option<i32> {
+        self.take().map(i32::from_ne_bytes)
+    }
+
+    /// Read a native-endian `u64`.
+    pub fn u64(&mut self) -> Option<u64> {
+        self.take().map(u64::from_ne_bytes)
+    }
+}
+
+/// The size of a Netlink route attribute header, i.e., `struct rtattr`.
+#[cfg(any(feature = "core-only", target_os = "linux", target_os = "android"))]
+pub const RTA_HDR_LEN: usize = 4;
+
+/// The alignment of Netlink route attributes.
+#[cfg(any(feature = "core-only", target_os = "linux", target_os = "android"))]
+const RTA_ALIGN: usize = 4;
+
+/// A Netlink route attribute.
+#[cfg(any(feature = "core-only", target_os = "linux", target_os = "android"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RtAttr<'a> {
+    /// The attribute type, i.e., `rta_type`.
+    pub rta_type: u16,
+    /// The attribute payload, without trailing padding.
+    pub payload: &'a [u8],
+}
+
+/// An iterator over the Netlink route attributes in a buffer.
+///
+/// Iteration stops at the end of the buffer or at the first malformed attribute.
+#[cfg(any(feature = "core-only", target_os = "linux", target_os = "android"))]
+#[derive(Clone, Debug)]
+pub struct RtAttrs<'a>(pub &'a [u8]);
+
+#[cfg(any(feature = "core-only", target_os = "linux", target_os = "android"))]
+impl<'a> Iterator for RtAttrs<'a> {
+    type Item = RtAttr<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let hdr = self.0.get(..RTA_HDR_LEN)?;
+        let rta_len = usize::from(u16::from_ne_bytes([hdr[0], hdr[1]]));
+        let rta_type = u16::from_ne_bytes([hdr[2], hdr[3]]);
+        // This also rejects `rta_len` values smaller than the header.
+        let Some(payload) = self.0.get(RTA_HDR_LEN..rta_len) else {
+            self.0 = &[];
+            return None;
+        };
+        self.0 = self
+            .0
+            .get(aligned_by(rta_len, RTA_ALIGN)..)
+            .unwrap_or_default();
+        Some(RtAttr { rta_type, payload })
+    }
+}
+
+#[cfg(all(
+    test,
+    any(feature = "core-only", target_os = "linux", target_os = "android")
+))]
+mod test {
+    use super::{aligned_by, parse_c_int, NeFields, RtAttr, RtAttrs};
+
+    #[test]
+    fn aligned() {
+        assert_eq!(aligned_by(0, 4), 4);
+        assert_eq!(aligned_by(1, 4), 4);
+        assert_eq!(aligned_by(4, 4), 4);
+        assert_eq!(aligned_by(5, 4), 8);
+    }
+
+    #[test]
+    fn c_int() {
+        assert_eq!(parse_c_int(&42i32.to_ne_bytes()), Some(42));
+        assert_eq!(parse_c_int(&[0; 3]), None);
+    }
+
+    #[test]
+    fn ne_fields() {
+        let mut buf = [0u8; 19];
+        buf[0] = 1;
+        buf[1..3].copy_from_slice(&2u16.to_ne_bytes());
+        buf[3..7].copy_from_slice(&3u32.to_ne_bytes());
+        buf[7..11].copy_from_slice(&(-4i32).to_ne_bytes());
+        buf[11..].copy_from_slice(&5u64.to_ne_bytes());
+        let mut fields = NeFields(&buf);
+        assert_eq!(fields.u8(), Some(1));
+        assert_eq!(fields.u16(), Some(2));
+        assert_eq!(fields.u32(), Some(3));
+        assert_eq!(fields.i32(), Some(-4));
+        assert_eq!(fields.u64(), Some(5));
+        assert_eq!(fields.u8(), None);
+        // A short read fails without consuming anything.
+        let mut fields = NeFields(&buf[..3]);
+        assert_eq!(fields.u32(), None);
+        assert_eq!(fields.u8(), Some(1));
+    }
+
+    #[test]
+    fn rtattrs() {
+        let mut buf = [0u8; 16];
+        // A padded three-byte attribute, followed by a four-byte one.
+        buf[..2].copy_from_slice(&7u16.to_ne_bytes());
+        buf[2..4].copy_from_slice(&1u16.to_ne_bytes());
+        buf[4..7].copy_from_slice(b"lo\0");
+        buf[8..10].copy_from_slice(&8u16.to_ne_bytes());
+        buf[10..12].copy_from_slice(&2u16.to_ne_bytes());
+        buf[12..].copy_from_slice(&42i32.to_ne_bytes());
+        let mut attrs = RtAttrs(&buf);
+        assert_eq!(
+            attrs.next(),
+            Some(RtAttr {
+                rta_type: 1,
+                payload: b"lo\0"
+            })
+        );
+        assert_eq!(
+            attrs.next(),
+            Some(RtAttr {
+                rta_type: 2,
+                payload: &42i32.to_ne_bytes()
+            })
+        );
+        assert_eq!(attrs.next(), None);
+    }
+
+    #[test]
+    fn rtattrs_malformed() {
+        let [kind0, kind1] = 1u16.to_ne_bytes();
+        // An `rta_len` that is shorter than the header.
+        let [len0, len1] = 2u16.to_ne_bytes();
+        assert_eq!(RtAttrs(&[len0, len1, kind0, kind1]).count(), 0);
+        // An `rta_len` that exceeds the buffer.
+        let [len0, len1] = 8u16.to_ne_bytes();
+        assert_eq!(RtAttrs(&[len0, len1, kind0, kind1, 0]).count(), 0);
+    }
+}
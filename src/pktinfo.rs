@@ -0,0 +1,188 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::{
+    io::{Error, ErrorKind, Result},
+    ptr,
+};
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    solarish
+))]
+use libc::IP_PKTINFO;
+#[cfg(any(
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+use libc::IP_RECVIF;
+#[cfg(not(target_os = "windows"))]
+use libc::{cmsghdr, IPPROTO_IP, IPPROTO_IPV6, IPV6_PKTINFO};
+
+/// `WSACMSGHDR`, which has the same layout as the Linux `cmsghdr`.
+#[cfg(target_os = "windows")]
+#[repr(C)]
+#[allow(non_camel_case_types, clippy::struct_field_names)]
+struct cmsghdr {
+    cmsg_len: usize,
+    cmsg_level: i32,
+    cmsg_type: i32,
+}
+
+#[cfg(target_os = "windows")]
+const IPPROTO_IP: i32 = 0;
+#[cfg(target_os = "windows")]
+const IPPROTO_IPV6: i32 = 41;
+#[cfg(target_os = "windows")]
+const IP_PKTINFO: i32 = 19;
+#[cfg(target_os = "windows")]
+const IPV6_PKTINFO: i32 = 19;
+
+/// Return the offset of the control message data from the start of the `cmsghdr`.
+#[cfg(not(target_os = "windows"))]
+#[allow(clippy::missing_const_for_fn)] // `CMSG_LEN` is not `const` in all `libc` versions.
+fn data_offset() -> usize {
+    (unsafe { libc::CMSG_LEN(0) }) as usize
+}
+
+/// Return the offset of the control message data from the start of the `WSACMSGHDR`.
+#[cfg(target_os = "windows")]
+const fn data_offset() -> usize {
+    crate::parse::aligned_by(
+        std::mem::size_of::<cmsghdr>(),
+        std::mem::align_of::<usize>(),
+    )
+}
+
+fn invalid_err(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, msg)
+}
+
+/// Extract the interface index from a single packet information control message, i.e., a
+/// `cmsghdr` followed by its data.
+pub fn if_index(cmsg: &[u8]) -> Result<u32> {
+    if cmsg.len() < std::mem::size_of::<cmsghdr>() {
+        return Err(invalid_err("Control message too short"));
+    }
+    let hdr: cmsghdr = unsafe { ptr::read_unaligned(cmsg.as_ptr().cast()) };
+    // Determine where in the control message data the interface index is, and how wide it is.
+    let (offset, width) = match (hdr.cmsg_level, hdr.cmsg_type) {
+        // `in6_pktinfo` has the interface index after the IPv6 address on all platforms.
+        (IPPROTO_IPV6, IPV6_PKTINFO) => (16, 4),
+        // `in_pktinfo` has the interface index first on these platforms.
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            solarish
+        ))]
+        (IPPROTO_IP, IP_PKTINFO) => (0, 4),
+        // `IN_PKTINFO` has the interface index after the IPv4 address.
+        #[cfg(target_os = "windows")]
+        (IPPROTO_IP, IP_PKTINFO) => (4, 4),
+        // `IP_RECVIF` carries a `sockaddr_dl`, which has the interface index in `sdl_index`.
+        #[cfg(any(
+            target_os = "macos",
+            target_os = "freebsd",
+            target_os = "dragonfly",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        ))]
+        (IPPROTO_IP, IP_RECVIF) => (2, 2),
+        _ => return Err(invalid_err("Unsupported control message")),
+    };
+    let start = data_offset() + offset;
+    let bytes = cmsg
+        .get(start..start + width)
+        .ok_or_else(|| invalid_err("Control message data too short"))?;
+    Ok(match *bytes {
+        [a, b] => u16::from_ne_bytes([a, b]).into(),
+        [a, b, c, d] => u32::from_ne_bytes([a, b, c, d]),
+        _ => unreachable!("width is 2 or 4"),
+    })
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod test {
+    use std::{
+        io::ErrorKind,
+        net::{IpAddr, Ipv4Addr},
+    };
+
+    use libc::{
+        cmsghdr, in6_pktinfo, in_pktinfo, IPPROTO_IP, IPPROTO_IPV6, IPV6_PKTINFO, IP_PKTINFO,
+    };
+
+    use super::data_offset;
+    use crate::{interface_and_mtu, interface_and_mtu_from_pktinfo};
+
+    /// Build a control message with the given level, type and data.
+    fn cmsg<T>(level: i32, kind: i32, data: &T) -> Vec<u8> {
+        // `cmsghdr` has private padding fields on some platforms.
+        let mut hdr: cmsghdr = unsafe { std::mem::zeroed() };
+        #[allow(trivial_numeric_casts)] // `cmsg_len` is not a `usize` on all platforms.
+        {
+            hdr.cmsg_len = (data_offset() + std::mem::size_of::<T>()) as _;
+        }
+        hdr.cmsg_level = level;
+        hdr.cmsg_type = kind;
+        let mut buf = vec![0; data_offset() + std::mem::size_of::<T>()];
+        unsafe {
+            std::ptr::write_unaligned(buf.as_mut_ptr().cast(), hdr);
+            std::ptr::write_unaligned(
+                buf[data_offset()..].as_mut_ptr().cast(),
+                std::ptr::read(data),
+            );
+        }
+        buf
+    }
+
+    fn lo_index() -> u32 {
+        unsafe { libc::if_nametoindex(b"lo\0".as_ptr().cast()) }
+    }
+
+    #[test]
+    fn ip_pktinfo() {
+        let pktinfo = in_pktinfo {
+            ipi_ifindex: lo_index().try_into().unwrap(),
+            ipi_spec_dst: libc::in_addr { s_addr: 0 },
+            ipi_addr: libc::in_addr { s_addr: 0 },
+        };
+        assert_eq!(
+            interface_and_mtu_from_pktinfo(&cmsg(IPPROTO_IP, IP_PKTINFO, &pktinfo)).unwrap(),
+            interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap()
+        );
+    }
+
+    #[test]
+    fn ipv6_pktinfo() {
+        let pktinfo = in6_pktinfo {
+            ipi6_addr: libc::in6_addr { s6_addr: [0; 16] },
+            ipi6_ifindex: lo_index(),
+        };
+        assert_eq!(
+            interface_and_mtu_from_pktinfo(&cmsg(IPPROTO_IPV6, IPV6_PKTINFO, &pktinfo)).unwrap(),
+            interface_and_mtu(IpAddr::V4(Ipv4Addr::LOCALHOST)).unwrap()
+        );
+    }
+
+    #[test]
+    fn unsupported_cmsg() {
+        let err = interface_and_mtu_from_pktinfo(&cmsg(IPPROTO_IP, 0, &0u32)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn short_cmsg() {
+        let err = interface_and_mtu_from_pktinfo(&cmsg(IPPROTO_IP, IP_PKTINFO, &0u16)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -5,13 +5,20 @@
 // except according to those terms.
 
 use std::{
-    io::{Error, Read, Result, Write},
+    io::{Error, ErrorKind, Read, Result, Write},
     num::TryFromIntError,
     os::fd::{AsRawFd, FromRawFd as _, OwnedFd},
-    sync::atomic::Ordering,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
 };
 
-use libc::{fsync, read, socket, write, SOCK_RAW};
+use libc::{fsync, read, setsockopt, socket, socklen_t, timeval, write, SOCK_RAW, SOL_SOCKET};
+#[cfg(feature = "tokio")]
+use tokio::io::unix::AsyncFd;
 
 use crate::unlikely_err;
 
@@ -25,9 +32,27 @@ type AtomicRouteSocketSeq = std::sync::atomic::AtomicI32;
 #[cfg(not(any(target_os = "linux", target_os = "android")))]
 type RouteSocketSeq = i32;
 
-static SEQ: AtomicRouteSocketSeq = AtomicRouteSocketSeq::new(0);
+/// How long queries wait for the kernel to reply before failing with an [`ErrorKind::TimedOut`]
+/// error, see [`RouteSocket::set_read_timeout`].
+pub const QUERY_TIMEOUT: Duration = Duration::from_secs(1);
 
-pub struct RouteSocket(OwnedFd);
+/// The number of change notifications observed by the background monitor.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Whether the background monitor is currently running.
+static MONITOR_RUNNING: Mutex<bool> = Mutex::new(false);
+
+/// A route socket, on which replies are matched to requests by sequence number.
+///
+/// The socket cannot be cloned: clones would share the receive queue, and each would discard the
+/// replies to the queries of the others. Callers that need to run queries concurrently should open
+/// a socket for each.
+#[derive(Debug)]
+pub struct RouteSocket {
+    fd: OwnedFd,
+    /// The next sequence number for requests on this socket.
+    seq: AtomicRouteSocketSeq,
+}
 
 impl RouteSocket {
     pub fn new(domain: libc::c_int, protocol: libc::c_int) -> Result<Self> {
@@ -35,17 +60,200 @@ impl RouteSocket {
         if fd == -1 {
             return Err(Error::last_os_error());
         }
-        Ok(Self(unsafe { OwnedFd::from_raw_fd(fd) }))
+        // Netlink sends replies only to the requesting socket.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let seq = 0;
+        // Routing sockets receive the replies to the requests of all routing sockets, and the pid
+        // check does not tell apart the requests of one process. Start at a value derived from the
+        // descriptor, which is unique among open sockets, so that concurrently open sockets use
+        // different sequence numbers for their first 65,536 requests.
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let seq = fd.wrapping_shl(16);
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            seq: AtomicRouteSocketSeq::new(seq),
+        })
+    }
+
+    /// Return a new sequence number for a request on this socket.
+    pub fn new_seq(&self) -> RouteSocketSeq {
+        self.seq.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Receive a message into `buf` with the `recv` flags `flags`. Unlike [`Read::read`], this
+    /// reports a timeout as an [`ErrorKind::WouldBlock`] error.
+    #[cfg(any(feature = "tokio", target_os = "linux", target_os = "android"))]
+    fn recv(&self, buf: &mut [u8], flags: libc::c_int) -> Result<usize> {
+        retry_interrupted(|| {
+            check_result(unsafe {
+                libc::recv(self.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), flags)
+            })
+        })
+    }
+
+    /// Like [`RouteSocket::recv`], but first grow `buf` to the length of the message if it would
+    /// not fit, so that the message is not truncated.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn recv_growing(&self, buf: &mut Vec<u8>, flags: libc::c_int) -> Result<usize> {
+        // With `MSG_TRUNC`, Linux returns the full length of the message, even if it exceeds the
+        // buffer. `MSG_PEEK` leaves the message queued for the actual read.
+        let len = self.recv(&mut [0], flags | libc::MSG_PEEK | libc::MSG_TRUNC)?;
+        if len > buf.len() {
+            buf.resize(len, 0);
+        }
+        self.recv(buf, flags)
+    }
+
+    /// Like [`Read::read`], but first grow `buf` to the length of the message if it would not fit,
+    /// so that the message is not truncated.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[allow(clippy::needless_pass_by_ref_mut)] // Reads consume messages, as for `Read::read`.
+    pub fn read_growing(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        self.recv_growing(buf, 0).map_err(timed_out_if_would_block)
+    }
+
+    /// Make reads on this socket fail with an [`ErrorKind::TimedOut`] error if no
+    /// message arrives within `dur`. With `None`, reads block until a message arrives.
+    ///
+    /// As for [`std::net::UdpSocket::set_read_timeout`], a zero duration is an
+    /// [`ErrorKind::InvalidInput`] error.
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> Result<()> {
+        let mut tv = timeval {
+            tv_sec: 0,
+            tv_usec: 0,
+        };
+        if let Some(dur) = dur {
+            if dur.is_zero() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Cannot set a zero read timeout",
+                ));
+            }
+            tv.tv_sec = libc::time_t::try_from(dur.as_secs()).unwrap_or(libc::time_t::MAX);
+            #[allow(clippy::cast_possible_wrap, clippy::cast_lossless)] // Less than 1,000,000.
+            {
+                tv.tv_usec = dur.subsec_micros() as libc::suseconds_t;
+            }
+            // A zero `timeval` disables the timeout, so round sub-microsecond durations up.
+            if tv.tv_sec == 0 && tv.tv_usec == 0 {
+                tv.tv_usec = 1;
+            }
+        }
+        if unsafe {
+            setsockopt(
+                self.as_raw_fd(),
+                SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                std::ptr::from_ref(&tv).cast(),
+                #[allow(clippy::cast_possible_truncation)] // The size of a `timeval` fits.
+                {
+                    std::mem::size_of::<timeval>() as socklen_t
+                },
+            )
+        } == -1
+        {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Count every message received on this socket for which `is_change` returns true as a
+    /// change, until reading fails.
+    fn monitor(mut self, is_change: fn(&[u8]) -> bool) {
+        let mut buf = [0u8; 8192];
+        loop {
+            match self.read(&mut buf) {
+                Ok(len) if is_change(&buf[..len]) => (),
+                Ok(_) => continue,
+                // The kernel dropped messages, which means we missed at least one change.
+                Err(e) if e.raw_os_error() == Some(libc::ENOBUFS) => (),
+                Err(_) => break,
+            }
+            GENERATION.fetch_add(1, Ordering::Relaxed);
+        }
+        // Let the next call to `change_generation` restart the monitor.
+        if let Ok(mut running) = MONITOR_RUNNING.lock() {
+            *running = false;
+        }
+        GENERATION.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Return the current change generation, starting a background monitor thread reading from the
+/// socket returned by `open` if one is not already running.
+pub fn change_generation(
+    open: impl FnOnce() -> Result<RouteSocket>,
+    is_change: fn(&[u8]) -> bool,
+) -> Result<u64> {
+    let mut running = MONITOR_RUNNING
+        .lock()
+        .map_err(|e| Error::other(e.to_string()))?;
+    if !*running {
+        let fd = open()?;
+        thread::Builder::new()
+            .name("mtu-monitor".to_string())
+            .spawn(move || fd.monitor(is_change))?;
+        *running = true;
+    }
+    drop(running);
+    Ok(GENERATION.load(Ordering::Relaxed))
+}
+
+/// A [`RouteSocket`] whose reads wait for the kernel on the Tokio reactor instead of blocking.
+///
+/// Writes are not awaited, since the kernel processes a routing request as part of the `write`
+/// call. Replies are matched to requests by sequence number as for a [`RouteSocket`], and since the
+/// socket is borrowed mutably across each await, no other query can consume them in between.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct AsyncRouteSocket(AsyncFd<RouteSocket>);
+
+#[cfg(feature = "tokio")]
+impl AsyncRouteSocket {
+    /// Register `fd` with the reactor of the current Tokio runtime.
+    pub fn new(fd: RouteSocket) -> Result<Self> {
+        AsyncFd::new(fd).map(Self)
+    }
+
+    /// Return a new sequence number for a request on this socket. The BSD queries take theirs
+    /// before registering the socket.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn new_seq(&self) -> RouteSocketSeq {
+        self.0.get_ref().new_seq()
     }
 
-    pub fn new_seq() -> RouteSocketSeq {
-        SEQ.fetch_add(1, Ordering::Relaxed)
+    /// Write `buf` to the socket, as [`Write::write_all`] does.
+    pub fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.0.get_mut().write_all(buf)
+    }
+
+    /// Wait for a message and read it into `buf`, as [`Read::read`] does. On Linux, `buf` is grown
+    /// first if the message would not fit, as for [`RouteSocket::read_growing`]. Fail with an
+    /// [`ErrorKind::TimedOut`] error if no message arrives within [`QUERY_TIMEOUT`].
+    #[allow(clippy::needless_pass_by_ref_mut)] // Only one read at a time may consume replies.
+    #[allow(clippy::ptr_arg)] // `buf` is only grown on Linux.
+    pub async fn read(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        let read = async {
+            loop {
+                let mut guard = self.0.readable().await?;
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                let res = guard.try_io(|fd| fd.get_ref().recv_growing(buf, libc::MSG_DONTWAIT));
+                #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                let res = guard.try_io(|fd| fd.get_ref().recv(buf, libc::MSG_DONTWAIT));
+                if let Ok(res) = res {
+                    return res;
+                }
+            }
+        };
+        tokio::time::timeout(QUERY_TIMEOUT, read)
+            .await
+            .unwrap_or_else(|_| Err(timed_out_err()))
     }
 }
 
 impl AsRawFd for RouteSocket {
     fn as_raw_fd(&self) -> i32 {
-        self.0.as_raw_fd()
+        self.fd.as_raw_fd()
     }
 }
 
@@ -59,10 +267,22 @@ fn check_result(res: isize) -> Result<usize> {
     }
 }
 
+/// Call `syscall` until it does not fail with `EINTR`, so that signals delivered while it blocks
+/// are not reported as errors.
+fn retry_interrupted(mut syscall: impl FnMut() -> Result<usize>) -> Result<usize> {
+    loop {
+        match syscall() {
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            res => return res,
+        }
+    }
+}
+
 impl Write for RouteSocket {
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
-        let res = unsafe { write(self.as_raw_fd(), buf.as_ptr().cast(), buf.len()) };
-        check_result(res)
+        retry_interrupted(|| {
+            check_result(unsafe { write(self.as_raw_fd(), buf.as_ptr().cast(), buf.len()) })
+        })
     }
 
     fn flush(&mut self) -> Result<()> {
@@ -71,11 +291,96 @@ impl Write for RouteSocket {
     }
 }
 
+/// Return the error for a query that got no reply in time.
+pub fn timed_out_err() -> Error {
+    Error::new(ErrorKind::TimedOut, "Timed out waiting for a routing reply")
+}
+
+/// Return a timeout error for `e` if it is an [`ErrorKind::WouldBlock`] error, which is how the
+/// kernel reports that the read timeout expired.
+fn timed_out_if_would_block(e: Error) -> Error {
+    if e.kind() == ErrorKind::WouldBlock {
+        timed_out_err()
+    } else {
+        e
+    }
+}
+
 impl Read for RouteSocket {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         // If we've written a well-formed message into the kernel via `write`, we should be able to
-        // read a well-formed message back out, and not block.
-        let res = unsafe { read(self.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
-        check_result(res)
+        // read a well-formed message back out, and not block. Should the kernel not reply anyway,
+        // the read timeout, if set, ends the read with `EAGAIN`.
+        retry_interrupted(|| {
+            check_result(unsafe { read(self.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) })
+        })
+        .map_err(timed_out_if_would_block)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Error, ErrorKind};
+
+    use super::retry_interrupted;
+
+    #[test]
+    fn interrupted_syscalls_are_retried() {
+        let mut calls = 0;
+        let res = retry_interrupted(|| {
+            calls += 1;
+            if calls < 3 {
+                Err(Error::from_raw_os_error(libc::EINTR))
+            } else {
+                Ok(42)
+            }
+        });
+        assert_eq!(res.unwrap(), 42);
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn other_errors_are_not_retried() {
+        let mut calls = 0;
+        let res = retry_interrupted(|| {
+            calls += 1;
+            Err(Error::from_raw_os_error(libc::EAGAIN))
+        });
+        assert_eq!(res.unwrap_err().kind(), ErrorKind::WouldBlock);
+        assert_eq!(calls, 1);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn buffer_grows_to_fit() {
+        use std::{
+            io::Write as _,
+            os::fd::{FromRawFd as _, OwnedFd},
+        };
+
+        use super::{AtomicRouteSocketSeq, RouteSocket};
+
+        // A datagram socket pair stands in for a netlink socket, which has the same `MSG_TRUNC`
+        // semantics.
+        let mut fds = [0; 2];
+        assert_eq!(
+            unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_DGRAM, 0, fds.as_mut_ptr()) },
+            0
+        );
+        let [mut tx, mut rx] = fds.map(|fd| RouteSocket {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            seq: AtomicRouteSocketSeq::new(0),
+        });
+        let msg: Vec<u8> = (0..10_000u16).map(|i| i.to_ne_bytes()[0]).collect();
+        tx.write_all(&msg).unwrap();
+        tx.write_all(&msg[..10]).unwrap();
+
+        let mut buf = vec![0; 16];
+        assert_eq!(rx.read_growing(&mut buf).unwrap(), msg.len());
+        assert_eq!(buf, msg);
+        // The buffer does not shrink for smaller messages.
+        assert_eq!(rx.read_growing(&mut buf).unwrap(), 10);
+        assert_eq!(buf[..10], msg[..10]);
+        assert_eq!(buf.len(), msg.len());
     }
 }
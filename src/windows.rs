@@ -5,47 +5,383 @@
 // except according to those terms.
 
 use std::{
-    ffi::CStr,
+    collections::HashMap,
+    ffi::{c_void, CStr},
+    hash::{DefaultHasher, Hash as _, Hasher as _},
     io::{Error, ErrorKind, Result},
-    net::IpAddr,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    os::windows::io::{AsRawSocket as _, BorrowedSocket},
     ptr, slice,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Mutex,
+    },
+    time::Duration,
 };
 
-use windows::Win32::{
-    Foundation::NO_ERROR,
-    NetworkManagement::{
-        IpHelper::{
-            if_indextoname, FreeMibTable, GetBestInterfaceEx, GetIpInterfaceTable,
-            MIB_IPINTERFACE_ROW, MIB_IPINTERFACE_TABLE,
+use windows::{
+    core::PCSTR,
+    Win32::{
+        Foundation::{
+            BOOLEAN, ERROR_HOST_UNREACHABLE, ERROR_NETWORK_UNREACHABLE, HANDLE, NO_ERROR,
+            WIN32_ERROR,
+        },
+        NetworkManagement::{
+            IpHelper::{
+                MIB_IF_ROW2, MIB_IPFORWARD_ROW2, MIB_IPFORWARD_TABLE2, MIB_IPINTERFACE_ROW,
+                MIB_IPINTERFACE_TABLE, MIB_NOTIFICATION_TYPE, MIB_UNICASTIPADDRESS_ROW,
+                MIB_UNICASTIPADDRESS_TABLE,
+            },
+            Ndis::{IfOperStatusUp, IF_MAX_STRING_SIZE, NET_IF_ADMIN_STATUS_UP},
+        },
+        Networking::WinSock::{
+            getpeername, getsockname, ADDRESS_FAMILY, AF_INET, AF_INET6, AF_UNSPEC, IN6_ADDR,
+            IN6_ADDR_0, IN_ADDR, IN_ADDR_0, SOCKADDR, SOCKADDR_IN, SOCKADDR_IN6, SOCKADDR_IN6_0,
+            SOCKADDR_INET, SOCKET, SOCKET_ERROR,
         },
-        Ndis::IF_MAX_STRING_SIZE,
-    },
-    Networking::WinSock::{
-        AF_INET, AF_INET6, IN6_ADDR, IN6_ADDR_0, IN_ADDR, IN_ADDR_0, SOCKADDR, SOCKADDR_IN,
-        SOCKADDR_IN6, SOCKADDR_INET,
     },
 };
 
-use crate::default_err;
+use self::iphlpapi::{
+    if_indextoname, if_nametoindex, CancelMibChangeNotify2, FreeMibTable, GetBestInterfaceEx,
+    GetBestRoute2, GetIfEntry2, GetIpForwardTable2, GetIpInterfaceTable, GetUnicastIpAddressTable,
+    NotifyIpInterfaceChange,
+};
+use crate::{
+    default_err, no_route_err, not_reachable_via, ChangeEvent, Interface, InterfaceFlags,
+    InterfaceIndex, InterfaceKind, InterfaceStats, RouteDetails, RouteEntry, RouteQuery,
+};
+
+/// The `IF_TYPE_*` interface types from `ipifcons.h` that map to [`InterfaceFlags`].
+const IF_TYPE_PPP: u32 = 23;
+const IF_TYPE_SOFTWARE_LOOPBACK: u32 = 24;
+
+/// With the default configuration, the IP Helper functions are imported statically from
+/// `iphlpapi.dll`.
+#[cfg(not(feature = "dynamic-link"))]
+mod iphlpapi {
+    pub use windows::Win32::NetworkManagement::IpHelper::{
+        if_indextoname, if_nametoindex, CancelMibChangeNotify2, FreeMibTable, GetBestInterfaceEx,
+        GetBestRoute2, GetIfEntry2, GetIpForwardTable2, GetIpInterfaceTable,
+        GetUnicastIpAddressTable, NotifyIpInterfaceChange,
+    };
+}
+
+/// With the `dynamic-link` feature, the IP Helper functions are resolved at runtime via
+/// `LoadLibraryA`/`GetProcAddress`, so that the crate can be used in contexts that cannot
+/// statically import from `iphlpapi.dll`. The wrappers below mirror the signatures of the
+/// `windows` crate functions they replace.
+#[cfg(feature = "dynamic-link")]
+#[allow(non_snake_case)] // Keep the names of the functions being wrapped.
+mod iphlpapi {
+    use std::{ffi::c_void, mem, ptr, sync::OnceLock};
+
+    use windows::{
+        core::{s, PCSTR, PSTR},
+        Win32::{
+            Foundation::{BOOLEAN, ERROR_PROC_NOT_FOUND, HANDLE, WIN32_ERROR},
+            NetworkManagement::{
+                IpHelper::{
+                    MIB_IF_ROW2, MIB_IPFORWARD_ROW2, MIB_IPFORWARD_TABLE2, MIB_IPINTERFACE_TABLE,
+                    MIB_UNICASTIPADDRESS_TABLE, PIPINTERFACE_CHANGE_CALLBACK,
+                },
+                Ndis::NET_LUID_LH,
+            },
+            Networking::WinSock::{ADDRESS_FAMILY, SOCKADDR, SOCKADDR_INET},
+            System::LibraryLoader::{GetProcAddress, LoadLibraryA},
+        },
+    };
 
-struct MibTablePtr(*mut MIB_IPINTERFACE_TABLE);
+    type Proc = unsafe extern "system" fn() -> isize;
+    type GetBestInterfaceExFn = unsafe extern "system" fn(*const SOCKADDR, *mut u32) -> u32;
+    type GetBestRoute2Fn = unsafe extern "system" fn(
+        *const NET_LUID_LH,
+        u32,
+        *const SOCKADDR_INET,
+        *const SOCKADDR_INET,
+        u32,
+        *mut MIB_IPFORWARD_ROW2,
+        *mut SOCKADDR_INET,
+    ) -> WIN32_ERROR;
+    type GetIpForwardTable2Fn =
+        unsafe extern "system" fn(ADDRESS_FAMILY, *mut *mut MIB_IPFORWARD_TABLE2) -> WIN32_ERROR;
+    type GetIpInterfaceTableFn =
+        unsafe extern "system" fn(ADDRESS_FAMILY, *mut *mut MIB_IPINTERFACE_TABLE) -> WIN32_ERROR;
+    type GetUnicastIpAddressTableFn = unsafe extern "system" fn(
+        ADDRESS_FAMILY,
+        *mut *mut MIB_UNICASTIPADDRESS_TABLE,
+    ) -> WIN32_ERROR;
+    type GetIfEntry2Fn = unsafe extern "system" fn(*mut MIB_IF_ROW2) -> WIN32_ERROR;
+    type FreeMibTableFn = unsafe extern "system" fn(*const c_void);
+    type IfIndexToNameFn = unsafe extern "system" fn(u32, PSTR) -> PSTR;
+    type IfNameToIndexFn = unsafe extern "system" fn(PCSTR) -> u32;
+    type NotifyIpInterfaceChangeFn = unsafe extern "system" fn(
+        ADDRESS_FAMILY,
+        PIPINTERFACE_CHANGE_CALLBACK,
+        *const c_void,
+        BOOLEAN,
+        *mut HANDLE,
+    ) -> WIN32_ERROR;
+    type CancelMibChangeNotify2Fn = unsafe extern "system" fn(HANDLE) -> WIN32_ERROR;
 
-impl MibTablePtr {
-    fn mut_ptr_ptr(&mut self) -> *mut *mut MIB_IPINTERFACE_TABLE {
+    pub struct Functions {
+        get_best_interface_ex: GetBestInterfaceExFn,
+        get_best_route2: GetBestRoute2Fn,
+        get_ip_forward_table2: GetIpForwardTable2Fn,
+        get_ip_interface_table: GetIpInterfaceTableFn,
+        get_unicast_ip_address_table: GetUnicastIpAddressTableFn,
+        get_if_entry2: GetIfEntry2Fn,
+        free_mib_table: FreeMibTableFn,
+        if_indextoname: IfIndexToNameFn,
+        if_nametoindex: IfNameToIndexFn,
+        notify_ip_interface_change: NotifyIpInterfaceChangeFn,
+        cancel_mib_change_notify2: CancelMibChangeNotify2Fn,
+    }
+
+    impl Functions {
+        fn load() -> Option<Self> {
+            // The module handle is intentionally never freed, since the resolved function pointers
+            // are cached for the lifetime of the process.
+            let module = unsafe { LoadLibraryA(s!("iphlpapi.dll")) }.ok()?;
+            let resolve = |name: PCSTR| unsafe { GetProcAddress(module, name) };
+            // Transmuting the `FARPROC`s is OK, since the signatures match the documented ones.
+            unsafe {
+                Some(Self {
+                    get_best_interface_ex: mem::transmute::<Proc, GetBestInterfaceExFn>(resolve(
+                        s!("GetBestInterfaceEx"),
+                    )?),
+                    get_best_route2: mem::transmute::<Proc, GetBestRoute2Fn>(resolve(s!(
+                        "GetBestRoute2"
+                    ))?),
+                    get_ip_forward_table2: mem::transmute::<Proc, GetIpForwardTable2Fn>(resolve(
+                        s!("GetIpForwardTable2"),
+                    )?),
+                    get_ip_interface_table: mem::transmute::<Proc, GetIpInterfaceTableFn>(resolve(
+                        s!("GetIpInterfaceTable"),
+                    )?),
+                    get_unicast_ip_address_table: mem::transmute::<Proc, GetUnicastIpAddressTableFn>(
+                        resolve(s!("GetUnicastIpAddressTable"))?,
+                    ),
+                    get_if_entry2: mem::transmute::<Proc, GetIfEntry2Fn>(resolve(s!(
+                        "GetIfEntry2"
+                    ))?),
+                    free_mib_table: mem::transmute::<Proc, FreeMibTableFn>(resolve(s!(
+                        "FreeMibTable"
+                    ))?),
+                    if_indextoname: mem::transmute::<Proc, IfIndexToNameFn>(resolve(s!(
+                        "if_indextoname"
+                    ))?),
+                    if_nametoindex: mem::transmute::<Proc, IfNameToIndexFn>(resolve(s!(
+                        "if_nametoindex"
+                    ))?),
+                    notify_ip_interface_change: mem::transmute::<Proc, NotifyIpInterfaceChangeFn>(
+                        resolve(s!("NotifyIpInterfaceChange"))?,
+                    ),
+                    cancel_mib_change_notify2: mem::transmute::<Proc, CancelMibChangeNotify2Fn>(
+                        resolve(s!("CancelMibChangeNotify2"))?,
+                    ),
+                })
+            }
+        }
+    }
+
+    /// Return the runtime-resolved IP Helper functions, loading them on first use.
+    pub fn functions() -> Option<&'static Functions> {
+        static FUNCTIONS: OnceLock<Option<Functions>> = OnceLock::new();
+        FUNCTIONS.get_or_init(Functions::load).as_ref()
+    }
+
+    pub unsafe fn GetBestInterfaceEx(pdestaddr: *const SOCKADDR, pdwbestifindex: *mut u32) -> u32 {
+        functions().map_or(ERROR_PROC_NOT_FOUND.0, |f| {
+            (f.get_best_interface_ex)(pdestaddr, pdwbestifindex)
+        })
+    }
+
+    pub unsafe fn GetBestRoute2(
+        interfaceluid: Option<*const NET_LUID_LH>,
+        interfaceindex: u32,
+        sourceaddress: Option<*const SOCKADDR_INET>,
+        destinationaddress: *const SOCKADDR_INET,
+        addresssortoptions: u32,
+        bestroute: *mut MIB_IPFORWARD_ROW2,
+        bestsourceaddress: *mut SOCKADDR_INET,
+    ) -> WIN32_ERROR {
+        functions().map_or(ERROR_PROC_NOT_FOUND, |f| {
+            (f.get_best_route2)(
+                interfaceluid.unwrap_or(ptr::null()),
+                interfaceindex,
+                sourceaddress.unwrap_or(ptr::null()),
+                destinationaddress,
+                addresssortoptions,
+                bestroute,
+                bestsourceaddress,
+            )
+        })
+    }
+
+    pub unsafe fn GetIpForwardTable2(
+        family: ADDRESS_FAMILY,
+        table: *mut *mut MIB_IPFORWARD_TABLE2,
+    ) -> WIN32_ERROR {
+        functions().map_or(ERROR_PROC_NOT_FOUND, |f| {
+            (f.get_ip_forward_table2)(family, table)
+        })
+    }
+
+    pub unsafe fn GetIpInterfaceTable(
+        family: ADDRESS_FAMILY,
+        table: *mut *mut MIB_IPINTERFACE_TABLE,
+    ) -> WIN32_ERROR {
+        functions().map_or(ERROR_PROC_NOT_FOUND, |f| {
+            (f.get_ip_interface_table)(family, table)
+        })
+    }
+
+    pub unsafe fn GetUnicastIpAddressTable(
+        family: ADDRESS_FAMILY,
+        table: *mut *mut MIB_UNICASTIPADDRESS_TABLE,
+    ) -> WIN32_ERROR {
+        functions().map_or(ERROR_PROC_NOT_FOUND, |f| {
+            (f.get_unicast_ip_address_table)(family, table)
+        })
+    }
+
+    pub unsafe fn GetIfEntry2(row: *mut MIB_IF_ROW2) -> WIN32_ERROR {
+        functions().map_or(ERROR_PROC_NOT_FOUND, |f| (f.get_if_entry2)(row))
+    }
+
+    pub unsafe fn FreeMibTable(memory: *const c_void) {
+        if let Some(f) = functions() {
+            (f.free_mib_table)(memory);
+        }
+    }
+
+    pub unsafe fn if_indextoname(interfaceindex: u32, interfacename: &mut [u8; 256]) -> PSTR {
+        functions().map_or(PSTR(ptr::null_mut()), |f| {
+            (f.if_indextoname)(interfaceindex, PSTR(interfacename.as_mut_ptr()))
+        })
+    }
+
+    pub unsafe fn if_nametoindex(interfacename: PCSTR) -> u32 {
+        functions().map_or(0, |f| (f.if_nametoindex)(interfacename))
+    }
+
+    pub unsafe fn NotifyIpInterfaceChange(
+        family: ADDRESS_FAMILY,
+        callback: PIPINTERFACE_CHANGE_CALLBACK,
+        callercontext: Option<*const c_void>,
+        initialnotification: BOOLEAN,
+        notificationhandle: *mut HANDLE,
+    ) -> WIN32_ERROR {
+        functions().map_or(ERROR_PROC_NOT_FOUND, |f| {
+            (f.notify_ip_interface_change)(
+                family,
+                callback,
+                callercontext.unwrap_or(ptr::null()),
+                initialnotification,
+                notificationhandle,
+            )
+        })
+    }
+
+    pub unsafe fn CancelMibChangeNotify2(notificationhandle: HANDLE) -> WIN32_ERROR {
+        functions().map_or(ERROR_PROC_NOT_FOUND, |f| {
+            (f.cancel_mib_change_notify2)(notificationhandle)
+        })
+    }
+}
+
+/// A table that an IP Helper function allocates, and that needs to be freed with `FreeMibTable`.
+trait MibTable {
+    type Row;
+
+    /// Allocate the table for the given address family into `table`. IP Helper functions return
+    /// their error code directly, rather than via `GetLastError`.
+    unsafe fn get(family: ADDRESS_FAMILY, table: *mut *mut Self) -> WIN32_ERROR;
+
+    /// Return the rows of `table`.
+    unsafe fn rows<'a>(table: *const Self) -> &'a [Self::Row];
+}
+
+impl MibTable for MIB_IPINTERFACE_TABLE {
+    type Row = MIB_IPINTERFACE_ROW;
+
+    unsafe fn get(family: ADDRESS_FAMILY, table: *mut *mut Self) -> WIN32_ERROR {
+        GetIpInterfaceTable(family, table)
+    }
+
+    unsafe fn rows<'a>(table: *const Self) -> &'a [Self::Row] {
+        slice::from_raw_parts(
+            ptr::addr_of!((*table).Table).cast(),
+            (*table).NumEntries as usize,
+        )
+    }
+}
+
+impl MibTable for MIB_IPFORWARD_TABLE2 {
+    type Row = MIB_IPFORWARD_ROW2;
+
+    unsafe fn get(family: ADDRESS_FAMILY, table: *mut *mut Self) -> WIN32_ERROR {
+        GetIpForwardTable2(family, table)
+    }
+
+    unsafe fn rows<'a>(table: *const Self) -> &'a [Self::Row] {
+        slice::from_raw_parts(
+            ptr::addr_of!((*table).Table).cast(),
+            (*table).NumEntries as usize,
+        )
+    }
+}
+
+impl MibTable for MIB_UNICASTIPADDRESS_TABLE {
+    type Row = MIB_UNICASTIPADDRESS_ROW;
+
+    unsafe fn get(family: ADDRESS_FAMILY, table: *mut *mut Self) -> WIN32_ERROR {
+        GetUnicastIpAddressTable(family, table)
+    }
+
+    unsafe fn rows<'a>(table: *const Self) -> &'a [Self::Row] {
+        slice::from_raw_parts(
+            ptr::addr_of!((*table).Table).cast(),
+            (*table).NumEntries as usize,
+        )
+    }
+}
+
+struct MibTablePtr<T: MibTable>(*mut T);
+
+impl<T: MibTable> MibTablePtr<T> {
+    /// Get the table for the given address family, such as the list of all interfaces with
+    /// associated metadata or the routing table.
+    fn get(family: ADDRESS_FAMILY) -> Result<Self> {
+        let mut table = Self::default();
+        // The IP Helper function allocates memory, which MibTablePtr::drop will free.
+        let res = unsafe { T::get(family, table.mut_ptr_ptr()) };
+        if res != NO_ERROR {
+            return Err(win32_err(res));
+        }
+        Ok(table)
+    }
+
+    fn mut_ptr_ptr(&mut self) -> *mut *mut T {
         ptr::from_mut(&mut self.0)
     }
+
+    fn rows(&self) -> &[T::Row] {
+        unsafe { T::rows(self.0) }
+    }
 }
 
-impl Default for MibTablePtr {
+impl<T: MibTable> Default for MibTablePtr<T> {
     fn default() -> Self {
         Self(ptr::null_mut())
     }
 }
 
-impl Drop for MibTablePtr {
+impl<T: MibTable> Drop for MibTablePtr<T> {
     fn drop(&mut self) {
         if !self.0.is_null() {
-            // Free the memory allocated by GetIpInterfaceTable.
+            // Free the memory allocated by the IP Helper function.
             unsafe {
                 FreeMibTable(self.0.cast());
             }
@@ -53,12 +389,17 @@ impl Drop for MibTablePtr {
     }
 }
 
-pub fn interface_and_mtu_impl(remote: IpAddr) -> Result<(String, usize)> {
-    // Convert remote to Windows SOCKADDR_INET format. The SOCKADDR_INET union contains an IPv4 or
-    // an IPv6 address.
+/// Convert the remote destination of `query` to Windows `SOCKADDR_INET` format.
+fn sockaddr_inet(query: RouteQuery) -> SOCKADDR_INET {
+    to_sockaddr_inet(query.remote, query.flowinfo, query.scope_id())
+}
+
+/// Convert `ip` to Windows `SOCKADDR_INET` format, with the given IPv6 `flowinfo` and `scope_id`.
+fn to_sockaddr_inet(ip: IpAddr, flowinfo: u32, scope_id: u32) -> SOCKADDR_INET {
+    // The SOCKADDR_INET union contains an IPv4 or an IPv6 address.
     //
     // See https://learn.microsoft.com/en-us/windows/win32/api/ws2ipdef/ns-ws2ipdef-sockaddr_inet
-    let dst = match remote {
+    match ip {
         IpAddr::V4(ip) => {
             // Initialize the `SOCKADDR_IN` variant of `SOCKADDR_INET` based on `ip`.
             SOCKADDR_INET {
@@ -81,63 +422,667 @@ pub fn interface_and_mtu_impl(remote: IpAddr) -> Result<(String, usize)> {
                     sin6_addr: IN6_ADDR {
                         u: IN6_ADDR_0 { Byte: ip.octets() },
                     },
+                    sin6_flowinfo: flowinfo.to_be(),
+                    Anonymous: SOCKADDR_IN6_0 {
+                        sin6_scope_id: scope_id,
+                    },
                     ..Default::default()
                 },
             }
         }
-    };
+    }
+}
+
+/// Route queries on Windows need no socket, so there is nothing to keep open between them.
+#[derive(Debug)]
+pub struct Resolver;
+
+impl Resolver {
+    #[allow(clippy::unnecessary_wraps)] // The signature is the same as on the other platforms.
+    pub const fn new() -> Result<Self> {
+        Ok(Self)
+    }
+
+    // The signature is the same as on the other platforms.
+    #[allow(clippy::unused_self, clippy::needless_pass_by_ref_mut)]
+    pub fn interface_and_mtu(&mut self, query: RouteQuery) -> Result<Interface> {
+        interface_and_mtu_impl(query)
+    }
+}
+
+/// Like [`interface_and_mtu_impl`], but running the lookup on the blocking thread pool of the Tokio
+/// runtime, since the IP Helper functions have no asynchronous variant.
+#[cfg(feature = "tokio")]
+pub async fn interface_and_mtu_async_impl(query: RouteQuery) -> Result<Interface> {
+    tokio::task::spawn_blocking(move || interface_and_mtu_impl(query))
+        .await
+        .map_err(Error::other)?
+}
+
+pub fn interface_and_mtu_impl(query: RouteQuery) -> Result<Interface> {
+    let remote = query.remote;
+    let dst = sockaddr_inet(query);
 
     // Get the interface index of the best outbound interface towards `dst`.
+    let idx = if query.oif.is_some() || query.src.is_some() {
+        let src = query.src.map(|src| to_sockaddr_inet(src, 0, 0));
+        best_route_if_index(&dst, query.oif, src.as_ref())?
+    } else {
+        best_if_index(&dst)?
+    };
+
+    route_interface(idx, remote)
+}
+
+/// Return the interface with index `idx`, with its MTU for the address family of `remote`.
+fn route_interface(idx: u32, remote: IpAddr) -> Result<Interface> {
+    // Get a list of all interfaces with associated metadata.
+    let family = if remote.is_ipv4() { AF_INET } else { AF_INET6 };
+    let if_table = MibTablePtr::<MIB_IPINTERFACE_TABLE>::get(AF_UNSPEC)?;
+
+    // Find the local interface matching `idx`.
+    interface_row(if_table.rows(), idx, family)
+        .map_or_else(|| Err(default_err()), name_mtu)
+        .map(|(name, mtu)| {
+            let mut interface = Interface::new(name, mtu, idx);
+            add_link_info(&mut interface);
+            interface
+        })
+}
+
+/// Return the row in `rows` of the interface with index `index` for `family`. There is one row
+/// per address family, and their MTUs can differ, so only fall back to the row of another family
+/// if there is none for `family`.
+fn interface_row(
+    rows: &[MIB_IPINTERFACE_ROW],
+    index: u32,
+    family: ADDRESS_FAMILY,
+) -> Option<&MIB_IPINTERFACE_ROW> {
+    let for_index = || {
+        rows.iter()
+            .filter(move |iface| iface.InterfaceIndex == index)
+    };
+    for_index()
+        .find(|iface| iface.Family == family)
+        .or_else(|| for_index().next())
+}
+
+/// Fill in the Ethernet address, if it has a non-zero one, the flags, the kind and the link speed
+/// of `interface`. Loopback and tunnel interfaces have no Ethernet address. This is best-effort,
+/// and on failure they remain unknown.
+fn add_link_info(interface: &mut Interface) {
+    let mut row = MIB_IF_ROW2 {
+        InterfaceIndex: interface.index.0,
+        ..Default::default()
+    };
+    if unsafe { GetIfEntry2(&mut row) } != NO_ERROR {
+        return;
+    }
+    interface.mac = if row.PhysicalAddressLength == 6 {
+        <[u8; 6]>::try_from(&row.PhysicalAddress[..6])
+            .ok()
+            .filter(|mac| *mac != [0; 6])
+    } else {
+        None
+    };
+    let mut flags = InterfaceFlags::empty();
+    if row.AdminStatus == NET_IF_ADMIN_STATUS_UP {
+        flags |= InterfaceFlags::UP;
+    }
+    if row.OperStatus == IfOperStatusUp {
+        flags |= InterfaceFlags::RUNNING;
+    }
+    match row.Type {
+        IF_TYPE_SOFTWARE_LOOPBACK => flags |= InterfaceFlags::LOOPBACK,
+        IF_TYPE_PPP => flags |= InterfaceFlags::POINTOPOINT,
+        _ => (),
+    }
+    interface.flags = flags;
+    interface.kind = Some(InterfaceKind::from_if_type(row.Type));
+    // Unknown speeds are reported as `NDIS_LINK_SPEED_UNKNOWN`, i.e., all bits set.
+    interface.speed_bps =
+        Some(row.TransmitLinkSpeed).filter(|&speed| speed != 0 && speed != u64::MAX);
+}
+
+pub fn interface_and_route_mtu_impl(query: RouteQuery) -> Result<(Interface, Option<usize>)> {
+    // There are no per-route MTUs on Windows.
+    interface_and_mtu_impl(query).map(|interface| (interface, None))
+}
+
+/// Return the index of the best outbound interface towards `dst`.
+fn best_if_index(dst: &SOCKADDR_INET) -> Result<u32> {
     let mut idx = 0;
     let res = unsafe {
-        // We're now casting `&dst` to a `SOCKADDR` pointer. This is OK based on
+        // We're now casting `dst` to a `SOCKADDR` pointer. This is OK based on
         // https://learn.microsoft.com/en-us/windows/win32/winsock/sockaddr-2.
         // With that, we call `GetBestInterfaceEx` to get the interface index into `idx`.
         // See https://learn.microsoft.com/en-us/windows/win32/api/iphlpapi/nf-iphlpapi-getbestinterfaceex
         GetBestInterfaceEx(
-            ptr::from_ref(&dst).cast::<SOCKADDR>(),
+            ptr::from_ref(dst).cast::<SOCKADDR>(),
             ptr::from_mut(&mut idx),
         )
     };
-    if res != 0 {
-        return Err(Error::last_os_error());
+    // `GetBestInterfaceEx` returns its error code directly, rather than via `GetLastError`.
+    if res != NO_ERROR.0 {
+        return Err(best_if_index_err(res));
     }
+    Ok(idx)
+}
 
-    // Get a list of all interfaces with associated metadata.
-    let mut if_table = MibTablePtr::default();
-    // GetIpInterfaceTable allocates memory, which MibTablePtr::drop will free.
-    let family = if remote.is_ipv4() { AF_INET } else { AF_INET6 };
-    if unsafe { GetIpInterfaceTable(family, if_table.mut_ptr_ptr()) } != NO_ERROR {
+/// Convert a nonzero status returned by `GetBestInterfaceEx` into an error.
+fn best_if_index_err(res: u32) -> Error {
+    if res == ERROR_NETWORK_UNREACHABLE.0 || res == ERROR_HOST_UNREACHABLE.0 {
+        return no_route_err("No route to destination".to_string());
+    }
+    win32_err(WIN32_ERROR(res))
+}
+
+/// Convert a Win32 error code returned by an IP Helper function into an error.
+fn win32_err(res: WIN32_ERROR) -> Error {
+    #[allow(clippy::cast_possible_wrap)] // Win32 error codes are reported as `i32` by `std`.
+    Error::from_raw_os_error(res.0 as i32)
+}
+
+/// Return the index of the interface of the best route towards `dst`, through interface `oif` and
+/// from source address `src` if given.
+fn best_route_if_index(
+    dst: &SOCKADDR_INET,
+    oif: Option<u32>,
+    src: Option<&SOCKADDR_INET>,
+) -> Result<u32> {
+    let mut route = MIB_IPFORWARD_ROW2::default();
+    let mut source = SOCKADDR_INET::default();
+    // See https://learn.microsoft.com/en-us/windows/win32/api/netioapi/nf-netioapi-getbestroute2
+    let res = unsafe {
+        GetBestRoute2(
+            None,
+            oif.unwrap_or(0),
+            src.map(ptr::from_ref),
+            ptr::from_ref(dst),
+            0,
+            ptr::from_mut(&mut route),
+            ptr::from_mut(&mut source),
+        )
+    };
+    if let Some(oif) = oif {
+        if res != NO_ERROR || route.InterfaceIndex != oif {
+            return Err(not_reachable_via(oif));
+        }
+    } else if res != NO_ERROR {
+        return Err(best_if_index_err(res.0));
+    }
+    Ok(route.InterfaceIndex)
+}
+
+/// Return the name and MTU of the interface described by `iface`.
+fn name_mtu(iface: &MIB_IPINTERFACE_ROW) -> Result<(String, usize)> {
+    // Get the MTU.
+    let mtu: usize = iface.NlMtu.try_into().map_err(|_| default_err())?;
+    // Get the interface name.
+    let mut interfacename = [0u8; IF_MAX_STRING_SIZE as usize];
+    // if_indextoname writes into the provided buffer.
+    if unsafe { if_indextoname(iface.InterfaceIndex, &mut interfacename).is_null() } {
+        return Err(default_err());
+    }
+    // Convert the interface name to a Rust string, replacing any invalid UTF-8.
+    let name = CStr::from_bytes_until_nul(interfacename.as_ref())
+        .map_err(|_| default_err())?
+        .to_string_lossy()
+        .into_owned();
+    // We found our interface information.
+    Ok((name, mtu))
+}
+
+pub fn interface_index_impl(query: RouteQuery) -> Result<u32> {
+    best_if_index(&sockaddr_inet(query))
+}
+
+pub fn interfaces_impl() -> Result<Vec<Interface>> {
+    let mut interfaces = Vec::new();
+    for iface in MibTablePtr::<MIB_IPINTERFACE_TABLE>::get(AF_UNSPEC)?.rows() {
+        let Ok((name, mtu)) = name_mtu(iface) else {
+            continue;
+        };
+        interfaces.push(Interface::new(name, mtu, iface.InterfaceIndex));
+    }
+    // There is a row per address family, which may have different MTUs. Keep the smaller one.
+    interfaces.sort_unstable_by_key(|interface| (interface.index, interface.mtu));
+    interfaces.dedup_by_key(|interface| interface.index);
+    for interface in &mut interfaces {
+        add_link_info(interface);
+    }
+    Ok(interfaces)
+}
+
+pub fn mtu_snapshot_impl() -> Result<HashMap<u32, usize>> {
+    let mut snapshot = HashMap::new();
+    for iface in MibTablePtr::<MIB_IPINTERFACE_TABLE>::get(AF_UNSPEC)?.rows() {
+        let Ok(mtu) = usize::try_from(iface.NlMtu) else {
+            continue;
+        };
+        // There is a row per address family, which may have different MTUs.
+        snapshot
+            .entry(iface.InterfaceIndex)
+            .and_modify(|prev: &mut usize| *prev = (*prev).min(mtu))
+            .or_insert(mtu);
+    }
+    Ok(snapshot)
+}
+
+pub fn interface_and_mtu_by_index_impl(index: u32) -> Result<(String, usize)> {
+    MibTablePtr::<MIB_IPINTERFACE_TABLE>::get(AF_UNSPEC)?
+        .rows()
+        .iter()
+        .find(|iface| iface.InterfaceIndex == index)
+        .map_or_else(|| Err(default_err()), name_mtu)
+}
+
+pub fn family_mtus_impl(index: u32) -> Result<(Option<usize>, Option<usize>)> {
+    let if_table = MibTablePtr::<MIB_IPINTERFACE_TABLE>::get(AF_UNSPEC)?;
+    family_mtus(if_table.rows(), index)
+}
+
+/// Return the IPv4 and IPv6 MTUs of the interface with index `index` from its rows in `rows`.
+fn family_mtus(rows: &[MIB_IPINTERFACE_ROW], index: u32) -> Result<(Option<usize>, Option<usize>)> {
+    let mtu = |family| {
+        rows.iter()
+            .find(|iface| iface.InterfaceIndex == index && iface.Family == family)
+            .map(|iface| usize::try_from(iface.NlMtu).map_err(|_| default_err()))
+            .transpose()
+    };
+    match (mtu(AF_INET)?, mtu(AF_INET6)?) {
+        (None, None) => Err(default_err()),
+        mtus => Ok(mtus),
+    }
+}
+
+pub fn interface_stats_impl(index: u32) -> Result<InterfaceStats> {
+    let mut row = MIB_IF_ROW2 {
+        InterfaceIndex: index,
+        ..Default::default()
+    };
+    let res = unsafe { GetIfEntry2(&mut row) };
+    if res != NO_ERROR {
+        return Err(win32_err(res));
+    }
+    Ok(InterfaceStats {
+        rx_bytes: row.InOctets,
+        tx_bytes: row.OutOctets,
+        rx_packets: row.InUcastPkts.saturating_add(row.InNUcastPkts),
+        tx_packets: row.OutUcastPkts.saturating_add(row.OutNUcastPkts),
+    })
+}
+
+pub fn interface_by_index_impl(index: u32) -> Result<Interface> {
+    let (name, mtu) = interface_and_mtu_by_index_impl(index)?;
+    let mut interface = Interface::new(name, mtu, index);
+    add_link_info(&mut interface);
+    Ok(interface)
+}
+
+/// Return the index of the interface named `name`, or `None` if there is none. Unlike on other
+/// platforms, `if_nametoindex` does not report why it fails.
+pub fn interface_index_by_name(name: &CStr) -> Option<u32> {
+    // SAFETY: `name` is a NUL-terminated string that outlives the call.
+    match unsafe { if_nametoindex(PCSTR(name.as_ptr().cast())) } {
+        0 => None,
+        index => Some(index),
+    }
+}
+
+/// Return the IP address in `sa`, if it is an IPv4 or IPv6 socket address.
+fn sockaddr_inet_ip(sa: &SOCKADDR_INET) -> Option<IpAddr> {
+    unsafe {
+        match sa.si_family {
+            AF_INET => Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(
+                sa.Ipv4.sin_addr.S_un.S_addr,
+            )))),
+            AF_INET6 => Some(IpAddr::V6(Ipv6Addr::from(sa.Ipv6.sin6_addr.u.Byte))),
+            _ => None,
+        }
+    }
+}
+
+pub fn local_addrs_impl() -> Result<Vec<(IpAddr, u32)>> {
+    Ok(MibTablePtr::<MIB_UNICASTIPADDRESS_TABLE>::get(AF_UNSPEC)?
+        .rows()
+        .iter()
+        .filter_map(|row| Some((sockaddr_inet_ip(&row.Address)?, row.InterfaceIndex)))
+        .collect())
+}
+
+/// Return the IP address of the peer of `socket`, or its local address if `peer` is false.
+pub fn socket_ip(socket: BorrowedSocket<'_>, peer: bool) -> Result<IpAddr> {
+    let mut sa = SOCKADDR_INET::default();
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)] // It is 28 bytes.
+    let mut len = std::mem::size_of_val(&sa) as i32;
+    #[allow(clippy::cast_possible_truncation)] // A `SOCKET` is pointer-sized.
+    let s = SOCKET(socket.as_raw_socket() as usize);
+    let name = ptr::from_mut(&mut sa).cast::<SOCKADDR>();
+    // SAFETY: `socket` is open for the lifetime of the borrow, and `sa` has room for the `len`
+    // bytes that are written at most, which fit an IPv4 or IPv6 socket address.
+    let res = unsafe {
+        if peer {
+            getpeername(s, name, ptr::from_mut(&mut len))
+        } else {
+            getsockname(s, name, ptr::from_mut(&mut len))
+        }
+    };
+    if res == SOCKET_ERROR {
         return Err(Error::last_os_error());
     }
-    // Make a slice
-    let ifaces = unsafe {
-        slice::from_raw_parts::<MIB_IPINTERFACE_ROW>(
-            &(*if_table.0).Table[0],
-            (*if_table.0).NumEntries as usize,
+    sockaddr_inet_ip(&sa).ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Not an IP socket"))
+}
+
+pub fn routes_impl() -> Result<Vec<RouteEntry>> {
+    Ok(MibTablePtr::<MIB_IPFORWARD_TABLE2>::get(AF_UNSPEC)?
+        .rows()
+        .iter()
+        .filter_map(|row| {
+            Some(RouteEntry {
+                destination: sockaddr_inet_ip(&row.DestinationPrefix.Prefix)?,
+                prefix_len: row.DestinationPrefix.PrefixLength,
+                // Directly reachable destinations have an unspecified next hop.
+                gateway: sockaddr_inet_ip(&row.NextHop).filter(|ip| !ip.is_unspecified()),
+                oif_index: InterfaceIndex(row.InterfaceIndex),
+                // There are no per-route MTUs on Windows.
+                mtu: None,
+            })
+        })
+        .collect())
+}
+
+pub fn is_on_link_impl(query: RouteQuery) -> Result<bool> {
+    best_route(query).map(|(route, _)| next_hop(&route).is_none())
+}
+
+/// Return the next hop of `route`, unless the destination is on-link.
+fn next_hop(route: &MIB_IPFORWARD_ROW2) -> Option<IpAddr> {
+    // Directly reachable destinations have an unspecified next hop.
+    sockaddr_inet_ip(&route.NextHop).filter(|ip| !ip.is_unspecified())
+}
+
+pub fn route_details_impl(query: RouteQuery) -> Result<RouteDetails> {
+    let (route, source) = best_route(query)?;
+    let gateway = next_hop(&route);
+    Ok(RouteDetails {
+        interface: route_interface(route.InterfaceIndex, query.remote)?,
+        // The interface type is not looked up on Windows, so there is no fallback MTU.
+        interface_type: None,
+        // There are no per-route MTUs on Windows.
+        route_mtu: None,
+        on_link: gateway.is_none(),
+        gateway,
+        source: sockaddr_inet_ip(&source),
+        // Windows does not expose the peer address of point-to-point interfaces.
+        peer: None,
+    })
+}
+
+pub fn route_prefix_len_impl(query: RouteQuery) -> Result<Option<u8>> {
+    let (route, _) = best_route(query)?;
+    Ok(Some(route.DestinationPrefix.PrefixLength))
+}
+
+/// Return the best route towards `query.remote` and the source address to use with it.
+fn best_route(query: RouteQuery) -> Result<(MIB_IPFORWARD_ROW2, SOCKADDR_INET)> {
+    let dst = sockaddr_inet(query);
+    let mut route = MIB_IPFORWARD_ROW2::default();
+    let mut source = SOCKADDR_INET::default();
+    // See https://learn.microsoft.com/en-us/windows/win32/api/netioapi/nf-netioapi-getbestroute2
+    let res = unsafe {
+        GetBestRoute2(
+            None,
+            0,
+            None,
+            ptr::from_ref(&dst),
+            0,
+            ptr::from_mut(&mut route),
+            ptr::from_mut(&mut source),
+        )
+    };
+    if res != NO_ERROR {
+        return Err(best_if_index_err(res.0));
+    }
+    Ok((route, source))
+}
+
+pub fn is_route_ambiguous_impl(query: RouteQuery) -> Result<bool> {
+    // `GetBestInterfaceEx` only reports the single best interface.
+    interface_and_mtu_impl(query).map(|_| false)
+}
+
+/// A registration for interface change notifications, whose callback passes the indices of the
+/// changed interfaces to `recv` through a channel.
+#[derive(Debug)]
+pub struct Watcher {
+    handle: HANDLE,
+    changes: Receiver<u32>,
+    /// The sending end of `changes`, which the callback gets a pointer to, and which therefore
+    /// needs to stay in place until the registration is cancelled.
+    _sender: Box<Sender<u32>>,
+}
+
+impl Watcher {
+    pub fn new() -> Result<Self> {
+        let (sender, changes) = mpsc::channel();
+        let sender = Box::new(sender);
+        let mut handle = HANDLE::default();
+        let res = unsafe {
+            NotifyIpInterfaceChange(
+                AF_UNSPEC,
+                Some(interface_changed),
+                Some(ptr::from_ref::<Sender<u32>>(&sender).cast()),
+                BOOLEAN(0),
+                ptr::from_mut(&mut handle),
+            )
+        };
+        if res != NO_ERROR {
+            return Err(win32_err(res));
+        }
+        Ok(Self {
+            handle,
+            changes,
+            _sender: sender,
+        })
+    }
+
+    #[allow(clippy::needless_pass_by_ref_mut)] // The signature is the same as on the other platforms.
+    pub fn recv(&mut self) -> Result<ChangeEvent> {
+        let index = self.changes.recv().map_err(Error::other)?;
+        Ok(Self::event(index))
+    }
+
+    #[allow(clippy::needless_pass_by_ref_mut)] // The signature is the same as on the other platforms.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<ChangeEvent>> {
+        match self.changes.recv_timeout(timeout) {
+            Ok(index) => Ok(Some(Self::event(index))),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(e) => Err(Error::other(e)),
+        }
+    }
+
+    /// Return the change event for the interface with index `index`.
+    fn event(index: u32) -> ChangeEvent {
+        // The callback only gets the index and family of the interface, so look up its MTU.
+        ChangeEvent {
+            index: Some(InterfaceIndex(index)),
+            mtu: interface_and_mtu_by_index_impl(index)
+                .ok()
+                .map(|(_, mtu)| mtu),
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        // This waits for running callbacks to return, so that the sender can be dropped after it.
+        // There is nothing left to do if cancelling fails.
+        unsafe {
+            _ = CancelMibChangeNotify2(self.handle);
+        }
+    }
+}
+
+/// The callback for interface change notifications, which sends the index of the changed interface
+/// to the `Sender<u32>` that `context` points to.
+unsafe extern "system" fn interface_changed(
+    context: *const c_void,
+    row: *const MIB_IPINTERFACE_ROW,
+    _kind: MIB_NOTIFICATION_TYPE,
+) {
+    if let (Some(sender), Some(row)) = (context.cast::<Sender<u32>>().as_ref(), row.as_ref()) {
+        // The receiver is only gone while the registration is being cancelled.
+        _ = sender.send(row.InterfaceIndex);
+    }
+}
+
+pub fn change_generation_impl() -> Result<u64> {
+    // There is no interface table generation counter on Windows, so hash the relevant parts of the
+    // interface table and bump our own counter whenever the hash changes.
+    static LAST: Mutex<Option<(u64, u64)>> = Mutex::new(None);
+
+    let if_table = MibTablePtr::<MIB_IPINTERFACE_TABLE>::get(AF_UNSPEC)?;
+    let mut hasher = DefaultHasher::new();
+    for iface in if_table.rows() {
+        (
+            iface.InterfaceIndex,
+            iface.Family.0,
+            iface.NlMtu,
+            iface.Connected.0,
         )
+            .hash(&mut hasher);
+    }
+    let hash = hasher.finish();
+
+    let mut last = LAST.lock().map_err(|e| Error::other(e.to_string()))?;
+    let generation = match *last {
+        Some((last_hash, generation)) if last_hash == hash => generation,
+        Some((_, generation)) => generation + 1,
+        None => 0,
     };
+    *last = Some((hash, generation));
+    drop(last);
+    Ok(generation)
+}
 
-    // Find the local interface matching `idx`.
-    for iface in ifaces {
-        if iface.InterfaceIndex == idx {
-            // Get the MTU.
-            let mtu: usize = iface.NlMtu.try_into().map_err(|_| default_err())?;
-            // Get the interface name.
-            let mut interfacename = [0u8; IF_MAX_STRING_SIZE as usize];
-            // if_indextoname writes into the provided buffer.
-            if unsafe { if_indextoname(iface.InterfaceIndex, &mut interfacename).is_null() } {
-                return Err(default_err());
-            }
-            // Convert the interface name to a Rust string.
-            let name = CStr::from_bytes_until_nul(interfacename.as_ref())
-                .map_err(|_| default_err())?
-                .to_str()
-                .map_err(|err| Error::new(ErrorKind::Other, err))?
-                .to_string();
-            // We found our interface information.
-            return Ok((name, mtu));
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "dynamic-link")]
+    use std::net::Ipv4Addr;
+    use std::{
+        io::ErrorKind,
+        net::{IpAddr, Ipv6Addr},
+    };
+
+    use windows::Win32::{
+        Foundation::{
+            SetLastError, ERROR_ACCESS_DENIED, ERROR_HOST_UNREACHABLE, ERROR_INVALID_PARAMETER,
+            ERROR_NETWORK_UNREACHABLE,
+        },
+        NetworkManagement::IpHelper::MIB_IPINTERFACE_ROW,
+        Networking::WinSock::{ADDRESS_FAMILY, AF_INET, AF_INET6},
+    };
+
+    #[cfg(feature = "dynamic-link")]
+    use super::iphlpapi;
+    use super::{
+        best_if_index_err, family_mtus, family_mtus_impl, interface_and_mtu_impl, interface_row,
+        MibTablePtr, Watcher, MIB_IPINTERFACE_TABLE,
+    };
+
+    #[test]
+    fn unreachable_is_no_route() {
+        for res in [ERROR_NETWORK_UNREACHABLE, ERROR_HOST_UNREACHABLE] {
+            let err = best_if_index_err(res.0);
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+            assert_eq!(err.to_string(), "No route to destination");
         }
     }
-    Err(default_err())
+
+    #[test]
+    fn other_status_is_returned_code() {
+        let err = best_if_index_err(ERROR_INVALID_PARAMETER.0);
+        assert_eq!(
+            err.raw_os_error(),
+            Some(ERROR_INVALID_PARAMETER.0.try_into().unwrap())
+        );
+    }
+
+    #[test]
+    fn interface_table_error_is_returned_code() {
+        // Make sure a stale `GetLastError` value is not what gets reported.
+        unsafe {
+            SetLastError(ERROR_ACCESS_DENIED);
+        }
+        let Err(err) = MibTablePtr::<MIB_IPINTERFACE_TABLE>::get(ADDRESS_FAMILY(u16::MAX)) else {
+            panic!("GetIpInterfaceTable accepted an invalid address family");
+        };
+        assert_eq!(
+            err.raw_os_error(),
+            Some(ERROR_INVALID_PARAMETER.0.try_into().unwrap())
+        );
+    }
+
+    #[test]
+    fn family_mtus_match_family() {
+        let row = |family, index, mtu| MIB_IPINTERFACE_ROW {
+            Family: family,
+            InterfaceIndex: index,
+            NlMtu: mtu,
+            ..Default::default()
+        };
+        let rows = [
+            row(AF_INET6, 1, 1280),
+            row(AF_INET, 2, 9000),
+            row(AF_INET, 1, 1500),
+            row(AF_INET6, 3, 1400),
+        ];
+        assert_eq!(family_mtus(&rows, 1).unwrap(), (Some(1500), Some(1280)));
+        assert_eq!(family_mtus(&rows, 2).unwrap(), (Some(9000), None));
+        assert_eq!(family_mtus(&rows, 3).unwrap(), (None, Some(1400)));
+        assert!(family_mtus(&rows, 4).is_err());
+    }
+
+    #[test]
+    fn interface_row_matches_family() {
+        let row = |family, index, mtu| MIB_IPINTERFACE_ROW {
+            Family: family,
+            InterfaceIndex: index,
+            NlMtu: mtu,
+            ..Default::default()
+        };
+        let rows = [
+            row(AF_INET, 1, 1500),
+            row(AF_INET6, 1, 1280),
+            row(AF_INET, 2, 9000),
+        ];
+        let mtu = |index, family| interface_row(&rows, index, family).map(|iface| iface.NlMtu);
+        assert_eq!(mtu(1, AF_INET), Some(1500));
+        assert_eq!(mtu(1, AF_INET6), Some(1280));
+        // Without a row for the family, fall back to the row of another one.
+        assert_eq!(mtu(2, AF_INET6), Some(9000));
+        assert_eq!(mtu(3, AF_INET), None);
+    }
+
+    #[test]
+    fn watcher_registers() {
+        // Dropping the watcher cancels the registration.
+        drop(Watcher::new().unwrap());
+    }
+
+    #[test]
+    fn loopback_v6_uses_v6_row() {
+        let interface = interface_and_mtu_impl(IpAddr::V6(Ipv6Addr::LOCALHOST).into()).unwrap();
+        let (_, inet6) = family_mtus_impl(interface.index.get()).unwrap();
+        assert_eq!(Some(interface.mtu), inet6);
+    }
+
+    #[test]
+    #[cfg(feature = "dynamic-link")]
+    fn dynamic_link_resolves() {
+        assert!(iphlpapi::functions().is_some());
+        let interface = interface_and_mtu_impl(IpAddr::V4(Ipv4Addr::LOCALHOST).into()).unwrap();
+        assert_eq!(interface.name, "loopback_0");
+    }
 }
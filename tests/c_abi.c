@@ -0,0 +1,54 @@
+/* Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms. */
+
+/*
+ * Calls the `c_abi` entry point through `include/mtu.h`, as a C program would. CI links this
+ * against the crate built as a static library with the `c_abi` feature and runs it.
+ */
+
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/socket.h>
+
+#include "mtu.h"
+
+static int failures = 0;
+
+static void check(int ok, const char *what) {
+    if (!ok) {
+        fprintf(stderr, "FAILED: %s\n", what);
+        failures++;
+    }
+}
+
+static void check_loopback(int af, const unsigned char *addr, const char *what) {
+    char name[64];
+    size_t mtu = 0;
+    int res = mtu_interface_and_mtu(af, addr, name, sizeof(name), &mtu);
+    check(res == 0, what);
+    if (res == 0) {
+        check(strlen(name) > 0, "loopback interface has a name");
+        check(mtu > 0, "loopback interface has an MTU");
+        printf("%s: %s, MTU %zu\n", what, name, mtu);
+    }
+}
+
+int main(void) {
+    static const unsigned char v4[4] = {127, 0, 0, 1};
+    static const unsigned char v6[16] = {0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1};
+    char name[1];
+
+    check_loopback(AF_INET, v4, "IPv4 loopback");
+    check_loopback(AF_INET6, v6, "IPv6 loopback");
+    check(mtu_interface_and_mtu(AF_INET, v4, NULL, 0, NULL) == 0, "NULL outputs are ignored");
+    check(mtu_interface_and_mtu(-1, v4, NULL, 0, NULL) == EINVAL, "unknown family is EINVAL");
+    check(mtu_interface_and_mtu(AF_INET, NULL, NULL, 0, NULL) == EINVAL, "NULL addr is EINVAL");
+    check(mtu_interface_and_mtu(AF_INET, v4, name, sizeof(name), NULL) == ERANGE,
+          "short name buffer is ERANGE");
+
+    return failures == 0 ? 0 : 1;
+}
@@ -0,0 +1,20 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Check that the `parse` module builds without `std`.
+//!
+//! CI also builds this file as a library for a bare-metal target with the `core-only` feature,
+//! where `std` does not exist.
+
+#![no_std]
+#![allow(missing_docs)]
+
+extern crate alloc;
+
+// Which helpers are compiled, and used by the tests in the module, depends on the target.
+#[allow(dead_code)]
+#[path = "../src/parse.rs"]
+mod parse;